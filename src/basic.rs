@@ -1,7 +1,28 @@
 mod board;
+mod coord;
+mod diagram;
+mod estimate;
+mod format;
 mod game;
+mod info;
+mod note;
+mod render;
+mod replay;
 mod rule;
+mod sgf;
+mod svg;
+mod symmetry;
 
 pub use board::*;
+pub use coord::*;
+pub use diagram::*;
+pub use estimate::*;
+pub use format::*;
 pub use game::*;
+pub use info::*;
+pub use note::*;
+pub use render::*;
+pub use replay::*;
 pub use rule::*;
+pub use sgf::*;
+pub use symmetry::*;
@@ -18,20 +18,32 @@ impl Stone {
 impl std::fmt::Display for Stone {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            Stone::Empty => write!(f, "{}", "."),
-            Stone::Black => write!(f, "{}", "x"),
-            Stone::White => write!(f, "{}", "o"),
+            Stone::Empty => write!(f, "."),
+            Stone::Black => write!(f, "x"),
+            Stone::White => write!(f, "o"),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum BoardSize {
     Normal,
     Medium,
     Small,
 }
 
+impl BoardSize {
+    // count of lines on each side
+    pub fn lines(&self) -> usize {
+        match self {
+            BoardSize::Normal => 19,
+            BoardSize::Medium => 13,
+            BoardSize::Small => 9,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct Board {
     coord: [Stone; 19 * 19],
     size: usize,
@@ -40,17 +52,17 @@ pub struct Board {
 impl Board {
     // new board with size
     pub fn new(size: BoardSize) -> Board {
-        let i = match size {
-            BoardSize::Normal => 19,
-            BoardSize::Medium => 13,
-            BoardSize::Small => 9,
-        };
         Board {
             coord: [Stone::Empty; 19 * 19],
-            size: i,
+            size: size.lines(),
         }
     }
 
+    // count of lines on each side
+    pub fn lines(&self) -> usize {
+        self.size
+    }
+
     pub fn size(&self) -> BoardSize {
         match self.size {
             9 => BoardSize::Small,
@@ -64,9 +76,17 @@ impl Board {
         Ok(self.coord[i] == t)
     }
 
+    // count of the stone on board
+    pub fn count(&self, t: Stone) -> usize {
+        self.coord[..self.size * self.size]
+            .iter()
+            .filter(|s| **s == t)
+            .count()
+    }
+
     pub fn at(&self, x: usize, y: usize) -> Result<Stone, String> {
         let i = self.point_to_index(x, y)?;
-        Ok(self.coord[i].clone())
+        Ok(self.coord[i])
     }
 
     // add a stone to the point
@@ -83,28 +103,17 @@ impl Board {
     }
 
     // point (x, y) is star position
-    fn is_star_position(&self, x: usize, y: usize) -> Result<bool, String> {
+    pub fn is_star_position(&self, x: usize, y: usize) -> Result<bool, String> {
         let _ = self.point_to_index(x, y)?;
         match self.size() {
             BoardSize::Normal => {
-                return Ok((x == 4 || x == 10 || x == 16) && (y == 4 || y == 10 || y == 16));
+                Ok((x == 4 || x == 10 || x == 16) && (y == 4 || y == 10 || y == 16))
             }
             BoardSize::Medium => Ok((x == 4 || x == 7 || x == 10) && (y == 4 || y == 7 || y == 10)),
             BoardSize::Small => Ok((x == 3 || x == 5 || x == 7) && (y == 3 || y == 5 || y == 7)),
         }
     }
 
-    // change index to point
-    fn index_to_point(&self, i: usize) -> Result<(usize, usize), String> {
-        if i >= self.size * self.size {
-            return Err(format!(
-                "index {} is too large for current board size {}",
-                i, self.size,
-            ));
-        }
-        Ok((i % self.size + 1, i / self.size + 1))
-    }
-
     // change point to index
     fn point_to_index(&self, x: usize, y: usize) -> Result<usize, String> {
         if x < 1 || x > self.size || y < 1 || y > self.size {
@@ -119,54 +128,21 @@ impl Board {
 
 impl std::fmt::Display for Board {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "  ")?;
-        for i in 0..self.size {
-            write!(f, "{} ", ('a' as u8 + i as u8) as char)?;
-        }
-
-        for i in 0..self.size * self.size {
-            let v = self.coord[i];
-            let (x, y) = self.index_to_point(i).unwrap();
-            if x == 1 {
-                write!(f, "{}", "\n")?;
-                write!(f, "{} ", ('a' as u8 + (y - 1) as u8) as char)?;
-            }
-            if self.is_star_position(x, y).unwrap() {
-                match v {
-                    Stone::Empty => {
-                        write!(f, "{}", "。")?;
-                    }
-                    _other => {
-                        write!(f, "{} ", v)?;
-                    }
-                }
-            } else {
-                write!(f, "{} ", v)?;
-            }
-            if x == self.size {
-                write!(f, "{}", ('a' as u8 + (y - 1) as u8) as char)?;
-            }
-        }
-        write!(f, "{}", "\n")?;
-        write!(f, "  ")?;
-        for i in 0..self.size {
-            write!(f, "{} ", ('a' as u8 + i as u8) as char)?;
-        }
-        write!(f, "{}", "\n")
+        write!(f, "{}", self.with_markup(&[]))
     }
 }
 
 // zip board data with follow rule:
 //  - 2 bit as 1 position
 //  - 0 for empty, 1 for black stone, 2 for white stone, 3 is illegal
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub enum BoardZip {
     // 19*19*2 = 361*2 = 128+128+128+128+128+128-46
-    NormalBoardZip(u128, u128, u128, u128, u128, u128),
+    Normal(u128, u128, u128, u128, u128, u128),
     // 13*13*2 = 169*2 = 128+128+128-46
-    MediumBoardZip(u128, u128, u128),
+    Medium(u128, u128, u128),
     // 9*9*2 = 81*2 = 128+64-30
-    SmallBoardZip(u128, u64),
+    Small(u128, u64),
 }
 
 pub fn zip_board(board: &Board) -> BoardZip {
@@ -178,32 +154,32 @@ pub fn zip_board(board: &Board) -> BoardZip {
 }
 
 fn zip_board_normal(board: &Board) -> BoardZip {
-    let d1 = zip_stone_128(board, 64 * 0, 64 * 1);
-    let d2 = zip_stone_128(board, 64 * 1, 64 * 2);
+    let d1 = zip_stone_128(board, 0, 64);
+    let d2 = zip_stone_128(board, 64, 64 * 2);
     let d3 = zip_stone_128(board, 64 * 2, 64 * 3);
     let d4 = zip_stone_128(board, 64 * 3, 64 * 4);
     let d5 = zip_stone_128(board, 64 * 4, 64 * 5);
     let d6 = zip_stone_128(board, 64 * 5, 361);
-    BoardZip::NormalBoardZip(d1, d2, d3, d4, d5, d6)
+    BoardZip::Normal(d1, d2, d3, d4, d5, d6)
 }
 
 fn zip_board_medium(board: &Board) -> BoardZip {
-    let d1 = zip_stone_128(board, 64 * 0, 64 * 1);
-    let d2 = zip_stone_128(board, 64 * 1, 64 * 2);
+    let d1 = zip_stone_128(board, 0, 64);
+    let d2 = zip_stone_128(board, 64, 64 * 2);
     let d3 = zip_stone_128(board, 64 * 2, 169);
-    BoardZip::MediumBoardZip(d1, d2, d3)
+    BoardZip::Medium(d1, d2, d3)
 }
 fn zip_board_small(board: &Board) -> BoardZip {
-    let d1 = zip_stone_128(board, 64 * 0, 64 * 1);
-    let d2 = zip_stone_128(board, 64 * 1, 81) as u64;
-    BoardZip::SmallBoardZip(d1, d2)
+    let d1 = zip_stone_128(board, 0, 64);
+    let d2 = zip_stone_128(board, 64, 81) as u64;
+    BoardZip::Small(d1, d2)
 }
 
 fn zip_stone_128(board: &Board, from: usize, to: usize) -> u128 {
     let mut d: u128 = 0;
     for i in from..to - 1 {
         d += zip_stone_to_val(board.coord[i]) as u128;
-        d = d << 2;
+        d <<= 2;
     }
     d += zip_stone_to_val(board.coord[to - 1]) as u128;
     d
@@ -219,18 +195,16 @@ fn zip_stone_to_val(stone: Stone) -> u8 {
 
 pub fn unzip_board(zip: &BoardZip) -> Board {
     match zip {
-        BoardZip::NormalBoardZip(d1, d2, d3, d4, d5, d6) => {
-            return unzip_board_normal(d1, d2, d3, d4, d5, d6);
-        }
-        BoardZip::MediumBoardZip(d1, d2, d3) => unzip_board_medium(d1, d2, d3),
-        BoardZip::SmallBoardZip(d1, d2) => unzip_board_small(d1, d2),
+        BoardZip::Normal(d1, d2, d3, d4, d5, d6) => unzip_board_normal(d1, d2, d3, d4, d5, d6),
+        BoardZip::Medium(d1, d2, d3) => unzip_board_medium(d1, d2, d3),
+        BoardZip::Small(d1, d2) => unzip_board_small(d1, d2),
     }
 }
 
 fn unzip_board_normal(d1: &u128, d2: &u128, d3: &u128, d4: &u128, d5: &u128, d6: &u128) -> Board {
     let mut b = Board::new(BoardSize::Normal);
-    unzip_stone_128(&mut b, d1, 64 * 0, 64 * 1);
-    unzip_stone_128(&mut b, d2, 64 * 1, 64 * 2);
+    unzip_stone_128(&mut b, d1, 0, 64);
+    unzip_stone_128(&mut b, d2, 64, 64 * 2);
     unzip_stone_128(&mut b, d3, 64 * 2, 64 * 3);
     unzip_stone_128(&mut b, d4, 64 * 3, 64 * 4);
     unzip_stone_128(&mut b, d5, 64 * 4, 64 * 5);
@@ -239,23 +213,23 @@ fn unzip_board_normal(d1: &u128, d2: &u128, d3: &u128, d4: &u128, d5: &u128, d6:
 }
 fn unzip_board_medium(d1: &u128, d2: &u128, d3: &u128) -> Board {
     let mut b = Board::new(BoardSize::Medium);
-    unzip_stone_128(&mut b, d1, 64 * 0, 64 * 1);
-    unzip_stone_128(&mut b, d2, 64 * 1, 64 * 2);
+    unzip_stone_128(&mut b, d1, 0, 64);
+    unzip_stone_128(&mut b, d2, 64, 64 * 2);
     unzip_stone_128(&mut b, d3, 64 * 2, 169);
     b
 }
 fn unzip_board_small(d1: &u128, d2: &u64) -> Board {
     let mut b = Board::new(BoardSize::Small);
-    unzip_stone_128(&mut b, d1, 64 * 0, 64 * 1);
-    unzip_stone_128(&mut b, &(*d2 as u128), 64 * 1, 81);
+    unzip_stone_128(&mut b, d1, 0, 64);
+    unzip_stone_128(&mut b, &(*d2 as u128), 64, 81);
     b
 }
 
 fn unzip_stone_128(b: &mut Board, d: &u128, from: usize, to: usize) {
-    let mut r = d.clone();
+    let mut r = *d;
     for i in from..to {
         b.coord[to - i - 1 + from] = unzip_val_to_stone(r as u8 % 4);
-        r = r >> 2;
+        r >>= 2;
     }
 }
 
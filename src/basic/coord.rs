@@ -0,0 +1,178 @@
+use std::fmt;
+use std::str::FromStr;
+
+// gtp columns skip I
+const GTP_COLUMNS: &str = "ABCDEFGHJKLMNOPQRST";
+
+// ways to write a point, (x, y) is 1 based from the top left corner
+//  - Sgf: "cd", column letter then row letter from the top
+//  - Gtp: "C16", column letter without I then row number from the bottom
+//  - Numeric: "3,4", column number then row number from the top
+//  - Japanese: "17-4", column number from the right then row number from the top
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Notation {
+    Sgf,
+    Gtp,
+    Numeric,
+    Japanese,
+}
+
+impl FromStr for Notation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sgf" => Ok(Notation::Sgf),
+            "gtp" => Ok(Notation::Gtp),
+            "numeric" => Ok(Notation::Numeric),
+            "japanese" => Ok(Notation::Japanese),
+            other => Err(format!("invalid notation: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Notation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Notation::Sgf => write!(f, "sgf"),
+            Notation::Gtp => write!(f, "gtp"),
+            Notation::Numeric => write!(f, "numeric"),
+            Notation::Japanese => write!(f, "japanese"),
+        }
+    }
+}
+
+impl Notation {
+    // parse point and check it is on the board of size * size
+    pub fn parse(&self, s: &str, size: usize) -> Result<(usize, usize), String> {
+        let s = s.trim();
+        let invalid = || format!("invalid {} point: {}", self, s);
+        let out_of_board = || format!("point {} is not match current board size {}", s, size);
+        let (x, y) = match self {
+            Notation::Sgf => {
+                let arr = s.as_bytes();
+                if arr.len() != 2 || !arr[0].is_ascii_lowercase() || !arr[1].is_ascii_lowercase() {
+                    return Err(invalid());
+                }
+                ((arr[0] - b'a') as usize + 1, (arr[1] - b'a') as usize + 1)
+            }
+            Notation::Gtp => {
+                let column = s.chars().next().ok_or_else(invalid)?.to_ascii_uppercase();
+                let x = GTP_COLUMNS.find(column).ok_or_else(invalid)? + 1;
+                let row: usize = s[1..].parse().map_err(|_| invalid())?;
+                if row < 1 || row > size {
+                    return Err(out_of_board());
+                }
+                (x, size + 1 - row)
+            }
+            Notation::Numeric => {
+                let (x, y) = s.split_once(',').ok_or_else(invalid)?;
+                let x = x.trim().parse().map_err(|_| invalid())?;
+                let y = y.trim().parse().map_err(|_| invalid())?;
+                (x, y)
+            }
+            Notation::Japanese => {
+                let (x, y) = s.split_once('-').ok_or_else(invalid)?;
+                let x: usize = x.trim().parse().map_err(|_| invalid())?;
+                let y = y.trim().parse().map_err(|_| invalid())?;
+                if x < 1 || x > size {
+                    return Err(out_of_board());
+                }
+                (size + 1 - x, y)
+            }
+        };
+        check_range(x, y, size)?;
+        Ok((x, y))
+    }
+
+    pub fn format(&self, x: usize, y: usize, size: usize) -> Result<String, String> {
+        check_range(x, y, size)?;
+        Ok(self.point(x, y, size))
+    }
+
+    pub fn column_label(&self, x: usize, size: usize) -> String {
+        match self {
+            Notation::Sgf => ((b'a' + (x - 1) as u8) as char).to_string(),
+            Notation::Gtp => GTP_COLUMNS[x - 1..x].to_string(),
+            Notation::Numeric => x.to_string(),
+            Notation::Japanese => (size + 1 - x).to_string(),
+        }
+    }
+
+    pub fn row_label(&self, y: usize, size: usize) -> String {
+        match self {
+            Notation::Sgf => ((b'a' + (y - 1) as u8) as char).to_string(),
+            Notation::Gtp => (size + 1 - y).to_string(),
+            Notation::Numeric | Notation::Japanese => y.to_string(),
+        }
+    }
+
+    // like aa-ss, for prompt
+    pub fn range(&self, size: usize) -> String {
+        let (first, last) = (self.point(1, 1, size), self.point(size, size, size));
+        match self {
+            Notation::Sgf | Notation::Gtp => format!("{}-{}", first, last),
+            Notation::Numeric | Notation::Japanese => format!("{} to {}", first, last),
+        }
+    }
+
+    fn point(&self, x: usize, y: usize, size: usize) -> String {
+        match self {
+            Notation::Sgf | Notation::Gtp => {
+                format!("{}{}", self.column_label(x, size), self.row_label(y, size))
+            }
+            Notation::Numeric => format!("{},{}", x, y),
+            Notation::Japanese => format!("{}-{}", size + 1 - x, y),
+        }
+    }
+}
+
+// convert point between notations
+pub fn convert_point(s: &str, from: Notation, to: Notation, size: usize) -> Result<String, String> {
+    let (x, y) = from.parse(s, size)?;
+    to.format(x, y, size)
+}
+
+fn check_range(x: usize, y: usize, size: usize) -> Result<(), String> {
+    if x < 1 || x > size || y < 1 || y > size {
+        return Err(format!(
+            "point ({}, {}) is not match current board size {}",
+            x, y, size
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_each_notation() {
+        assert!(Notation::Sgf.parse("aa", 19).unwrap() == (1, 1));
+        assert!(Notation::Sgf.parse("ss", 19).unwrap() == (19, 19));
+        assert!(Notation::Sgf.parse("ss", 9).is_err());
+        assert!(Notation::Sgf.parse("A1", 19).is_err());
+        assert!(Notation::Gtp.parse("A1", 19).unwrap() == (1, 19));
+        assert!(Notation::Gtp.parse("t19", 19).unwrap() == (19, 1));
+        assert!(Notation::Gtp.parse("J10", 19).unwrap() == (9, 10));
+        assert!(Notation::Gtp.parse("I10", 19).is_err());
+        assert!(Notation::Gtp.parse("A10", 9).is_err());
+        assert!(Notation::Numeric.parse("3, 4", 9).unwrap() == (3, 4));
+        assert!(Notation::Numeric.parse("0,4", 9).is_err());
+        assert!(Notation::Japanese.parse("4-4", 19).unwrap() == (16, 4));
+        assert!(Notation::Japanese.parse("17-3", 19).unwrap() == (3, 3));
+        assert!(Notation::Japanese.parse("10-1", 9).is_err());
+    }
+
+    #[test]
+    fn convert_between_notations() {
+        assert!(convert_point("dd", Notation::Sgf, Notation::Gtp, 19).unwrap() == "D16");
+        assert!(convert_point("D16", Notation::Gtp, Notation::Japanese, 19).unwrap() == "16-4");
+        assert!(convert_point("16-4", Notation::Japanese, Notation::Numeric, 19).unwrap() == "4,4");
+        assert!(convert_point("4,4", Notation::Numeric, Notation::Sgf, 19).unwrap() == "dd");
+        assert!(Notation::Gtp.range(9) == "A9-J1");
+        assert!(Notation::Japanese.range(9) == "9-1 to 1-9");
+        assert!(Notation::Sgf.format(10, 1, 9).is_err());
+    }
+}
@@ -0,0 +1,373 @@
+use crate::basic::*;
+
+// text diagrams of positions
+//
+// sensei's library diagram, numbers are steps from the one in m option,
+// 0 is the 10th step:
+//
+//     $$Bcm21 title
+//     $$ +-------------------+
+//     $$ | . . . . . . . . . |
+//     $$ | . . X O 1 2 . . . |
+//     $$ | . . , . . . , . . |
+//     ...
+//     $$ +-------------------+
+//
+// igo is the latex package, which draws the board by commands like
+// \black{c3,d4} and \white[2]{e5}
+
+impl MarkedBoard<'_> {
+    pub fn to_sensei(&self, coordinates: bool) -> Result<String, String> {
+        let size = self.board.lines();
+        let first = self.numbers.iter().min_by_key(|(_, _, n)| *n);
+        let (player, start) = match first {
+            Some((x, y, n)) => (self.board.at(*x, *y)?, *n),
+            None => (Stone::Black, 1),
+        };
+        if let Some((_, _, n)) = self.numbers.iter().find(|(_, _, n)| *n >= start + 10) {
+            return Err(format!(
+                "step {} is out of 10 steps from {} in one diagram",
+                n, start
+            ));
+        }
+        let mut text = match player {
+            Stone::White => String::from("$$W"),
+            _ => String::from("$$B"),
+        };
+        if coordinates {
+            text.push('c');
+        }
+        if start != 1 {
+            text.push_str(&format!("m{}", start));
+        }
+        text.push('\n');
+        let border = format!("$$ +{}+\n", "-".repeat(size * 2 + 1));
+        text.push_str(&border);
+        for y in 1..=size {
+            text.push_str("$$ |");
+            for x in 1..=size {
+                text.push(' ');
+                text.push(self.sensei_symbol(x, y, start)?);
+            }
+            text.push_str(" |\n");
+        }
+        text.push_str(&border);
+        Ok(text)
+    }
+
+    fn sensei_symbol(&self, x: usize, y: usize, start: usize) -> Result<char, String> {
+        let stone = self.board.at(x, y)?;
+        if let Some((_, _, n)) = self.numbers.iter().find(|(nx, ny, _)| *nx == x && *ny == y) {
+            return Ok(char::from_digit(((n - start + 1) % 10) as u32, 10).unwrap());
+        }
+        let mark = self.markup.iter().find(|m| m.x == x && m.y == y);
+        let symbol = match (stone, mark.map(|m| &m.mark)) {
+            (Stone::Black, Some(Mark::Circle)) => 'B',
+            (Stone::White, Some(Mark::Circle)) => 'W',
+            (Stone::Empty, Some(Mark::Circle)) => 'C',
+            (Stone::Black, Some(Mark::Square)) => '#',
+            (Stone::White, Some(Mark::Square)) => '@',
+            (Stone::Empty, Some(Mark::Square)) => 'S',
+            (Stone::Black, Some(Mark::Triangle)) => 'Y',
+            (Stone::White, Some(Mark::Triangle)) => 'Q',
+            (Stone::Empty, Some(Mark::Triangle)) => 'T',
+            // only single lower case letters can be labels
+            (Stone::Empty, Some(Mark::Label(label)))
+                if label.len() == 1 && label.chars().all(|c| c.is_ascii_lowercase()) =>
+            {
+                label.chars().next().unwrap()
+            }
+            (Stone::Black, _) => 'X',
+            (Stone::White, _) => 'O',
+            (Stone::Empty, _) if self.board.is_star_position(x, y)? => ',',
+            (Stone::Empty, _) => '.',
+        };
+        Ok(symbol)
+    }
+
+    pub fn to_igo(&self) -> String {
+        let size = self.board.lines();
+        let point = |x: usize, y: usize| {
+            Notation::Gtp
+                .format(x, y, size)
+                .unwrap()
+                .to_ascii_lowercase()
+        };
+        let mut text = format!("\\gobansize{{{}}}\n\\cleargoban\n", size);
+        for (stone, command) in [(Stone::Black, "\\black"), (Stone::White, "\\white")] {
+            let mut points = vec![];
+            for y in 1..=size {
+                for x in 1..=size {
+                    let numbered = self.numbers.iter().any(|(nx, ny, _)| *nx == x && *ny == y);
+                    if self.board.is(x, y, stone).unwrap() && !numbered {
+                        points.push(point(x, y));
+                    }
+                }
+            }
+            if !points.is_empty() {
+                text.push_str(&format!("{}{{{}}}\n", command, points.join(",")));
+            }
+        }
+        let mut numbers = self.numbers.clone();
+        numbers.sort_by_key(|(_, _, n)| *n);
+        for (x, y, n) in numbers {
+            let command = match self.board.at(x, y).unwrap() {
+                Stone::White => "\\white",
+                _ => "\\black",
+            };
+            text.push_str(&format!("{}[{}]{{{}}}\n", command, n, point(x, y)));
+        }
+        for m in self.markup.iter() {
+            let symbol = match &m.mark {
+                Mark::Triangle => String::from("\\igotriangle"),
+                Mark::Square => String::from("\\igosquare"),
+                Mark::Circle => String::from("\\igocircle"),
+                Mark::Label(label) => escape_latex(label),
+            };
+            text.push_str(&format!(
+                "\\gobansymbol{{{}}}{{{}}}\n",
+                point(m.x, m.y),
+                symbol
+            ));
+        }
+        text.push_str("\\showfullgoban\n");
+        text
+    }
+}
+
+// label text in latex, special characters are escaped
+fn escape_latex(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+impl Game {
+    // current position, stones played in steps from..=to are numbered
+    pub fn to_sensei(
+        &self,
+        numbers: Option<(usize, usize)>,
+        coordinates: bool,
+    ) -> Result<String, String> {
+        let note = self.note();
+        self.board()
+            .with_markup(&note.markup)
+            .with_numbers(self.step_numbers_in(numbers))
+            .to_sensei(coordinates)
+    }
+
+    pub fn to_igo(&self, numbers: Option<(usize, usize)>) -> String {
+        let note = self.note();
+        self.board()
+            .with_markup(&note.markup)
+            .with_numbers(self.step_numbers_in(numbers))
+            .to_igo()
+    }
+}
+
+// position and marks of sensei's library diagram, numbered stones are
+// placed as normal stones. diagrams without some edges are placed at the
+// corner or side they show, and a 19 * 19 board is used if the size is
+// not known from both edges.
+pub fn parse_sensei(text: &str) -> Result<(Board, Vec<Markup>), String> {
+    let mut lines = text
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .enumerate();
+    let header = match lines.next() {
+        Some((_, line)) if line.starts_with("$$") => {
+            line[2..].split_whitespace().next().unwrap_or("")
+        }
+        _ => return Err(String::from("line 1: not a sensei's library diagram")),
+    };
+    let first = match header.chars().next() {
+        Some('W') => Stone::White,
+        _ => Stone::Black,
+    };
+
+    let (mut top, mut bottom) = (false, false);
+    let (mut left, mut right) = (false, false);
+    let mut rows: Vec<Vec<char>> = vec![];
+    for (n, line) in lines {
+        let at = |err: String| format!("line {}: {}", n + 1, err);
+        let line = line
+            .strip_prefix("$$")
+            .ok_or_else(|| at(String::from("expect $$")))?
+            .trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('+') || line.starts_with('-') {
+            if rows.is_empty() {
+                top = true;
+            } else {
+                bottom = true;
+            }
+            continue;
+        }
+        if bottom {
+            return Err(at(String::from("row after the bottom edge")));
+        }
+        let mut tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.first() == Some(&"|") {
+            left = true;
+            tokens.remove(0);
+        }
+        if tokens.last() == Some(&"|") {
+            right = true;
+            tokens.pop();
+        }
+        let mut row = vec![];
+        for token in tokens {
+            let mut chars = token.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => row.push(c),
+                _ => return Err(at(format!("invalid point: {}", token))),
+            }
+        }
+        if !rows.is_empty() && rows[0].len() != row.len() {
+            return Err(at(String::from("rows are not in the same length")));
+        }
+        rows.push(row);
+    }
+    if rows.is_empty() {
+        return Err(String::from("empty diagram"));
+    }
+
+    let (width, height) = (rows[0].len(), rows.len());
+    let size = match (left && right, top && bottom) {
+        (true, true) if width != height => {
+            return Err(format!("board of {} * {} is not square", width, height))
+        }
+        (true, _) => width,
+        (_, true) => height,
+        _ => 19,
+    };
+    let mut board = match size {
+        19 => Board::new(BoardSize::Normal),
+        13 => Board::new(BoardSize::Medium),
+        9 => Board::new(BoardSize::Small),
+        other => return Err(format!("invalid board size {}", other)),
+    };
+    if width > size || height > size {
+        return Err(format!("diagram is larger than board size {}", size));
+    }
+    let dx = if !left && right { size - width } else { 0 };
+    let dy = if !top && bottom { size - height } else { 0 };
+
+    let mut markup = vec![];
+    for (j, row) in rows.iter().enumerate() {
+        for (i, c) in row.iter().enumerate() {
+            let (x, y) = (i + 1 + dx, j + 1 + dy);
+            let (stone, mark) = match c {
+                'X' => (Stone::Black, None),
+                'O' => (Stone::White, None),
+                '.' | ',' | '+' => (Stone::Empty, None),
+                'B' => (Stone::Black, Some(Mark::Circle)),
+                'W' => (Stone::White, Some(Mark::Circle)),
+                'C' => (Stone::Empty, Some(Mark::Circle)),
+                '#' => (Stone::Black, Some(Mark::Square)),
+                '@' => (Stone::White, Some(Mark::Square)),
+                'S' => (Stone::Empty, Some(Mark::Square)),
+                'Y' => (Stone::Black, Some(Mark::Triangle)),
+                'Q' => (Stone::White, Some(Mark::Triangle)),
+                'T' => (Stone::Empty, Some(Mark::Triangle)),
+                'a'..='z' => (Stone::Empty, Some(Mark::Label(c.to_string()))),
+                // odd steps are played by the first player
+                '0'..='9' => match c.to_digit(10).unwrap() % 2 {
+                    1 => (first, None),
+                    _ => (first.another(), None),
+                },
+                other => return Err(format!("invalid point: {}", other)),
+            };
+            if stone != Stone::Empty {
+                board.add(stone, x, y)?;
+            }
+            if let Some(mark) = mark {
+                markup.push(Markup { mark, x, y });
+            }
+        }
+    }
+    Ok((board, markup))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sensei_round_trip() {
+        let mut g = Game::new(BoardSize::Small);
+        for step in ["cc", "gg", "cg", "gc"] {
+            g.next(Cmd::Step(step.to_string())).unwrap();
+        }
+        g.add_mark(String::from("cc"), Mark::Triangle).unwrap();
+        g.add_mark(String::from("ee"), Mark::Label(String::from("a")))
+            .unwrap();
+        let text = g.to_sensei(Some((3, 4)), true).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0] == "$$Bcm3");
+        assert!(lines[1] == "$$ +-------------------+");
+        assert!(lines[3] == "$$ | . . . . . . . . . |");
+        assert!(lines[4] == "$$ | . . Y . , . 2 . . |");
+        assert!(lines[6] == "$$ | . . , . a . , . . |");
+        assert!(lines[8] == "$$ | . . 1 . , . O . . |");
+
+        let (board, markup) = parse_sensei(&text).unwrap();
+        assert!(board.at(3, 3).unwrap() == Stone::Black);
+        assert!(board.at(7, 3).unwrap() == Stone::White);
+        assert!(board.at(3, 7).unwrap() == Stone::Black);
+        assert!(board.count(Stone::Black) == 2 && board.count(Stone::White) == 2);
+        assert!(markup.len() == 2);
+        let numbers = vec![(3, 3, 1), (7, 3, 11)];
+        assert!(g
+            .board()
+            .with_markup(&[])
+            .with_numbers(numbers)
+            .to_sensei(false)
+            .is_err());
+    }
+
+    #[test]
+    fn parse_partial_sensei() {
+        let text = "$$W\n$$ . . . . |\n$$ . O 1 . |\n$$ . . . . |\n$$ --------+";
+        let (board, _) = parse_sensei(text).unwrap();
+        assert!(board.size() == BoardSize::Normal);
+        assert!(board.at(17, 18).unwrap() == Stone::White);
+        assert!(board.at(18, 18).unwrap() == Stone::White);
+        assert!(parse_sensei("$$\n$$ | X |\n$$ | Z |").is_err());
+    }
+
+    #[test]
+    fn igo_commands() {
+        let mut g = Game::new(BoardSize::Small);
+        for step in ["cc", "gg", "cg"] {
+            g.next(Cmd::Step(step.to_string())).unwrap();
+        }
+        g.add_mark(String::from("cg"), Mark::Circle).unwrap();
+        let text = g.to_igo(Some((2, 3)));
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0] == "\\gobansize{9}");
+        assert!(lines[2] == "\\black{c7}");
+        assert!(lines[3] == "\\white[2]{g3}");
+        assert!(lines[4] == "\\black[3]{c3}");
+        assert!(lines[5] == "\\gobansymbol{c3}{\\igocircle}");
+        assert!(lines[6] == "\\showfullgoban");
+        g.add_mark(String::from("ee"), Mark::Label(String::from("50%_{a}")))
+            .unwrap();
+        let text = g.to_igo(None);
+        assert!(text.contains("\\gobansymbol{e5}{50\\%\\_\\{a\\}}\n"));
+    }
+}
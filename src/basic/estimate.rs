@@ -0,0 +1,248 @@
+use crate::basic::*;
+use crate::util::Rng;
+
+// who owns each point, from -1 for white to 1 for black
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ownership {
+    size: usize,
+    values: Vec<f64>,
+}
+
+impl Ownership {
+    pub fn at(&self, x: usize, y: usize) -> f64 {
+        self.values[(y - 1) * self.size + x - 1]
+    }
+
+    // area score of points owned more than half, positive if black leads
+    pub fn score(&self, komi: f64) -> f64 {
+        let black = self.values.iter().filter(|v| **v > 0.5).count();
+        let white = self.values.iter().filter(|v| **v < -0.5).count();
+        black as f64 - white as f64 - komi
+    }
+
+    // b and w labels on owned empty points, triangles on stones taken as dead
+    pub fn markup(&self, board: &Board) -> Vec<Markup> {
+        let mut markup = vec![];
+        for y in 1..=self.size {
+            for x in 1..=self.size {
+                let owner = self.at(x, y);
+                let mark = match board.at(x, y).unwrap() {
+                    Stone::Empty if owner > 0.5 => Mark::Label(String::from("b")),
+                    Stone::Empty if owner < -0.5 => Mark::Label(String::from("w")),
+                    Stone::Black if owner < -0.5 => Mark::Triangle,
+                    Stone::White if owner > 0.5 => Mark::Triangle,
+                    _ => continue,
+                };
+                markup.push(Markup { mark, x, y });
+            }
+        }
+        markup
+    }
+}
+
+// like B+3.5, W+0.5 or 0
+pub fn format_score(score: f64) -> String {
+    if score > 0.0 {
+        format!("B+{}", score)
+    } else if score < 0.0 {
+        format!("W+{}", -score)
+    } else {
+        String::from("0")
+    }
+}
+
+// bouzy's 5/21 algorithm, influence of stones is spread by 5 dilations
+// and then cut by 21 erosions, what is left is taken as territory
+pub fn bouzy(board: &Board) -> Ownership {
+    let size = board.lines();
+    let mut values: Vec<i32> = (0..size * size)
+        .map(|i| match board.at(i % size + 1, i / size + 1).unwrap() {
+            Stone::Black => 128,
+            Stone::White => -128,
+            Stone::Empty => 0,
+        })
+        .collect();
+    for _ in 0..5 {
+        values = dilate(&values, size);
+    }
+    for _ in 0..21 {
+        values = erode(&values, size);
+    }
+    Ownership {
+        size,
+        values: values.iter().map(|v| v.signum() as f64).collect(),
+    }
+}
+
+fn dilate(values: &[i32], size: usize) -> Vec<i32> {
+    let mut result = values.to_vec();
+    for (i, v) in values.iter().enumerate() {
+        let around: Vec<i32> = neighbours(i, size).iter().map(|n| values[*n]).collect();
+        if *v >= 0 && around.iter().all(|n| *n >= 0) {
+            result[i] += around.iter().filter(|n| **n > 0).count() as i32;
+        }
+        if *v <= 0 && around.iter().all(|n| *n <= 0) {
+            result[i] -= around.iter().filter(|n| **n < 0).count() as i32;
+        }
+    }
+    result
+}
+
+fn erode(values: &[i32], size: usize) -> Vec<i32> {
+    let mut result = values.to_vec();
+    for (i, v) in values.iter().enumerate() {
+        let around: Vec<i32> = neighbours(i, size).iter().map(|n| values[*n]).collect();
+        if *v > 0 {
+            result[i] = (v - around.iter().filter(|n| **n <= 0).count() as i32).max(0);
+        }
+        if *v < 0 {
+            result[i] = (v + around.iter().filter(|n| **n >= 0).count() as i32).min(0);
+        }
+    }
+    result
+}
+
+// indexes of points next to the index
+fn neighbours(i: usize, size: usize) -> Vec<usize> {
+    let (x, y) = (i % size, i / size);
+    let mut around = vec![];
+    if x > 0 {
+        around.push(i - 1);
+    }
+    if x + 1 < size {
+        around.push(i + 1);
+    }
+    if y > 0 {
+        around.push(i - size);
+    }
+    if y + 1 < size {
+        around.push(i + size);
+    }
+    around
+}
+
+// average area owner at the end of random games from the board, next is
+// the colour to play first. stones never fill their own eyes.
+pub fn monte_carlo(board: &Board, next: Stone, playouts: usize, rng: &mut Rng) -> Ownership {
+    let size = board.lines();
+    let mut values = vec![0.0; size * size];
+    for _ in 0..playouts {
+        let end = playout(board, next, rng);
+        for (i, value) in values.iter_mut().enumerate() {
+            *value += area_owner(&end, i);
+        }
+    }
+    Ownership {
+        size,
+        values: values.iter().map(|v| v / playouts.max(1) as f64).collect(),
+    }
+}
+
+fn playout(board: &Board, mut stone: Stone, rng: &mut Rng) -> Board {
+    let size = board.lines();
+    let mut b = board.clone();
+    let mut passes = 0;
+    // games with ko may never end
+    for _ in 0..size * size * 3 {
+        let mut candidates: Vec<usize> = (0..size * size)
+            .filter(|i| stone_at(&b, *i) == Stone::Empty && !is_eye(&b, *i, stone))
+            .collect();
+        let mut played = false;
+        while !candidates.is_empty() {
+            let i = candidates.swap_remove(rng.below(candidates.len()));
+            let (x, y) = (i % size + 1, i / size + 1);
+            let mut nb = b.clone();
+            nb.add(stone, x, y).unwrap();
+            if remove_lose_liberty_stones(&mut nb, x, y).is_ok() {
+                b = nb;
+                played = true;
+                break;
+            }
+        }
+        passes = if played { 0 } else { passes + 1 };
+        if passes == 2 {
+            break;
+        }
+        stone = stone.another();
+    }
+    b
+}
+
+fn stone_at(board: &Board, i: usize) -> Stone {
+    let size = board.lines();
+    board.at(i % size + 1, i / size + 1).unwrap()
+}
+
+// empty point surrounded by stones of the colour only
+fn is_eye(board: &Board, i: usize, stone: Stone) -> bool {
+    neighbours(i, board.lines())
+        .iter()
+        .all(|n| stone_at(board, *n) == stone)
+}
+
+fn area_owner(board: &Board, i: usize) -> f64 {
+    let owner = match stone_at(board, i) {
+        Stone::Empty => {
+            let around: Vec<Stone> = neighbours(i, board.lines())
+                .iter()
+                .map(|n| stone_at(board, *n))
+                .collect();
+            if around.iter().all(|s| *s == Stone::Black) {
+                Stone::Black
+            } else if around.iter().all(|s| *s == Stone::White) {
+                Stone::White
+            } else {
+                Stone::Empty
+            }
+        }
+        stone => stone,
+    };
+    match owner {
+        Stone::Black => 1.0,
+        Stone::White => -1.0,
+        Stone::Empty => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bouzy_walls() {
+        // black wall on the 3rd line and white wall on the 7th line
+        let mut b = Board::new(BoardSize::Small);
+        for y in 1..=9 {
+            b.add(Stone::Black, 3, y).unwrap();
+            b.add(Stone::White, 7, y).unwrap();
+        }
+        let o = bouzy(&b);
+        assert!(o.at(1, 1) == 1.0 && o.at(2, 2) == 1.0 && o.at(5, 5) == 0.0);
+        assert!(o.at(9, 9) == -1.0 && o.at(8, 5) == -1.0);
+        let markup = o.markup(&b);
+        assert!(markup.contains(&Markup {
+            mark: Mark::Label(String::from("w")),
+            x: 9,
+            y: 1
+        }));
+        assert!(format_score(o.score(6.5)) == "W+6.5");
+    }
+
+    #[test]
+    fn monte_carlo_living_group() {
+        // black owns all but 3 eyes, which white can not play
+        let mut b = Board::new(BoardSize::Small);
+        for y in 1..=9 {
+            for x in 1..=9 {
+                if (x, y) != (1, 1) && (x, y) != (9, 9) {
+                    b.add(Stone::Black, x, y).unwrap();
+                }
+            }
+        }
+        b.del(5, 5).unwrap();
+        let o = monte_carlo(&b, Stone::White, 10, &mut Rng::new(1));
+        assert!(o.at(1, 1) == 1.0 && o.at(5, 5) == 1.0);
+        assert!(o.markup(&b).len() == 3);
+        assert!(format_score(o.score(6.5)) == "B+74.5");
+    }
+}
@@ -0,0 +1,403 @@
+use crate::basic::*;
+use crate::util::{LinkedTree, LinkedTreeOperation};
+
+// native dump format:
+//
+//     mapleque/rustgo
+//     version 1
+//     size 19
+//     info <key> <value>
+//     setup <point> ...
+//     undo <rule> <taken by black> <taken by white>
+//     current <child index> ...
+//     start
+//     ; <note line>
+//     <cmd>
+//     (
+//     <cmd of variation>
+//     )
+//
+// header lines are "<key> <value>" until start, unknown keys are ignored.
+// setup is the black stones put before the first step, like handicap.
+// undo is the undo rule and the steps taken back by each player, it is
+// missing for free undo.
+// current is the path from root to the current node, the end of main line
+// is used if it is missing.
+// each node is a cmd line followed by its note lines, a node with more than
+// one child wraps each child branch with "(" and ")".
+//
+// version 0 has no version line, the board size is the second line and only
+// the current line of steps is kept.
+pub const DUMP_MAGIC: &str = "mapleque/rustgo";
+pub const DUMP_VERSION: usize = 1;
+
+impl Game {
+    pub fn to_native(&self) -> String {
+        let mut data = format!("{}\nversion {}\n", DUMP_MAGIC, DUMP_VERSION);
+        match self.board_size() {
+            BoardSize::Normal => data.push_str("size 19\n"),
+            BoardSize::Medium => data.push_str("size 13\n"),
+            BoardSize::Small => data.push_str("size 9\n"),
+        }
+        for (key, value) in self.info().fields() {
+            data.push_str(format!("info {} {}\n", key, escape(&value)).as_str());
+        }
+        let stones = self.handicap_stones();
+        if !stones.is_empty() {
+            let points: Vec<String> = stones
+                .iter()
+                .map(|(x, y)| Cmd::point_to_cmd(*x, *y))
+                .collect();
+            data.push_str(&format!("setup {}\n", points.join(" ")));
+        }
+        let (rule, black, white) = (
+            self.undo_rule(),
+            self.undos_taken(&Player::Black),
+            self.undos_taken(&Player::White),
+        );
+        if rule != UndoRule::Free || black + white > 0 {
+            data.push_str(&format!("undo {} {} {}\n", rule, black, white));
+        }
+        let path: Vec<String> = self.current_path().iter().map(|i| i.to_string()).collect();
+        data.push_str(format!("current {}\n", path.join(" ")).trim_end());
+        data.push('\n');
+        let (cmd, note) = self.root_nodes();
+        dump_node(&cmd, &note, &mut data);
+        data
+    }
+
+    // load any version of native format
+    pub fn from_native(data: &str) -> Result<Game, String> {
+        let lines: Vec<&str> = data.lines().collect();
+        if lines.first() != Some(&DUMP_MAGIC) {
+            return Err(String::from("line 1: not a rustgo dump file"));
+        }
+        match lines.get(1) {
+            Some(line) if line.starts_with("version ") => load_v1(&lines),
+            _ => load_v0(&lines),
+        }
+    }
+}
+
+fn dump_node(cmd: &LinkedTree<Cmd>, note: &LinkedTree<Note>, data: &mut String) {
+    data.push_str(format!("{}\n", cmd.val()).as_str());
+    data.push_str(&dump_note_lines(&note.val()));
+    let len = cmd.child_len();
+    for i in 0..len {
+        if len > 1 {
+            data.push_str("(\n");
+        }
+        dump_node(&cmd.child(i).unwrap(), &note.child(i).unwrap(), data);
+        if len > 1 {
+            data.push_str(")\n");
+        }
+    }
+}
+
+// note lines follow the cmd line they belong to, each one starts with "; "
+fn dump_note_lines(note: &Note) -> String {
+    let mut data = String::new();
+    if !note.comment.is_empty() {
+        data.push_str(format!("; comment {}\n", escape(&note.comment)).as_str());
+    }
+    if let Some(annotation) = note.annotation {
+        data.push_str(format!("; annotation {}\n", annotation).as_str());
+    }
+    if let Some(judgement) = note.judgement {
+        data.push_str(format!("; judgement {}\n", judgement).as_str());
+    }
+    for m in note.markup.iter() {
+        let point = Cmd::point_to_cmd(m.x, m.y);
+        match &m.mark {
+            Mark::Triangle => data.push_str(format!("; triangle {}\n", point).as_str()),
+            Mark::Square => data.push_str(format!("; square {}\n", point).as_str()),
+            Mark::Circle => data.push_str(format!("; circle {}\n", point).as_str()),
+            Mark::Label(text) => {
+                data.push_str(format!("; label {} {}\n", point, escape(text)).as_str())
+            }
+        }
+    }
+    data
+}
+
+// return false if the key of note line is unknown
+fn load_note_line(g: &mut Game, line: &str) -> Result<bool, String> {
+    let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+    match key {
+        "comment" => g.set_comment(unescape(value)),
+        "annotation" => g.set_annotation(Some(value.parse()?)),
+        "judgement" => g.set_judgement(Some(value.parse()?)),
+        "triangle" => g.add_mark(value.to_string(), Mark::Triangle)?,
+        "square" => g.add_mark(value.to_string(), Mark::Square)?,
+        "circle" => g.add_mark(value.to_string(), Mark::Circle)?,
+        "label" => {
+            let (point, text) = value.split_once(' ').unwrap_or((value, ""));
+            g.add_mark(point.to_string(), Mark::Label(unescape(text)))?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn load_cmd_line(g: &mut Game, line: &str) -> Result<(), String> {
+    match line {
+        "pass" => g.next(Cmd::Pass),
+        other => g.next(Cmd::Step(other.to_string())),
+    }
+}
+
+fn new_game_of_size(size: &str) -> Result<Game, String> {
+    match size {
+        "19" => Ok(Game::new(BoardSize::Normal)),
+        "13" => Ok(Game::new(BoardSize::Medium)),
+        "9" => Ok(Game::new(BoardSize::Small)),
+        other => Err(format!("invalid board size {}", other)),
+    }
+}
+
+fn load_v0(lines: &[&str]) -> Result<Game, String> {
+    let at = |n: usize, err: String| format!("line {}: {}", n + 1, err);
+    let size = lines
+        .get(1)
+        .ok_or_else(|| at(1, String::from("missing board size")))?;
+    let mut g = new_game_of_size(size).map_err(|err| at(1, err))?;
+    let mut started = false;
+    for (n, &line) in lines.iter().enumerate().skip(2) {
+        match line {
+            "" => {}
+            "start" if !started => started = true,
+            other if !started => match other.strip_prefix("info ") {
+                Some(info) => {
+                    let (key, value) = info.split_once(' ').unwrap_or((info, ""));
+                    g.set_info_from_file(key, value).map_err(|err| at(n, err))?;
+                }
+                None => return Err(at(n, String::from("expect start"))),
+            },
+            other => match other.strip_prefix("; ") {
+                Some(note) => {
+                    if !load_note_line(&mut g, note).map_err(|err| at(n, err))? {
+                        return Err(at(n, format!("invalid note line: {}", note)));
+                    }
+                }
+                None => load_cmd_line(&mut g, other).map_err(|err| at(n, err))?,
+            },
+        }
+    }
+    if !started {
+        return Err(at(lines.len() - 1, String::from("missing start")));
+    }
+    Ok(g)
+}
+
+fn load_v1(lines: &[&str]) -> Result<Game, String> {
+    let at = |n: usize, err: String| format!("line {}: {}", n + 1, err);
+    let mut g: Option<Game> = None;
+    let mut path: Option<Vec<usize>> = None;
+    let mut undo: Option<(UndoRule, [usize; 2])> = None;
+    let mut n = 1;
+    // header
+    loop {
+        let line = *lines
+            .get(n)
+            .ok_or_else(|| at(n, String::from("missing start")))?;
+        if line == "start" {
+            break;
+        }
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "version" => {
+                let version: usize = value
+                    .parse()
+                    .map_err(|_| at(n, format!("invalid version {}", value)))?;
+                if version > DUMP_VERSION {
+                    return Err(at(n, format!("unsupported version {}", version)));
+                }
+            }
+            "size" => g = Some(new_game_of_size(value).map_err(|err| at(n, err))?),
+            "info" => {
+                let game = g
+                    .as_mut()
+                    .ok_or_else(|| at(n, String::from("info before size")))?;
+                let (key, value) = value.split_once(' ').unwrap_or((value, ""));
+                game.set_info_from_file(key, &unescape(value))
+                    .map_err(|err| at(n, err))?;
+            }
+            "setup" => {
+                let game = g
+                    .as_mut()
+                    .ok_or_else(|| at(n, String::from("setup before size")))?;
+                let mut points = vec![];
+                for p in value.split_whitespace() {
+                    points.push(
+                        Notation::Sgf
+                            .parse(p, game.board().lines())
+                            .map_err(|err| at(n, err))?,
+                    );
+                }
+                game.set_handicap_stones(&points)
+                    .map_err(|err| at(n, err))?;
+            }
+            "undo" => {
+                let fields: Vec<&str> = value.split_whitespace().collect();
+                let invalid = || at(n, format!("invalid undo {}", value));
+                let [rule, black, white] = fields[..] else {
+                    return Err(invalid());
+                };
+                let rule = rule.parse().map_err(|err| at(n, err))?;
+                let black = black.parse().map_err(|_| invalid())?;
+                let white = white.parse().map_err(|_| invalid())?;
+                undo = Some((rule, [black, white]));
+            }
+            "current" => {
+                let mut p = vec![];
+                for i in value.split_whitespace() {
+                    let i = i
+                        .parse()
+                        .map_err(|_| at(n, format!("invalid current path {}", value)))?;
+                    p.push(i);
+                }
+                path = Some(p);
+            }
+            _ => {}
+        }
+        n += 1;
+    }
+    let mut g = g.ok_or_else(|| at(n, String::from("missing size")))?;
+
+    // body, count steps of each opened branch to go back when it is closed
+    let mut branches: Vec<usize> = vec![0];
+    for (n, &line) in lines.iter().enumerate().skip(n + 1) {
+        match line {
+            "" => {}
+            "(" => branches.push(0),
+            ")" => {
+                if branches.len() < 2 {
+                    return Err(at(n, String::from("unmatched )")));
+                }
+                for _ in 0..branches.pop().unwrap() {
+                    g.undo().map_err(|err| at(n, err))?;
+                }
+            }
+            other => match other.strip_prefix("; ") {
+                Some(note) => {
+                    load_note_line(&mut g, note).map_err(|err| at(n, err))?;
+                }
+                None => {
+                    load_cmd_line(&mut g, other).map_err(|err| at(n, err))?;
+                    *branches.last_mut().unwrap() += 1;
+                }
+            },
+        }
+    }
+    if branches.len() > 1 {
+        return Err(at(lines.len() - 1, String::from("unclosed (")));
+    }
+
+    // go to the end of main line if current path is not given
+    while g.undo().is_ok() {}
+    match path {
+        Some(path) => {
+            for i in path {
+                g.redo(i)
+                    .map_err(|err| format!("invalid current path: {}", err))?;
+            }
+        }
+        None => {
+            while !g.redo_list().is_empty() {
+                g.redo(0)?;
+            }
+        }
+    }
+    // set at last, steps of the body are taken back with free undo
+    if let Some((rule, taken)) = undo {
+        g.set_undo_rule(rule);
+        g.set_undos_taken(taken);
+    }
+    Ok(g)
+}
+
+pub(crate) fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+pub(crate) fn unescape(value: &str) -> String {
+    let mut ret = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => ret.push('\n'),
+            Some(other) => ret.push(other),
+            None => {}
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_v0_dump() {
+        let data = "mapleque/rustgo\n9\nstart\ncc\npass\ngg\n";
+        let g = Game::from_native(data).unwrap();
+        assert!(g.step_count() == 3);
+        assert!(g.board_size() == BoardSize::Small);
+        assert!(g.next_player() == Player::White);
+    }
+
+    #[test]
+    fn v1_keeps_variations_and_notes() {
+        let mut g = Game::new(BoardSize::Small);
+        g.set_info("white", "Go Seigen").unwrap();
+        g.set_comment(String::from("line 1\nline \\2"));
+        g.next(Cmd::Step(String::from("cc"))).unwrap();
+        g.next(Cmd::Step(String::from("gg"))).unwrap();
+        g.add_mark(String::from("cc"), Mark::Label(String::from("A")))
+            .unwrap();
+        g.undo().unwrap();
+        g.next(Cmd::Pass).unwrap();
+        g.set_annotation(Some(MoveAnnotation::Doubtful));
+        g.next(Cmd::Step(String::from("ee"))).unwrap();
+
+        let data = g.to_native();
+        let l = Game::from_native(&data).unwrap();
+        assert!(l.to_native() == data);
+        assert!(l.step_count() == 3);
+        assert!(l.info().white == "Go Seigen");
+        let data = data.replace("current 0 1 0", "current 0 0");
+        let l = Game::from_native(&data).unwrap();
+        assert!(l.step_count() == 2);
+        assert!(l.note().mark_at(3, 3) == Some(&Mark::Label(String::from("A"))));
+
+        // the undo rule and the steps taken back are kept
+        let mut g = l;
+        g.set_undo_rule(UndoRule::Once);
+        g.request_undo(Player::White).unwrap();
+        g.accept_undo(Player::Black).unwrap();
+        let l = Game::from_native(&g.to_native()).unwrap();
+        assert!(l.undo_rule() == UndoRule::Once && l.undos_taken(&Player::White) == 1);
+    }
+
+    #[test]
+    fn errors_with_line_number() {
+        let err = Game::from_native("mapleque/rustgo\n9\nstart\ncc\ncc\n")
+            .err()
+            .unwrap();
+        assert!(err.starts_with("line 5: "));
+        let err = Game::from_native("mapleque/rustgo\nversion 9\nsize 9\nstart\n")
+            .err()
+            .unwrap();
+        assert!(err == "line 2: unsupported version 9");
+        let err = Game::from_native("mapleque/rustgo\nversion 1\nsize 9\nstart\n(\ncc\n")
+            .err()
+            .unwrap();
+        assert!(err == "line 6: unclosed (");
+        let data = "mapleque/rustgo\nversion 1\nsize 9\nfuture key\nstart\ncc\n; future note\n";
+        assert!(Game::from_native(data).unwrap().step_count() == 1);
+    }
+}
@@ -1,22 +1,112 @@
 use crate::basic::*;
 use crate::util::{LinkedTree, LinkedTreeOperation};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 use std::time::SystemTime;
-use std::{fmt, fs};
+use std::{fmt, fs, io};
 
 pub struct Game {
     current_board: Board,
     current_player: Player,
     current_cmd: LinkedTree<Cmd>,
     current_zip_board: LinkedTree<BoardZip>,
+    current_note: LinkedTree<Note>,
+    info: GameInfo,
+    autosave: Option<String>,
+    // error of the last autosave, none when it is written
+    autosave_error: Option<String>,
+    // hash of the data last written by autosave, which is not written again
+    autosaved: u64,
+    notation: Notation,
+    style: Style,
+    undo_rule: UndoRule,
+    // the player asking to take back the last step
+    undo_request: Option<Player>,
+    // steps taken back by black and white
+    undos_taken: [usize; 2],
 }
 
-#[derive(Clone, PartialEq)]
+// how steps are taken back. with free anyone can undo any time, otherwise
+// the player who just played asks and the opponent accepts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UndoRule {
+    Free,
+    Never,
+    // once for each player in a game
+    Once,
+    Unlimited,
+}
+
+impl FromStr for UndoRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "free" => Ok(UndoRule::Free),
+            "none" => Ok(UndoRule::Never),
+            "once" => Ok(UndoRule::Once),
+            "unlimited" => Ok(UndoRule::Unlimited),
+            other => Err(format!("invalid undo rule: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for UndoRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UndoRule::Free => write!(f, "free"),
+            UndoRule::Never => write!(f, "none"),
+            UndoRule::Once => write!(f, "once"),
+            UndoRule::Unlimited => write!(f, "unlimited"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Player {
     Black,
     White,
 }
 
-#[derive(Clone, Debug)]
+impl Player {
+    pub fn stone(&self) -> Stone {
+        match self {
+            Player::Black => Stone::Black,
+            Player::White => Stone::White,
+        }
+    }
+
+    pub fn another(&self) -> Player {
+        match self {
+            Player::Black => Player::White,
+            Player::White => Player::Black,
+        }
+    }
+}
+
+impl FromStr for Player {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "black" => Ok(Player::Black),
+            "white" => Ok(Player::White),
+            other => Err(format!("invalid player: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Player {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Player::Black => write!(f, "black"),
+            Player::White => write!(f, "white"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Cmd {
     Start,
     Pass,
@@ -24,15 +114,32 @@ pub enum Cmd {
 }
 
 impl Cmd {
+    // step is always in sgf notation, board size is checked by board
     fn cmd_to_point(cmd: String) -> Result<(usize, usize), String> {
-        if cmd.len() != 2 {
-            println!("invalid cmd: {:?} with length {}", cmd, cmd.len());
-            return Err(format!("invalid cmd: {}", cmd));
+        Notation::Sgf.parse(&cmd, 19)
+    }
+
+    pub fn point_to_cmd(x: usize, y: usize) -> String {
+        format!(
+            "{}{}",
+            (b'a' + (x - 1) as u8) as char,
+            (b'a' + (y - 1) as u8) as char
+        )
+    }
+}
+
+// pass or a point in sgf notation
+impl FromStr for Cmd {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pass" => Ok(Cmd::Pass),
+            p if p.len() == 2 && p.bytes().all(|b| b.is_ascii_lowercase()) => {
+                Ok(Cmd::Step(p.to_string()))
+            }
+            other => Err(format!("invalid step: {}", other)),
         }
-        let arr = cmd.as_bytes();
-        let x = arr[0] as usize - 'a' as usize + 1;
-        let y = arr[1] as usize - 'a' as usize + 1;
-        Ok((x, y))
     }
 }
 
@@ -52,65 +159,89 @@ impl Game {
         let b = Board::new(size.clone());
         let zb = zip_board(&b);
         let zb_history = LinkedTree::new_tree(zb).ptr();
+        let note_history = LinkedTree::new_tree(Note::default());
         Game {
             current_board: b,
             current_player: Player::Black,
             current_cmd: cmd_history.ptr(),
             current_zip_board: zb_history.ptr(),
+            current_note: note_history.ptr(),
+            info: GameInfo::default(),
+            autosave: None,
+            autosave_error: None,
+            autosaved: 0,
+            notation: Notation::Sgf,
+            style: Style::default(),
+            undo_rule: UndoRule::Free,
+            undo_request: None,
+            undos_taken: [0, 0],
         }
     }
 
     pub fn load(filename: String) -> Result<Game, String> {
-        let data = fs::read_to_string(&filename).unwrap();
-        let lines: Vec<&str> = data.split('\n').collect();
-        if lines.len() < 3 {
-            return Err(format!("Err 1: invalid dump file {}", &filename));
-        }
-        if lines[0] != "mapleque/rustgo" {
-            return Err(format!("Err 2: invalid dump file {}", &filename));
-        }
-        let mut g: Game = match lines[1] {
-            "19" => Ok(Game::new(BoardSize::Normal)),
-            "13" => Ok(Game::new(BoardSize::Medium)),
-            "9" => Ok(Game::new(BoardSize::Small)),
-            _ => Err(format!("Err 3: invalid dump file {}", &filename)),
-        }?;
-        if lines[2] != "start" {
-            return Err(format!("Err 4: invalid dump file {}", &filename));
-        }
-        let lines = &lines[3..];
-
-        for &line in lines {
-            match line {
-                "pass" => g.next(Cmd::Pass)?,
-                "" => {}
-                other => g.next(Cmd::Step(other.to_string()))?,
-            }
+        let mut file = fs::File::open(&filename)
+            .map_err(|err| format!("can not read {}: {}", &filename, err))?;
+        Game::load_from(&mut file).map_err(|err| format!("invalid file {}, {}", &filename, err))
+    }
+
+    // load sgf or any version of native format
+    pub fn load_from<R: io::Read>(reader: &mut R) -> Result<Game, String> {
+        let mut data = String::new();
+        reader
+            .read_to_string(&mut data)
+            .map_err(|err| format!("can not read: {}", err))?;
+        if data.trim_start().starts_with('(') {
+            return Game::from_sgf(&data);
         }
-        Ok(g)
+        Game::from_native(&data)
+    }
+
+    // save in native format
+    pub fn save_to<W: io::Write>(&self, writer: &mut W) -> Result<(), String> {
+        writer
+            .write_all(self.to_native().as_bytes())
+            .map_err(|err| format!("can not write: {}", err))
+    }
+
+    // dump to the file, as sgf if it ends with .sgf
+    pub fn dump_to(&self, filename: &str) -> Result<(), String> {
+        let data = if filename.ends_with(".sgf") {
+            self.to_sgf()
+        } else {
+            self.to_native()
+        };
+        fs::write(filename, data).map_err(|err| format!("can not write {}: {}", filename, err))
     }
 
-    pub fn dump(&self) {
+    // dump to dump_<unix-seconds>.<ext> in current directory
+    pub fn dump(&self, ext: &str) -> Result<String, String> {
         let filename = format!(
-            "dump_{}.txt",
+            "dump_{}.{}",
             SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
                 .unwrap()
-                .as_secs()
+                .as_secs(),
+            ext
         );
-        let mut data = String::from("mapleque/rustgo\n");
-        match self.current_board.size() {
-            BoardSize::Normal => data.push_str("19\n"),
-            BoardSize::Medium => data.push_str("13\n"),
-            BoardSize::Small => data.push_str("9\n"),
-        }
-        let list = self.current_cmd.list_parents();
-        for cmd in list {
-            data.push_str(format!("{}\n", cmd).as_str());
-        }
-        fs::write(&filename, data)
-            .expect(&format!("Unable to write file: {}", &filename).to_string());
-        println!("dump to file: {}", &filename);
+        self.dump_to(&filename)?;
+        Ok(filename)
+    }
+
+    // save to the file after every change of current node, None to stop
+    pub fn set_autosave(&mut self, filename: Option<String>) {
+        self.autosave = filename;
+        self.autosave_error = None;
+        self.autosaved = 0;
+        self.autosave();
+    }
+
+    pub fn autosave_file(&self) -> Option<&str> {
+        self.autosave.as_deref()
+    }
+
+    // the game keeps going if autosave fails, the caller tells the user
+    pub fn last_autosave_error(&self) -> Option<&str> {
+        self.autosave_error.as_deref()
     }
 
     pub fn next(&mut self, cmd: Cmd) -> Result<(), String> {
@@ -123,17 +254,106 @@ impl Game {
         };
         self.add_cmd_history(cmd);
         self.add_board_history();
+        self.add_note_history();
+        // playing on turns the request down
+        self.undo_request = None;
+        self.autosave();
         Ok(())
     }
 
+    // take back the last step, only with free undo rule
     pub fn undo(&mut self) -> Result<(), String> {
+        if self.undo_rule != UndoRule::Free {
+            return Err(String::from(
+                "undo should be asked by the player and accepted by the opponent",
+            ));
+        }
+        self.take_back()
+    }
+
+    fn take_back(&mut self) -> Result<(), String> {
         if self.current_cmd.parent().is_none() {
-            return Err(format!("can not undo"));
+            return Err("can not undo".to_string());
         }
         self.current_cmd = self.current_cmd.parent().unwrap().ptr();
         self.current_zip_board = self.current_zip_board.parent().unwrap().ptr();
+        self.current_note = self.current_note.parent().unwrap().ptr();
         self.current_board = unzip_board(&self.current_zip_board.val());
-        self.change_player()
+        self.change_player()?;
+        self.undo_request = None;
+        self.autosave();
+        Ok(())
+    }
+
+    pub fn undo_rule(&self) -> UndoRule {
+        self.undo_rule
+    }
+
+    pub fn set_undo_rule(&mut self, rule: UndoRule) {
+        self.undo_rule = rule;
+    }
+
+    pub fn undo_request(&self) -> Option<Player> {
+        self.undo_request.clone()
+    }
+
+    // count of steps the player has taken back
+    pub fn undos_taken(&self, player: &Player) -> usize {
+        self.undos_taken[player_index(player)]
+    }
+
+    // counts of black and white, when the game is loaded from a file
+    pub(crate) fn set_undos_taken(&mut self, taken: [usize; 2]) {
+        self.undos_taken = taken;
+    }
+
+    // the player who played the last step asks to take it back
+    pub fn request_undo(&mut self, player: Player) -> Result<(), String> {
+        if self.undo_request.is_some() {
+            return Err(String::from("undo is asked already"));
+        }
+        if self.current_cmd.parent().is_none() || self.player_of_step(self.step_count()) != player {
+            return Err(format!("{} has no step to take back", player));
+        }
+        match self.undo_rule {
+            UndoRule::Never => return Err(String::from("undo is not allowed in this game")),
+            UndoRule::Once if self.undos_taken(&player) > 0 => {
+                return Err(format!("{} has taken back once", player))
+            }
+            _ => {}
+        }
+        self.undo_request = Some(player);
+        Ok(())
+    }
+
+    // the opponent takes the last step back, which is kept in the game tree
+    // with a comment of who asked and who accepted
+    pub fn accept_undo(&mut self, player: Player) -> Result<(), String> {
+        let asking = self.asking_opponent(&player)?;
+        let mut note = self.note();
+        if !note.comment.is_empty() {
+            note.comment.push('\n');
+        }
+        note.comment
+            .push_str(&format!("taken back by {}, accepted by {}", asking, player));
+        self.current_note.set_val(note);
+        self.take_back()?;
+        self.undos_taken[player_index(&asking)] += 1;
+        Ok(())
+    }
+
+    pub fn reject_undo(&mut self, player: Player) -> Result<(), String> {
+        self.asking_opponent(&player)?;
+        self.undo_request = None;
+        Ok(())
+    }
+
+    // the player who asked for undo, if it is the opponent of player
+    fn asking_opponent(&self, player: &Player) -> Result<Player, String> {
+        match &self.undo_request {
+            Some(asking) if asking != player => Ok(asking.clone()),
+            _ => Err(String::from("no undo request")),
+        }
     }
 
     pub fn redo(&mut self, index: usize) -> Result<(), String> {
@@ -142,8 +362,11 @@ impl Game {
         }
         self.current_cmd = self.current_cmd.child(index).unwrap().ptr();
         self.current_zip_board = self.current_zip_board.child(index).unwrap().ptr();
+        self.current_note = self.current_note.child(index).unwrap().ptr();
         self.current_board = unzip_board(&self.current_zip_board.val());
-        self.change_player()
+        self.change_player()?;
+        self.autosave();
+        Ok(())
     }
 
     pub fn redo_list(&self) -> Vec<Cmd> {
@@ -155,14 +378,325 @@ impl Game {
         ret
     }
 
+    pub fn board(&self) -> &Board {
+        &self.current_board
+    }
+
+    // cmds from the first step to current node
+    pub fn steps(&self) -> Vec<Cmd> {
+        let mut list = self.current_cmd.list_parents();
+        list.remove(0);
+        list
+    }
+
+    // point of the last step, None for pass or start
+    pub fn last_step(&self) -> Option<(usize, usize)> {
+        match self.current_cmd.val() {
+            Cmd::Step(p) => Cmd::cmd_to_point(p).ok(),
+            _ => None,
+        }
+    }
+
+    // positions from root to current node, the first one is empty board
+    pub fn positions(&self) -> Vec<BoardZip> {
+        self.current_zip_board.list_parents()
+    }
+
+    // count of stones captured by (black, white) until current node
+    pub fn captures(&self) -> (usize, usize) {
+        let boards: Vec<Board> = self.positions().iter().map(unzip_board).collect();
+        let (mut black, mut white) = (0, 0);
+        for (i, pair) in boards.windows(2).enumerate() {
+            if self.player_of_step(i + 1) == Player::Black {
+                black += pair[0].count(Stone::White) - pair[1].count(Stone::White);
+            } else {
+                white += pair[0].count(Stone::Black) - pair[1].count(Stone::Black);
+            }
+        }
+        (black, white)
+    }
+
     pub fn step_count(&self) -> usize {
         self.current_cmd.deepth()
     }
 
+    // player of the n-th step from 1, black plays first unless handicap
+    // stones are put
+    pub fn player_of_step(&self, n: usize) -> Player {
+        let first = if self.current_zip_board.list_parents()[0]
+            == zip_board(&Board::new(self.board_size()))
+        {
+            Player::Black
+        } else {
+            Player::White
+        };
+        if n % 2 == 1 {
+            first
+        } else {
+            first.another()
+        }
+    }
+
     pub fn next_player(&self) -> Player {
         self.current_player.clone()
     }
 
+    pub fn info(&self) -> &GameInfo {
+        &self.info
+    }
+
+    // set info field by key, see INFO_KEYS
+    pub fn set_info(&mut self, key: &str, value: &str) -> Result<(), String> {
+        self.info.set(key, value)?;
+        self.autosave();
+        Ok(())
+    }
+
+    // all fields as they are, like of another game
+    pub(crate) fn set_game_info(&mut self, info: GameInfo) {
+        self.info = info;
+        self.autosave();
+    }
+
+    // set info field of a file, an unusual value is kept with a warning in
+    // the info instead of failing
+    pub(crate) fn set_info_from_file(&mut self, key: &str, value: &str) -> Result<(), String> {
+        self.info.set_from_file(key, value).map(|_| ())
+    }
+
+    // notation of board labels and input points
+    pub fn notation(&self) -> Notation {
+        self.notation
+    }
+
+    pub fn set_notation(&mut self, notation: Notation) {
+        self.notation = notation;
+    }
+
+    pub fn style(&self) -> &Style {
+        &self.style
+    }
+
+    pub fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    // (x, y, step number) of stones on board played from the step,
+    // the latest step wins if a point is played more than once
+    pub fn step_numbers(&self, from: usize) -> Vec<(usize, usize, usize)> {
+        let mut numbers: Vec<(usize, usize, usize)> = vec![];
+        for (i, cmd) in self.steps().iter().enumerate() {
+            if let Cmd::Step(p) = cmd {
+                let (x, y) = Cmd::cmd_to_point(p.clone()).unwrap();
+                numbers.retain(|(nx, ny, _)| *nx != x || *ny != y);
+                if i + 1 >= from {
+                    numbers.push((x, y, i + 1));
+                }
+            }
+        }
+        numbers.retain(|(x, y, _)| !self.current_board.is(*x, *y, Stone::Empty).unwrap());
+        numbers
+    }
+
+    // step numbers of steps from..=to, none if range is not given
+    pub fn step_numbers_in(&self, range: Option<(usize, usize)>) -> Vec<(usize, usize, usize)> {
+        match range {
+            Some((from, to)) => self
+                .step_numbers(from)
+                .into_iter()
+                .filter(|(_, _, n)| *n <= to)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    // change point in current notation to sgf notation used by Cmd::Step
+    pub fn sgf_point(&self, point: &str) -> Result<String, String> {
+        convert_point(
+            point,
+            self.notation,
+            Notation::Sgf,
+            self.current_board.lines(),
+        )
+    }
+
+    pub fn board_size(&self) -> BoardSize {
+        self.current_board.size()
+    }
+
+    // points the next player can put a stone on by the rules
+    pub fn legal_moves(&self) -> Vec<(usize, usize)> {
+        let stone = self.current_player.stone();
+        let history = self.positions();
+        let size = self.current_board.lines();
+        let mut moves = vec![];
+        for y in 1..=size {
+            for x in 1..=size {
+                if check_if_empty(&self.current_board, x, y).is_err() {
+                    continue;
+                }
+                let mut board = self.current_board.clone();
+                board.add(stone, x, y).unwrap();
+                if remove_lose_liberty_stones(&mut board, x, y).is_ok()
+                    && !history.contains(&zip_board(&board))
+                {
+                    moves.push((x, y));
+                }
+            }
+        }
+        moves
+    }
+
+    // put handicap stones at the fixed points, white plays first then
+    pub fn set_handicap(&mut self, count: usize) -> Result<(), String> {
+        if !(2..=9).contains(&count) {
+            return Err(format!("invalid handicap: {}", count));
+        }
+        let points = handicap_points(self.board().lines(), count);
+        self.set_handicap_stones(&points)
+    }
+
+    // put black stones before the first step, which can only be done on a
+    // game without any step
+    pub fn set_handicap_stones(&mut self, points: &[(usize, usize)]) -> Result<(), String> {
+        if self.current_cmd.parent().is_some() || self.current_cmd.child_len() > 0 {
+            return Err(String::from(
+                "handicap can only be set before the first step",
+            ));
+        }
+        let mut board = Board::new(self.board_size());
+        for (x, y) in points {
+            board.add(Stone::Black, *x, *y)?;
+        }
+        self.current_zip_board.set_val(zip_board(&board));
+        self.current_board = board;
+        self.info.handicap = if points.len() > 1 { points.len() } else { 0 };
+        self.current_player = if points.is_empty() {
+            Player::Black
+        } else {
+            Player::White
+        };
+        self.autosave();
+        Ok(())
+    }
+
+    // black stones put before the first step
+    pub fn handicap_stones(&self) -> Vec<(usize, usize)> {
+        let board = unzip_board(&self.current_zip_board.list_parents()[0]);
+        let size = board.lines();
+        let mut points = vec![];
+        for y in 1..=size {
+            for x in 1..=size {
+                if board.at(x, y).unwrap() == Stone::Black {
+                    points.push((x, y));
+                }
+            }
+        }
+        points
+    }
+
+    // child index of each step from root to current node
+    pub(crate) fn current_path(&self) -> Vec<usize> {
+        let mut path = vec![];
+        let mut cmd = self.current_cmd.ptr();
+        while let Some(parent) = cmd.parent() {
+            path.push(cmd.index());
+            cmd = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    // root of the cmd and note trees
+    pub(crate) fn root_nodes(&self) -> (LinkedTree<Cmd>, LinkedTree<Note>) {
+        let mut cmd = self.current_cmd.ptr();
+        while let Some(parent) = cmd.parent() {
+            cmd = parent;
+        }
+        let mut note = self.current_note.ptr();
+        while let Some(parent) = note.parent() {
+            note = parent;
+        }
+        (cmd, note)
+    }
+
+    // note of current node
+    pub fn note(&self) -> Note {
+        self.current_note.val()
+    }
+
+    pub fn set_note(&mut self, note: Note) {
+        self.current_note.set_val(note);
+        self.autosave();
+    }
+
+    pub fn set_comment(&mut self, comment: String) {
+        let mut note = self.note();
+        note.comment = comment;
+        self.set_note(note);
+    }
+
+    pub fn set_annotation(&mut self, annotation: Option<MoveAnnotation>) {
+        let mut note = self.note();
+        note.annotation = annotation;
+        self.set_note(note);
+    }
+
+    pub fn set_judgement(&mut self, judgement: Option<Judgement>) {
+        let mut note = self.note();
+        note.judgement = judgement;
+        self.set_note(note);
+    }
+
+    // mark the point given as aa-ss
+    pub fn add_mark(&mut self, point: String, mark: Mark) -> Result<(), String> {
+        let (x, y) = Cmd::cmd_to_point(point)?;
+        self.current_board.at(x, y)?;
+        let mut note = self.note();
+        note.mark(x, y, mark);
+        self.set_note(note);
+        Ok(())
+    }
+
+    pub fn remove_mark(&mut self, point: String) -> Result<(), String> {
+        let (x, y) = Cmd::cmd_to_point(point)?;
+        self.current_board.at(x, y)?;
+        let mut note = self.note();
+        note.unmark(x, y);
+        self.set_note(note);
+        Ok(())
+    }
+
+    // written to a temporary file first, so a crash while writing leaves
+    // the last file whole. nothing is written if the game is not changed.
+    fn autosave(&mut self) {
+        let Some(filename) = &self.autosave else {
+            return;
+        };
+        let data = if filename.ends_with(".sgf") {
+            self.to_sgf()
+        } else {
+            self.to_native()
+        };
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        let hash = hasher.finish();
+        if hash == self.autosaved && self.autosave_error.is_none() {
+            return;
+        }
+        let temp = format!("{}.tmp", filename);
+        let ret = fs::write(&temp, data)
+            .and_then(|_| fs::rename(&temp, filename))
+            .map_err(|err| format!("can not write {}: {}", filename, err));
+        match ret {
+            Ok(()) => {
+                self.autosaved = hash;
+                self.autosave_error = None;
+            }
+            Err(err) => self.autosave_error = Some(err),
+        }
+    }
+
     fn add_cmd_history(&mut self, cmd: Cmd) {
         let node = self.current_cmd.add_child(cmd);
         self.current_cmd = node;
@@ -174,6 +708,11 @@ impl Game {
         self.current_zip_board = node;
     }
 
+    fn add_note_history(&mut self) {
+        let node = self.current_note.add_child(Note::default());
+        self.current_note = node;
+    }
+
     fn change_player(&mut self) -> Result<(), String> {
         match self.current_player {
             Player::Black => self.current_player = Player::White,
@@ -184,10 +723,7 @@ impl Game {
 
     fn step(&mut self, cmd: String) -> Result<(), String> {
         let (x, y) = Cmd::cmd_to_point(cmd)?;
-        let stone = match self.current_player {
-            Player::Black => Stone::Black,
-            Player::White => Stone::White,
-        };
+        let stone = self.current_player.stone();
         check_if_empty(&self.current_board, x, y)?;
         self.current_board = check_if_never_repeat_with_new_stone(
             &self.current_board,
@@ -200,9 +736,40 @@ impl Game {
     }
 }
 
+// index of the player in per player arrays, black first
+fn player_index(player: &Player) -> usize {
+    match player {
+        Player::Black => 0,
+        Player::White => 1,
+    }
+}
+
+// fixed handicap points of gtp, corners first, then sides and center
+pub fn handicap_points(size: usize, count: usize) -> Vec<(usize, usize)> {
+    let near = if size < 13 { 3 } else { 4 };
+    let far = size + 1 - near;
+    let mid = size / 2 + 1;
+    let points = [
+        (near, far),
+        (far, near),
+        (near, near),
+        (far, far),
+        (near, mid),
+        (far, mid),
+        (mid, far),
+        (mid, near),
+    ];
+    let mut handicap: Vec<(usize, usize)> = points.iter().take(count.min(8)).cloned().collect();
+    if count % 2 == 1 && count >= 5 {
+        handicap.truncate(count - 1);
+        handicap.push((mid, mid));
+    }
+    handicap
+}
+
 impl std::fmt::Display for Game {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "\n")?;
+        writeln!(f)?;
         let indent = match self.current_board.size() {
             BoardSize::Normal => "        ",
             BoardSize::Medium => "  ",
@@ -212,9 +779,36 @@ impl std::fmt::Display for Game {
             Player::Black => write!(f, "{}White(o)   [{}] > Black(x)", indent, self.step_count())?,
             Player::White => write!(f, "{}White(o) < [{}]   Black(x)", indent, self.step_count())?,
         };
-        write!(f, "\n")?;
-        write!(f, "\n")?;
-        write!(f, "{}", self.current_board)
+        writeln!(f)?;
+        writeln!(f)?;
+        let note = self.note();
+        write!(
+            f,
+            "{}",
+            self.current_board
+                .with_markup(&note.markup)
+                .with_notation(self.notation)
+                .with_theme(self.style.theme)
+                .with_last_step(if self.style.last_step {
+                    self.last_step()
+                } else {
+                    None
+                })
+                .with_numbers(match self.style.numbers_from {
+                    Some(from) => self.step_numbers(from),
+                    None => vec![],
+                })
+        )?;
+        if let Some(annotation) = note.annotation {
+            writeln!(f, "annotation: {}", annotation)?;
+        }
+        if let Some(judgement) = note.judgement {
+            writeln!(f, "judgement: {}", judgement)?;
+        }
+        if !note.comment.is_empty() {
+            writeln!(f, "comment: {}", note.comment)?;
+        }
+        Ok(())
     }
 }
 
@@ -229,6 +823,8 @@ mod tests {
         assert!(Cmd::cmd_to_point(String::from("ss")).unwrap() == (19, 19));
         assert!(Cmd::cmd_to_point(String::from("as")).unwrap() == (1, 19));
         assert!(Cmd::cmd_to_point(String::from("sa")).unwrap() == (19, 1));
+        assert!(Cmd::cmd_to_point(String::from("za")).is_err());
+        assert!(Cmd::cmd_to_point(String::from("A1")).is_err());
     }
 
     #[test]
@@ -261,6 +857,112 @@ mod tests {
         assert!(g.current_board.is(1, 1, Stone::Empty).unwrap());
         assert!(g.step_count() == 4);
         assert!(g.next_player() == Player::Black);
-        assert!(g.redo_list().len() == 0);
+        assert!(g.redo_list().is_empty());
+    }
+
+    #[test]
+    fn captures_and_steps() {
+        let mut g = Game::new(BoardSize::Small);
+        for step in ["ba", "aa", "pass", "ab", "bb"] {
+            match step {
+                "pass" => g.next(Cmd::Pass).unwrap(),
+                other => g.next(Cmd::Step(other.to_string())).unwrap(),
+            }
+        }
+        assert!(g.captures() == (0, 0));
+        assert!(g.last_step() == Some((2, 2)));
+        g.next(Cmd::Pass).unwrap();
+        assert!(g.last_step().is_none());
+        g.next(Cmd::Step("ac".to_string())).unwrap();
+        assert!(g.captures() == (2, 0));
+        assert!(g.steps().len() == 7);
+        assert!(g.steps()[2].to_string() == "pass");
+    }
+
+    #[test]
+    fn handicap_stones() {
+        let mut g = Game::new(BoardSize::Normal);
+        g.set_handicap(5).unwrap();
+        assert!(g.board().count(Stone::Black) == 5 && g.board().is(10, 10, Stone::Black).unwrap());
+        assert!(g.next_player() == Player::White && g.info().handicap == 5);
+        g.next(Cmd::Step(String::from("qc"))).unwrap();
+        assert!(g.set_handicap(2).is_err());
+        g.undo().unwrap();
+        assert!(g.next_player() == Player::White && g.handicap_stones().len() == 5);
+        assert!(handicap_points(9, 2) == vec![(3, 7), (7, 3)]);
+        assert!(g.legal_moves().len() == 19 * 19 - 5);
+        g.redo(0).unwrap();
+        for data in [g.to_sgf(), g.to_native()] {
+            let loaded = Game::load_from(&mut data.as_bytes()).unwrap();
+            assert!(loaded.board() == g.board() && loaded.handicap_stones().len() == 5);
+        }
+    }
+
+    #[test]
+    fn undo_asked_and_accepted() {
+        let mut g = Game::new(BoardSize::Small);
+        g.set_undo_rule(UndoRule::Once);
+        g.next(Cmd::Step("cc".to_string())).unwrap();
+        assert!(g.undo().is_err());
+        assert!(g.request_undo(Player::White).is_err());
+        g.request_undo(Player::Black).unwrap();
+        assert!(g.accept_undo(Player::Black).is_err());
+        g.accept_undo(Player::White).unwrap();
+        assert!(g.step_count() == 0 && g.undos_taken(&Player::Black) == 1);
+        // the step taken back is kept with the record
+        let sgf = g.to_sgf();
+        assert!(sgf.contains("B[cc]C[taken back by black, accepted by white]"));
+
+        g.next(Cmd::Step("dd".to_string())).unwrap();
+        assert!(g.request_undo(Player::Black).is_err());
+        g.next(Cmd::Step("ee".to_string())).unwrap();
+        g.request_undo(Player::White).unwrap();
+        g.reject_undo(Player::Black).unwrap();
+        assert!(g.step_count() == 2 && g.undo_request().is_none());
+        g.set_undo_rule(UndoRule::Never);
+        assert!(g.request_undo(Player::White).is_err());
+    }
+
+    #[test]
+    fn numbers_of_stones_on_board() {
+        let mut g = Game::new(BoardSize::Small);
+        for step in ["ba", "aa", "ab", "cc", "dd"] {
+            g.next(Cmd::Step(step.to_string())).unwrap();
+        }
+        // aa is captured by ab
+        assert!(g.step_numbers(1) == vec![(2, 1, 1), (1, 2, 3), (3, 3, 4), (4, 4, 5)]);
+        assert!(g.step_numbers(4) == vec![(3, 3, 4), (4, 4, 5)]);
+    }
+
+    #[test]
+    fn input_in_notation() {
+        let mut g = Game::new(BoardSize::Small);
+        g.set_notation(Notation::Gtp);
+        let p = g.sgf_point("C7").unwrap();
+        assert!(p == "cc");
+        g.next(Cmd::Step(p)).unwrap();
+        assert!(g.current_board.is(3, 3, Stone::Black).unwrap());
+        assert!(g.sgf_point("C10").is_err());
+    }
+
+    #[test]
+    fn save_and_load_with_io() {
+        let mut g = Game::new(BoardSize::Small);
+        g.next(Cmd::Step("cc".to_string())).unwrap();
+        let mut data: Vec<u8> = vec![];
+        g.save_to(&mut data).unwrap();
+        let l = Game::load_from(&mut data.as_slice()).unwrap();
+        assert!(l.step_count() == 1);
+        assert!(l.current_board.is(3, 3, Stone::Black).unwrap());
+        let l = Game::load_from(&mut g.to_sgf().as_bytes()).unwrap();
+        assert!(l.step_count() == 1);
+        assert!(Game::load_from(&mut "nothing".as_bytes()).is_err());
+
+        g.set_autosave(Some(String::from("/nonexistent/rustgo/recovery.txt")));
+        assert!(g
+            .last_autosave_error()
+            .is_some_and(|err| err.starts_with("can not write")));
+        g.set_autosave(None);
+        assert!(g.last_autosave_error().is_none());
     }
 }
@@ -0,0 +1,222 @@
+// name used in cli and dump file, and the sgf property of each field
+pub const INFO_KEYS: [(&str, &str); 15] = [
+    ("black", "PB"),
+    ("black-rank", "BR"),
+    ("white", "PW"),
+    ("white-rank", "WR"),
+    ("date", "DT"),
+    ("event", "EV"),
+    ("round", "RO"),
+    ("place", "PC"),
+    ("rules", "RU"),
+    ("komi", "KM"),
+    ("handicap", "HA"),
+    ("time", "TM"),
+    ("overtime", "OT"),
+    ("result", "RE"),
+    ("name", "GN"),
+];
+
+// game metadata, empty, none or zero fields are unknown
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameInfo {
+    pub black: String,
+    pub black_rank: String,
+    pub white: String,
+    pub white_rank: String,
+    pub date: String,
+    pub event: String,
+    pub round: String,
+    pub place: String,
+    pub rules: String,
+    pub komi: Option<f64>,
+    pub handicap: usize,
+    // main time in seconds
+    pub time: Option<f64>,
+    pub overtime: String,
+    // like B+R, W+3.5, 0 for draw
+    pub result: String,
+    pub name: String,
+    // values of a file which are not valid for their fields, they are
+    // kept as they are to be saved again
+    unusual: Vec<(&'static str, String)>,
+}
+
+impl GameInfo {
+    pub fn get(&self, key: &str) -> Result<String, String> {
+        if let Some((_, value)) = self.unusual.iter().find(|(k, _)| *k == key) {
+            return Ok(value.clone());
+        }
+        let value = match key {
+            "black" => self.black.clone(),
+            "black-rank" => self.black_rank.clone(),
+            "white" => self.white.clone(),
+            "white-rank" => self.white_rank.clone(),
+            "date" => self.date.clone(),
+            "event" => self.event.clone(),
+            "round" => self.round.clone(),
+            "place" => self.place.clone(),
+            "rules" => self.rules.clone(),
+            "komi" => self.komi.map(|k| k.to_string()).unwrap_or_default(),
+            "handicap" if self.handicap == 0 => String::new(),
+            "handicap" => self.handicap.to_string(),
+            "time" => self.time.map(|t| t.to_string()).unwrap_or_default(),
+            "overtime" => self.overtime.clone(),
+            "result" => self.result.clone(),
+            "name" => self.name.clone(),
+            other => return Err(format!("invalid info key: {}", other)),
+        };
+        Ok(value)
+    }
+
+    // set field by key, empty value to clear
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        self.unusual.retain(|(k, _)| *k != key);
+        match key {
+            "black" => self.black = value.to_string(),
+            "black-rank" => self.black_rank = value.to_string(),
+            "white" => self.white = value.to_string(),
+            "white-rank" => self.white_rank = value.to_string(),
+            "date" => self.date = value.to_string(),
+            "event" => self.event = value.to_string(),
+            "round" => self.round = value.to_string(),
+            "place" => self.place = value.to_string(),
+            "rules" => self.rules = value.to_string(),
+            "komi" => self.komi = parse_or_none(key, value)?,
+            "handicap" => {
+                let handicap = parse_or_none(key, value)?.unwrap_or(0);
+                if handicap == 1 || handicap > 9 {
+                    return Err(format!("invalid handicap: {}", value));
+                }
+                self.handicap = handicap;
+            }
+            "time" => self.time = parse_or_none(key, value)?,
+            "overtime" => self.overtime = value.to_string(),
+            "result" => {
+                if !is_valid_result(value) {
+                    return Err(format!("invalid result: {}", value));
+                }
+                self.result = value.to_string();
+            }
+            "name" => self.name = value.to_string(),
+            other => return Err(format!("invalid info key: {}", other)),
+        }
+        Ok(())
+    }
+
+    // set field of a file, a value which is not valid for the field is
+    // kept as it is, and the error is returned as a warning
+    pub fn set_from_file(&mut self, key: &str, value: &str) -> Result<Option<String>, String> {
+        let Err(err) = self.set(key, value) else {
+            return Ok(None);
+        };
+        let Some((key, _)) = INFO_KEYS.iter().find(|(k, _)| *k == key) else {
+            return Err(err);
+        };
+        match *key {
+            "result" => self.result = value.trim().to_string(),
+            _ => {
+                // the field is unknown
+                self.set(key, "")?;
+                self.unusual.push((key, value.trim().to_string()));
+            }
+        }
+        Ok(Some(err))
+    }
+
+    // fields kept by set_from_file, which are not used by the game
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = self
+            .unusual
+            .iter()
+            .map(|(key, value)| format!("unusual {}: {}", key, value))
+            .collect();
+        if !is_valid_result(&self.result) {
+            warnings.push(format!("unusual result: {}", self.result));
+        }
+        warnings
+    }
+
+    // all known fields as (key, value)
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        INFO_KEYS
+            .iter()
+            .map(|(key, _)| (*key, self.get(key).unwrap()))
+            .filter(|(_, value)| !value.is_empty())
+            .collect()
+    }
+
+    pub fn sgf_key(key: &str) -> Option<&'static str> {
+        INFO_KEYS.iter().find(|(k, _)| *k == key).map(|(_, p)| *p)
+    }
+
+    pub fn key_of_sgf(prop: &str) -> Option<&'static str> {
+        INFO_KEYS.iter().find(|(_, p)| *p == prop).map(|(k, _)| *k)
+    }
+}
+
+fn parse_or_none<T: std::str::FromStr>(key: &str, value: &str) -> Result<Option<T>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("invalid {}: {}", key, value))
+}
+
+// result in sgf style: 0, Draw, Void, ?, B+R, W+T, B+F, W+3.5, B+
+pub(crate) fn is_valid_result(value: &str) -> bool {
+    match value {
+        "" | "0" | "Draw" | "Void" | "?" => true,
+        other => match other.split_once('+') {
+            Some(("B", score)) | Some(("W", score)) => match score {
+                "" | "R" | "Resign" | "T" | "Time" | "F" | "Forfeit" => true,
+                other => other.parse::<f64>().is_ok(),
+            },
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_get() {
+        let mut info = GameInfo::default();
+        assert!(info.fields().is_empty());
+        info.set("black", "Honinbo Shusaku").unwrap();
+        info.set("komi", "6.5").unwrap();
+        info.set("handicap", "3").unwrap();
+        info.set("result", "W+3.5").unwrap();
+        assert!(info.komi == Some(6.5));
+        assert!(info.get("handicap").unwrap() == "3");
+        assert!(info.set("komi", "lots").is_err());
+        assert!(info.set("handicap", "1").is_err());
+        assert!(info.set("result", "black wins").is_err());
+        assert!(info.set("color", "red").is_err());
+        let fields = info.fields();
+        assert!(fields.len() == 4);
+        assert!(fields[0] == ("black", String::from("Honinbo Shusaku")));
+        info.set("komi", "").unwrap();
+        assert!(info.fields().len() == 3);
+        info.set("komi", "0").unwrap();
+        assert!(info.komi == Some(0.0) && info.get("komi").unwrap() == "0");
+        info.set("time", "5400.0").unwrap();
+        assert!(info.time == Some(5400.0));
+
+        // unusual values of files are kept and warned
+        assert!(info.set_from_file("handicap", "1").unwrap().is_some());
+        assert!(info.set_from_file("result", "Jigo").unwrap().is_some());
+        assert!(info.handicap == 0 && info.get("handicap").unwrap() == "1");
+        assert!(info.result == "Jigo" && info.warnings().len() == 2);
+        assert!(info.set_from_file("color", "red").is_err());
+        info.set("handicap", "2").unwrap();
+        assert!(info.warnings() == vec![String::from("unusual result: Jigo")]);
+        assert!(GameInfo::sgf_key("komi") == Some("KM"));
+        assert!(GameInfo::key_of_sgf("PW") == Some("white"));
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+use std::str::FromStr;
+
+// move annotation, know as TE, BM, DO, IT in sgf
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MoveAnnotation {
+    Good,
+    Bad,
+    Doubtful,
+    Interesting,
+}
+
+impl FromStr for MoveAnnotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "good" => Ok(MoveAnnotation::Good),
+            "bad" => Ok(MoveAnnotation::Bad),
+            "doubtful" => Ok(MoveAnnotation::Doubtful),
+            "interesting" => Ok(MoveAnnotation::Interesting),
+            other => Err(format!("invalid annotation: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for MoveAnnotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveAnnotation::Good => write!(f, "good"),
+            MoveAnnotation::Bad => write!(f, "bad"),
+            MoveAnnotation::Doubtful => write!(f, "doubtful"),
+            MoveAnnotation::Interesting => write!(f, "interesting"),
+        }
+    }
+}
+
+// position judgement, know as DM, GB, GW, UC in sgf
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Judgement {
+    Even,
+    GoodForBlack,
+    GoodForWhite,
+    Unclear,
+}
+
+impl FromStr for Judgement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "even" => Ok(Judgement::Even),
+            "black" => Ok(Judgement::GoodForBlack),
+            "white" => Ok(Judgement::GoodForWhite),
+            "unclear" => Ok(Judgement::Unclear),
+            other => Err(format!("invalid judgement: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Judgement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Judgement::Even => write!(f, "even"),
+            Judgement::GoodForBlack => write!(f, "black"),
+            Judgement::GoodForWhite => write!(f, "white"),
+            Judgement::Unclear => write!(f, "unclear"),
+        }
+    }
+}
+
+// board markup, letters and numbers are both labels
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mark {
+    Triangle,
+    Square,
+    Circle,
+    Label(String),
+}
+
+impl Mark {
+    // the symbol shown on board, at most 2 chars
+    pub fn symbol(&self) -> String {
+        match self {
+            Mark::Triangle => String::from("^"),
+            Mark::Square => String::from("#"),
+            Mark::Circle => String::from("@"),
+            Mark::Label(text) => text.chars().take(2).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Markup {
+    pub mark: Mark,
+    pub x: usize,
+    pub y: usize,
+}
+
+// everything a reviewer attached to one node of the game tree
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Note {
+    pub comment: String,
+    pub annotation: Option<MoveAnnotation>,
+    pub judgement: Option<Judgement>,
+    pub markup: Vec<Markup>,
+}
+
+impl Note {
+    pub fn is_empty(&self) -> bool {
+        self.comment.is_empty()
+            && self.annotation.is_none()
+            && self.judgement.is_none()
+            && self.markup.is_empty()
+    }
+
+    // put a mark on the point, replace the old one if exists
+    pub fn mark(&mut self, x: usize, y: usize, mark: Mark) {
+        self.unmark(x, y);
+        self.markup.push(Markup { mark, x, y });
+    }
+
+    pub fn unmark(&mut self, x: usize, y: usize) {
+        self.markup.retain(|m| m.x != x || m.y != y);
+    }
+
+    pub fn mark_at(&self, x: usize, y: usize) -> Option<&Mark> {
+        self.markup
+            .iter()
+            .find(|m| m.x == x && m.y == y)
+            .map(|m| &m.mark)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mark_replace_and_remove() {
+        let mut n = Note::default();
+        assert!(n.is_empty());
+        n.mark(1, 1, Mark::Triangle);
+        n.mark(2, 1, Mark::Label(String::from("A")));
+        assert!(n.mark_at(1, 1) == Some(&Mark::Triangle));
+        n.mark(1, 1, Mark::Circle);
+        assert!(n.markup.len() == 2);
+        assert!(n.mark_at(1, 1) == Some(&Mark::Circle));
+        n.unmark(1, 1);
+        assert!(n.mark_at(1, 1).is_none());
+        assert!(n.mark_at(2, 1).unwrap().symbol() == "A");
+        assert!(!n.is_empty());
+    }
+
+    #[test]
+    fn parse_names() {
+        assert!("good".parse::<MoveAnnotation>().unwrap() == MoveAnnotation::Good);
+        assert!("black".parse::<Judgement>().unwrap() == Judgement::GoodForBlack);
+        assert!("nice".parse::<MoveAnnotation>().is_err());
+        assert!(Judgement::Unclear.to_string() == "unclear");
+    }
+}
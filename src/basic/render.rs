@@ -0,0 +1,307 @@
+use crate::basic::{Board, Markup, Notation, Stone};
+use std::fmt;
+use std::str::FromStr;
+
+const COLOR_BOARD: &str = "\x1b[48;5;179m";
+const COLOR_GRID: &str = "\x1b[38;5;94m";
+const COLOR_BLACK: &str = "\x1b[30m";
+const COLOR_WHITE: &str = "\x1b[97m";
+const COLOR_RESET: &str = "\x1b[0m";
+
+// how points are drawn, every point is 2 chars wide
+//  - Classic: x, o, . and full width 。 for star points
+//  - Ascii: x, o, . and + for star points
+//  - Unicode: box drawing grid with ● and ○
+//  - Color: unicode with ansi colours on a wooden background
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Theme {
+    Classic,
+    Ascii,
+    Unicode,
+    Color,
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(Theme::Classic),
+            "ascii" => Ok(Theme::Ascii),
+            "unicode" => Ok(Theme::Unicode),
+            "color" => Ok(Theme::Color),
+            other => Err(format!("invalid theme: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Theme::Classic => write!(f, "classic"),
+            Theme::Ascii => write!(f, "ascii"),
+            Theme::Unicode => write!(f, "unicode"),
+            Theme::Color => write!(f, "color"),
+        }
+    }
+}
+
+// render settings kept by game
+#[derive(Clone, Debug, PartialEq)]
+pub struct Style {
+    pub theme: Theme,
+    // mark the stone of last step
+    pub last_step: bool,
+    // show step numbers on stones played from this step
+    pub numbers_from: Option<usize>,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            theme: Theme::Classic,
+            last_step: false,
+            numbers_from: None,
+        }
+    }
+}
+
+// board display with marks, labels of notation and overlays on it
+pub struct MarkedBoard<'a> {
+    pub(crate) board: &'a Board,
+    pub(crate) markup: &'a [Markup],
+    pub(crate) notation: Notation,
+    pub(crate) theme: Theme,
+    pub(crate) last_step: Option<(usize, usize)>,
+    pub(crate) numbers: Vec<(usize, usize, usize)>,
+}
+
+impl Board {
+    pub fn with_markup<'a>(&'a self, markup: &'a [Markup]) -> MarkedBoard<'a> {
+        MarkedBoard {
+            board: self,
+            markup,
+            notation: Notation::Sgf,
+            theme: Theme::Classic,
+            last_step: None,
+            numbers: vec![],
+        }
+    }
+}
+
+impl MarkedBoard<'_> {
+    pub fn with_notation(mut self, notation: Notation) -> Self {
+        self.notation = notation;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_last_step(mut self, point: Option<(usize, usize)>) -> Self {
+        self.last_step = point;
+        self
+    }
+
+    // (x, y, step number) shown on stones
+    pub fn with_numbers(mut self, numbers: Vec<(usize, usize, usize)>) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    // column labels longer than 1 char are written vertically
+    fn fmt_columns(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let size = self.board.lines();
+        let labels: Vec<String> = (1..=size)
+            .map(|x| self.notation.column_label(x, size))
+            .collect();
+        let width = labels.iter().map(|l| l.len()).max().unwrap_or(1);
+        for line in 0..width {
+            write!(f, "{:indent$}", "", indent = indent + 1)?;
+            for label in labels.iter() {
+                let label = format!("{:>width$}", label, width = width);
+                write!(f, "{} ", &label[line..line + 1])?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+
+    fn cell(&self, x: usize, y: usize) -> String {
+        let stone = self.board.at(x, y).unwrap();
+        let mark = self.markup.iter().find(|m| m.x == x && m.y == y);
+        let number = match stone {
+            Stone::Empty => None,
+            _ => self
+                .numbers
+                .iter()
+                .find(|(nx, ny, _)| *nx == x && *ny == y)
+                .map(|(_, _, n)| (n % 100).to_string()),
+        };
+        let last = self.last_step == Some((x, y));
+        match self.theme {
+            Theme::Classic | Theme::Ascii => {
+                if let Some(m) = mark {
+                    return match stone {
+                        Stone::Empty => format!("{:<2}", m.mark.symbol()),
+                        other => {
+                            format!("{}{}", other, m.mark.symbol().chars().next().unwrap_or(' '))
+                        }
+                    };
+                }
+                if let Some(n) = number {
+                    return format!("{:<2}", n);
+                }
+                match stone {
+                    Stone::Empty if self.board.is_star_position(x, y).unwrap() => {
+                        match self.theme {
+                            Theme::Classic => String::from("。"),
+                            _ => String::from("+ "),
+                        }
+                    }
+                    // last step is in upper case
+                    other if last => format!("{} ", other.to_string().to_uppercase()),
+                    other => format!("{} ", other),
+                }
+            }
+            Theme::Unicode => self.unicode_cell(x, y, stone, mark, number, last),
+            Theme::Color => {
+                let color = match stone {
+                    Stone::Black => COLOR_BLACK,
+                    Stone::White => COLOR_WHITE,
+                    Stone::Empty => COLOR_GRID,
+                };
+                let text = self.unicode_cell(x, y, stone, mark, number, last);
+                format!("{}{}{}{}", COLOR_BOARD, color, text, COLOR_RESET)
+            }
+        }
+    }
+
+    fn unicode_cell(
+        &self,
+        x: usize,
+        y: usize,
+        stone: Stone,
+        mark: Option<&Markup>,
+        number: Option<String>,
+        last: bool,
+    ) -> String {
+        let size = self.board.lines();
+        // line to the next point
+        let line = if x < size { "─" } else { " " };
+        if let Some(n) = number {
+            return match n.len() {
+                1 => format!("{}{}", n, line),
+                _ => n,
+            };
+        }
+        let glyph = match (stone, mark) {
+            (Stone::Empty, Some(m)) => m.mark.symbol().chars().next().unwrap_or(' '),
+            (Stone::Black, _) if last => '◆',
+            (Stone::White, _) if last => '◇',
+            (Stone::Black, _) => '●',
+            (Stone::White, _) => '○',
+            (Stone::Empty, None) if self.board.is_star_position(x, y).unwrap() => '╋',
+            (Stone::Empty, None) => match (x == 1, x == size, y == 1, y == size) {
+                (true, _, true, _) => '┌',
+                (_, true, true, _) => '┐',
+                (true, _, _, true) => '└',
+                (_, true, _, true) => '┘',
+                (true, _, _, _) => '├',
+                (_, true, _, _) => '┤',
+                (_, _, true, _) => '┬',
+                (_, _, _, true) => '┴',
+                _ => '┼',
+            },
+        };
+        format!("{}{}", glyph, line)
+    }
+}
+
+impl fmt::Display for MarkedBoard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let size = self.board.lines();
+        let rows: Vec<String> = (1..=size)
+            .map(|y| self.notation.row_label(y, size))
+            .collect();
+        let indent = rows.iter().map(|l| l.len()).max().unwrap_or(1);
+        self.fmt_columns(f, indent)?;
+        for y in 1..=size {
+            write!(f, "{:>indent$} ", rows[y - 1], indent = indent)?;
+            for x in 1..=size {
+                write!(f, "{}", self.cell(x, y))?;
+            }
+            writeln!(f, "{}", rows[y - 1])?;
+        }
+        self.fmt_columns(f, indent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::{BoardSize, Mark};
+
+    #[test]
+    fn display_with_markup() {
+        let mut g = Board::new(BoardSize::Small);
+        g.add(Stone::Black, 1, 1).unwrap();
+        let markup = vec![
+            Markup {
+                mark: Mark::Triangle,
+                x: 1,
+                y: 1,
+            },
+            Markup {
+                mark: Mark::Label(String::from("12")),
+                x: 2,
+                y: 1,
+            },
+        ];
+        let text = format!("{}", g.with_markup(&markup));
+        let lines: Vec<&str> = text.split('\n').collect();
+        assert!(lines[1] == "a x^12. . . . . . . a");
+        assert!(format!("{}", g).split('\n').nth(1).unwrap() == "a x . . . . . . . . a");
+        let text = format!("{}", g.with_markup(&[]).with_notation(Notation::Japanese));
+        let lines: Vec<&str> = text.split('\n').collect();
+        assert!(lines[0] == "  9 8 7 6 5 4 3 2 1 ");
+        assert!(lines[1] == "1 x . . . . . . . . 1");
+        let g = Board::new(BoardSize::Medium);
+        let text = format!("{}", g.with_markup(&[]).with_notation(Notation::Gtp));
+        let lines: Vec<&str> = text.split('\n').collect();
+        assert!(lines[0] == "   A B C D E F G H J K L M N ");
+        assert!(lines[1] == "13 . . . . . . . . . . . . . 13");
+        assert!(lines[13] == " 1 . . . . . . . . . . . . . 1");
+    }
+
+    #[test]
+    fn display_with_theme() {
+        let mut g = Board::new(BoardSize::Small);
+        g.add(Stone::Black, 1, 1).unwrap();
+        g.add(Stone::White, 2, 1).unwrap();
+        g.add(Stone::Black, 3, 1).unwrap();
+        let text = format!(
+            "{}",
+            g.with_markup(&[])
+                .with_theme(Theme::Ascii)
+                .with_last_step(Some((2, 1)))
+        );
+        let lines: Vec<&str> = text.split('\n').collect();
+        assert!(lines[1] == "a x O x . . . . . . a");
+        assert!(lines[3] == "c . . + . + . + . . c");
+        let text = format!(
+            "{}",
+            g.with_markup(&[])
+                .with_theme(Theme::Unicode)
+                .with_numbers(vec![(1, 1, 1), (3, 1, 112)])
+        );
+        let lines: Vec<&str> = text.split('\n').collect();
+        assert!(lines[1] == "a 1─○─12┬─┬─┬─┬─┬─┐ a");
+        assert!(lines[3] == "c ├─┼─╋─┼─╋─┼─╋─┼─┤ c");
+        assert!(lines[9] == "i └─┴─┴─┴─┴─┴─┴─┴─┘ i");
+        let text = format!("{}", g.with_markup(&[]).with_theme(Theme::Color));
+        assert!(text.contains("\x1b[48;5;179m\x1b[30m●─\x1b[0m"));
+    }
+}
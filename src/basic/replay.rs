@@ -0,0 +1,198 @@
+use crate::basic::*;
+use crate::util::{encode_apng, encode_gif};
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+// board, black for grid and stones, white, last step marker
+const PALETTE: [[u8; 3]; 4] = [[220, 179, 92], [0, 0, 0], [255, 255, 255], [220, 20, 20]];
+const BOARD: u8 = 0;
+const BLACK: u8 = 1;
+const WHITE: u8 = 2;
+const MARKER: u8 = 3;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ReplayFormat {
+    Gif,
+    Apng,
+}
+
+impl FromStr for ReplayFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gif" => Ok(ReplayFormat::Gif),
+            "apng" | "png" => Ok(ReplayFormat::Apng),
+            other => Err(format!("invalid replay format: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for ReplayFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayFormat::Gif => write!(f, "gif"),
+            ReplayFormat::Apng => write!(f, "apng"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayOptions {
+    pub format: ReplayFormat,
+    // delay between frames in milliseconds
+    pub delay: u32,
+    // width and height of image, rounded down to fit the lines
+    pub pixels: usize,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        ReplayOptions {
+            format: ReplayFormat::Gif,
+            delay: 1000,
+            pixels: 400,
+        }
+    }
+}
+
+impl Game {
+    // animated image of every position from root to current node
+    pub fn to_replay(&self, options: &ReplayOptions) -> Result<Vec<u8>, String> {
+        let lines = self.board().lines();
+        // a margin of one cell around the board
+        let cell = options.pixels / (lines + 1);
+        if cell < 4 {
+            return Err(format!(
+                "{} pixels is too small for the board",
+                options.pixels
+            ));
+        }
+        let width = cell * (lines + 1);
+        if width > u16::MAX as usize {
+            return Err(format!(
+                "{} pixels is too large for the board",
+                options.pixels
+            ));
+        }
+        let steps = self.steps();
+        let frames: Vec<Vec<u8>> = self
+            .positions()
+            .iter()
+            .enumerate()
+            .map(|(i, zb)| {
+                // position i is after step i
+                let last = match i.checked_sub(1).map(|s| &steps[s]) {
+                    Some(Cmd::Step(p)) => Notation::Sgf.parse(p, lines).ok(),
+                    _ => None,
+                };
+                render_frame(&unzip_board(zb), cell, last)
+            })
+            .collect();
+        Ok(match options.format {
+            ReplayFormat::Gif => {
+                encode_gif(width as u16, width as u16, &PALETTE, &frames, options.delay)
+            }
+            ReplayFormat::Apng => {
+                encode_apng(width as u32, width as u32, &PALETTE, &frames, options.delay)
+            }
+        })
+    }
+
+    // format is apng if the filename ends with .png or .apng, gif otherwise
+    pub fn export_replay(&self, filename: &str, delay: u32, pixels: usize) -> Result<(), String> {
+        let format = if filename.ends_with(".png") || filename.ends_with(".apng") {
+            ReplayFormat::Apng
+        } else {
+            ReplayFormat::Gif
+        };
+        let data = self.to_replay(&ReplayOptions {
+            format,
+            delay,
+            pixels,
+        })?;
+        fs::write(filename, data).map_err(|err| err.to_string())
+    }
+}
+
+// palette indexes of board image, lines are drawn at the center of cells
+fn render_frame(board: &Board, cell: usize, last: Option<(usize, usize)>) -> Vec<u8> {
+    let lines = board.lines();
+    let width = cell * (lines + 1);
+    let mut pixels = vec![BOARD; width * width];
+    let (first, end) = (cell, cell * lines);
+    for i in 1..=lines {
+        let p = cell * i;
+        for q in first..=end {
+            pixels[p * width + q] = BLACK;
+            pixels[q * width + p] = BLACK;
+        }
+    }
+    let mut disc = |x: usize, y: usize, r: f64, color: u8, fill: bool| {
+        let (cx, cy) = ((cell * x) as f64, (cell * y) as f64);
+        let reach = r.ceil() as usize + 1;
+        for py in cell * y - reach..=cell * y + reach {
+            for px in cell * x - reach..=cell * x + reach {
+                let d = ((px as f64 - cx).powi(2) + (py as f64 - cy).powi(2)).sqrt();
+                if d <= r && (fill || d > r - 1.5) {
+                    pixels[py * width + px] = color;
+                }
+            }
+        }
+    };
+    let radius = cell as f64 * 0.48;
+    for y in 1..=lines {
+        for x in 1..=lines {
+            match board.at(x, y).unwrap() {
+                Stone::Empty if board.is_star_position(x, y).unwrap() => {
+                    disc(x, y, (cell as f64 / 10.0).max(1.5), BLACK, true)
+                }
+                Stone::Empty => {}
+                Stone::Black => disc(x, y, radius, BLACK, true),
+                Stone::White => {
+                    disc(x, y, radius, WHITE, true);
+                    disc(x, y, radius, BLACK, false);
+                }
+            }
+        }
+    }
+    if let Some((x, y)) = last {
+        disc(x, y, cell as f64 / 5.0, MARKER, true);
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_frames() {
+        let mut g = Game::new(BoardSize::Small);
+        g.next(Cmd::Step(String::from("cc"))).unwrap();
+        g.next(Cmd::Pass).unwrap();
+        g.next(Cmd::Step(String::from("gg"))).unwrap();
+        let frame = render_frame(&unzip_board(&g.positions()[1]), 10, Some((3, 3)));
+        let width = 100;
+        assert!(frame.len() == width * width);
+        assert!(frame[0] == BOARD && frame[10 * width + 10] == BLACK);
+        assert!(frame[30 * width + 30] == MARKER && frame[30 * width + 34] == BLACK);
+
+        let gif = g.to_replay(&ReplayOptions::default()).unwrap();
+        // 9 lines and the margin in 400 pixels
+        assert!(gif.starts_with(b"GIF89a\x90\x01\x90\x01"));
+        assert!(
+            gif.windows(4)
+                .filter(|w| w == &[0x21, 0xf9, 0x04, 0x00])
+                .count()
+                == 4
+        );
+        let options = ReplayOptions {
+            format: ReplayFormat::Apng,
+            delay: 500,
+            pixels: 10,
+        };
+        assert!(g.to_replay(&options).is_err());
+    }
+}
@@ -47,24 +47,30 @@ pub fn check_if_never_repeat_with_new_stone(
 pub fn remove_lose_liberty_stones(board: &mut Board, x: usize, y: usize) -> Result<(), String> {
     let stone = board.at(x, y)?;
     let mut remove_flag: bool = false;
-    for p in neighbour_at(&board, x, y) {
-        if p.stone == stone.another() {
-            if calc_liberty(&board, p.x, p.y) == 0 {
-                remove_block(board, p.x, p.y);
-                remove_flag = true;
-            }
+    for p in neighbour_at(board, x, y) {
+        if p.stone == stone.another() && calc_liberty(board, p.x, p.y) == 0 {
+            remove_block(board, p.x, p.y);
+            remove_flag = true;
         }
     }
-    if calc_liberty(&board, x, y) == 0 && !remove_flag {
+    if calc_liberty(board, x, y) == 0 && !remove_flag {
         return Err(format!("this point ({},{}) has no liberty", x, y));
     }
     Ok(())
 }
 
+// count of liberties of the block at the point, 0 for an empty point
+pub fn liberties(board: &Board, x: usize, y: usize) -> usize {
+    match board.at(x, y) {
+        Ok(Stone::Empty) | Err(_) => 0,
+        Ok(_) => calc_liberty(board, x, y),
+    }
+}
+
 // only considered with size edge
 fn neighbour_at(board: &Board, x: usize, y: usize) -> Vec<Point> {
     let mut ret = vec![];
-    for (nx, ny) in vec![(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+    for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
         if let Ok(stone) = board.at(nx, ny) {
             ret.push(Point::new(stone, nx, ny));
         }
@@ -104,7 +110,7 @@ fn get_block(board: &Board, x: usize, y: usize, block: &mut HashSet<Point>) {
 // remove all block stones start from target point
 fn remove_block(board: &mut Board, x: usize, y: usize) {
     let mut block = HashSet::new();
-    get_block(&board, x, y, &mut block);
+    get_block(board, x, y, &mut block);
     for bp in block.iter() {
         board.del(bp.x, bp.y).unwrap();
     }
@@ -138,11 +144,11 @@ mod tests {
     #[test]
     fn test_remove_block() {
         let mut b = Board::new(BoardSize::Small);
-        b.add(Stone::Black, 1, 1);
-        b.add(Stone::Black, 1, 2);
-        b.add(Stone::White, 2, 1);
-        b.add(Stone::White, 2, 2);
-        b.add(Stone::White, 1, 3);
+        b.add(Stone::Black, 1, 1).unwrap();
+        b.add(Stone::Black, 1, 2).unwrap();
+        b.add(Stone::White, 2, 1).unwrap();
+        b.add(Stone::White, 2, 2).unwrap();
+        b.add(Stone::White, 1, 3).unwrap();
         remove_block(&mut b, 1, 1);
         assert!(b.is(1, 1, Stone::Empty).unwrap());
         assert!(b.is(1, 2, Stone::Empty).unwrap());
@@ -166,43 +172,43 @@ mod tests {
     #[test]
     fn test_calc_liberty() {
         let mut b = Board::new(BoardSize::Small);
-        b.add(Stone::Black, 1, 1);
+        b.add(Stone::Black, 1, 1).unwrap();
         assert!(calc_liberty(&b, 1, 1) == 2);
-        b.add(Stone::Black, 1, 2);
+        b.add(Stone::Black, 1, 2).unwrap();
         assert!(calc_liberty(&b, 1, 1) == 3);
-        b.add(Stone::White, 2, 1);
+        b.add(Stone::White, 2, 1).unwrap();
         assert!(calc_liberty(&b, 1, 1) == 2);
-        b.add(Stone::Black, 2, 3);
+        b.add(Stone::Black, 2, 3).unwrap();
         assert!(calc_liberty(&b, 1, 1) == 2);
     }
 
     #[test]
     fn test_remove_lose_liberty_stones() {
         let mut b = Board::new(BoardSize::Small);
-        b.add(Stone::Black, 1, 1);
-        b.add(Stone::White, 2, 1);
-        b.add(Stone::White, 1, 2);
+        b.add(Stone::Black, 1, 1).unwrap();
+        b.add(Stone::White, 2, 1).unwrap();
+        b.add(Stone::White, 1, 2).unwrap();
         remove_lose_liberty_stones(&mut b, 1, 2).unwrap();
         assert!(b.is(1, 1, Stone::Empty).unwrap());
-        b.add(Stone::Black, 2, 2);
-        b.add(Stone::Black, 1, 3);
-        b.add(Stone::Black, 1, 1);
+        b.add(Stone::Black, 2, 2).unwrap();
+        b.add(Stone::Black, 1, 3).unwrap();
+        b.add(Stone::Black, 1, 1).unwrap();
         remove_lose_liberty_stones(&mut b, 1, 1).unwrap();
         assert!(b.is(1, 1, Stone::Black).unwrap());
         assert!(b.is(1, 2, Stone::Empty).unwrap());
 
         let mut b = Board::new(BoardSize::Small);
-        b.add(Stone::White, 2, 2);
-        b.add(Stone::White, 3, 2);
-        b.add(Stone::White, 3, 1);
-        b.add(Stone::White, 5, 1);
-        b.add(Stone::White, 5, 2);
-        b.add(Stone::Black, 2, 1);
-        b.add(Stone::Black, 4, 1);
-        b.add(Stone::Black, 1, 2);
-        b.add(Stone::Black, 4, 2);
-        b.add(Stone::Black, 2, 3);
-        b.add(Stone::Black, 3, 3);
+        b.add(Stone::White, 2, 2).unwrap();
+        b.add(Stone::White, 3, 2).unwrap();
+        b.add(Stone::White, 3, 1).unwrap();
+        b.add(Stone::White, 5, 1).unwrap();
+        b.add(Stone::White, 5, 2).unwrap();
+        b.add(Stone::Black, 2, 1).unwrap();
+        b.add(Stone::Black, 4, 1).unwrap();
+        b.add(Stone::Black, 1, 2).unwrap();
+        b.add(Stone::Black, 4, 2).unwrap();
+        b.add(Stone::Black, 2, 3).unwrap();
+        b.add(Stone::Black, 3, 3).unwrap();
         remove_lose_liberty_stones(&mut b, 4, 1).unwrap();
         assert!(b.is(3, 1, Stone::Empty).unwrap());
         assert!(b.is(3, 2, Stone::Empty).unwrap());
@@ -213,22 +219,22 @@ mod tests {
     fn test_check_if_never_repeat_with_new_stone() {
         let mut b = Board::new(BoardSize::Small);
         let mut his = vec![zip_board(&b)];
-        b.add(Stone::Black, 1, 1);
+        b.add(Stone::Black, 1, 1).unwrap();
         his.push(zip_board(&b));
-        b.add(Stone::White, 4, 1);
+        b.add(Stone::White, 4, 1).unwrap();
         his.push(zip_board(&b));
-        b.add(Stone::Black, 3, 1);
+        b.add(Stone::Black, 3, 1).unwrap();
         his.push(zip_board(&b));
-        b.add(Stone::White, 3, 2);
+        b.add(Stone::White, 3, 2).unwrap();
         his.push(zip_board(&b));
-        b.add(Stone::Black, 2, 2);
+        b.add(Stone::Black, 2, 2).unwrap();
         his.push(zip_board(&b));
-        b.add(Stone::White, 2, 1);
-        b.del(3, 1);
+        b.add(Stone::White, 2, 1).unwrap();
+        b.del(3, 1).unwrap();
         his.push(zip_board(&b));
 
         match check_if_never_repeat_with_new_stone(&b, Stone::Black, 3, 1, his) {
-            Ok(_) => assert!(false),
+            Ok(_) => panic!("repeated scene should be rejected"),
             Err(err) => assert!(err == "this point (3,1) has same scene before"),
         }
     }
@@ -0,0 +1,610 @@
+use crate::basic::*;
+use crate::util::{LinkedTree, LinkedTreeOperation};
+
+// one node of a sgf game tree, props keep the order they are read
+#[derive(Debug, Default, Clone)]
+pub struct SgfNode {
+    pub props: Vec<(String, Vec<String>)>,
+    pub children: Vec<SgfNode>,
+}
+
+impl SgfNode {
+    pub fn get(&self, key: &str) -> Option<&Vec<String>> {
+        self.props.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn first(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(|v| v.first()).map(|v| v.as_str())
+    }
+
+    fn push(&mut self, key: &str, value: String) {
+        self.props.push((key.to_string(), vec![value]));
+    }
+}
+
+// dropped in a loop, as a long sequence is a deep chain of nodes
+impl Drop for SgfNode {
+    fn drop(&mut self) {
+        let mut nodes = std::mem::take(&mut self.children);
+        while let Some(mut node) = nodes.pop() {
+            nodes.append(&mut node.children);
+        }
+    }
+}
+
+// parse the first game tree of a sgf collection
+pub fn parse_sgf(data: &str) -> Result<SgfNode, String> {
+    let mut parser = SgfParser {
+        chars: data.chars().collect(),
+        pos: 0,
+        depth: 0,
+    };
+    parser.skip_space();
+    parser.game_tree()
+}
+
+// variations nested deeper are rejected, so that a hostile file can not
+// overflow the stack
+const MAX_DEPTH: usize = 128;
+
+struct SgfParser {
+    chars: Vec<char>,
+    pos: usize,
+    // game trees opened and not closed
+    depth: usize,
+}
+
+impl SgfParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_space(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_space();
+        if self.peek() != Some(c) {
+            return Err(format!("sgf: expect '{}' at {}", c, self.pos));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    // GameTree = "(" Sequence { GameTree } ")"
+    fn game_tree(&mut self) -> Result<SgfNode, String> {
+        self.expect('(')?;
+        if self.depth == MAX_DEPTH {
+            return Err(format!("sgf: too deeply nested at {}", self.pos));
+        }
+        self.depth += 1;
+        let mut sequence = vec![];
+        loop {
+            self.skip_space();
+            if self.peek() != Some(';') {
+                break;
+            }
+            self.pos += 1;
+            sequence.push(self.node()?);
+        }
+        if sequence.is_empty() {
+            return Err(format!("sgf: empty sequence at {}", self.pos));
+        }
+        let mut variations = vec![];
+        loop {
+            self.skip_space();
+            if self.peek() != Some('(') {
+                break;
+            }
+            variations.push(self.game_tree()?);
+        }
+        self.expect(')')?;
+        self.depth -= 1;
+
+        let mut node = sequence.pop().unwrap();
+        node.children = variations;
+        while let Some(mut parent) = sequence.pop() {
+            parent.children = vec![node];
+            node = parent;
+        }
+        Ok(node)
+    }
+
+    fn node(&mut self) -> Result<SgfNode, String> {
+        let mut node = SgfNode::default();
+        loop {
+            self.skip_space();
+            let mut key = String::new();
+            while let Some(c) = self.peek() {
+                if !c.is_ascii_uppercase() {
+                    break;
+                }
+                key.push(c);
+                self.pos += 1;
+            }
+            if key.is_empty() {
+                return Ok(node);
+            }
+            let mut values = vec![];
+            loop {
+                self.skip_space();
+                if self.peek() != Some('[') {
+                    break;
+                }
+                self.pos += 1;
+                values.push(self.value()?);
+            }
+            if values.is_empty() {
+                return Err(format!("sgf: property {} without value", key));
+            }
+            node.props.push((key, values));
+        }
+    }
+
+    fn value(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(String::from("sgf: unexpected end of value")),
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(value);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        // soft line break
+                        Some('\n') => {}
+                        Some(c) => value.push(c),
+                        None => return Err(String::from("sgf: unexpected end of value")),
+                    }
+                    self.pos += 1;
+                }
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+}
+
+fn escape_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+fn sgf_to_point(value: &str) -> Result<(usize, usize), String> {
+    let arr = value.as_bytes();
+    if arr.len() != 2 || !arr[0].is_ascii_lowercase() || !arr[1].is_ascii_lowercase() {
+        return Err(format!("sgf: invalid point {}", value));
+    }
+    Ok(((arr[0] - b'a') as usize + 1, (arr[1] - b'a') as usize + 1))
+}
+
+// points of a list, which may be compressed to rectangles like aa:cc
+fn sgf_to_points(values: &[String]) -> Result<Vec<(usize, usize)>, String> {
+    let mut points = vec![];
+    for value in values {
+        let (from, to) = value.split_once(':').unwrap_or((value, value));
+        let ((x1, y1), (x2, y2)) = (sgf_to_point(from)?, sgf_to_point(to)?);
+        for y in y1.min(y2)..=y1.max(y2) {
+            for x in x1.min(x2)..=x1.max(x2) {
+                points.push((x, y));
+            }
+        }
+    }
+    Ok(points)
+}
+
+// a node without a move is kept with the node before it
+fn merge_note(into: &mut Note, from: Note) {
+    if !from.comment.is_empty() {
+        if !into.comment.is_empty() {
+            into.comment.push('\n');
+        }
+        into.comment.push_str(&from.comment);
+    }
+    into.annotation = from.annotation.or(into.annotation.take());
+    into.judgement = from.judgement.or(into.judgement.take());
+    for m in from.markup {
+        into.mark(m.x, m.y, m.mark);
+    }
+}
+
+fn note_to_sgf(note: &Note, node: &mut SgfNode) {
+    if !note.comment.is_empty() {
+        node.push("C", note.comment.clone());
+    }
+    match note.annotation {
+        Some(MoveAnnotation::Good) => node.push("TE", String::from("1")),
+        Some(MoveAnnotation::Bad) => node.push("BM", String::from("1")),
+        Some(MoveAnnotation::Doubtful) => node.push("DO", String::new()),
+        Some(MoveAnnotation::Interesting) => node.push("IT", String::new()),
+        None => {}
+    }
+    match note.judgement {
+        Some(Judgement::Even) => node.push("DM", String::from("1")),
+        Some(Judgement::GoodForBlack) => node.push("GB", String::from("1")),
+        Some(Judgement::GoodForWhite) => node.push("GW", String::from("1")),
+        Some(Judgement::Unclear) => node.push("UC", String::from("1")),
+        None => {}
+    }
+    for (key, kind) in [
+        ("TR", Mark::Triangle),
+        ("SQ", Mark::Square),
+        ("CR", Mark::Circle),
+    ] {
+        let points: Vec<String> = note
+            .markup
+            .iter()
+            .filter(|m| m.mark == kind)
+            .map(|m| Cmd::point_to_cmd(m.x, m.y))
+            .collect();
+        if !points.is_empty() {
+            node.props.push((key.to_string(), points));
+        }
+    }
+    let labels: Vec<String> = note
+        .markup
+        .iter()
+        .filter_map(|m| match &m.mark {
+            Mark::Label(text) => Some(format!("{}:{}", Cmd::point_to_cmd(m.x, m.y), text)),
+            _ => None,
+        })
+        .collect();
+    if !labels.is_empty() {
+        node.props.push((String::from("LB"), labels));
+    }
+}
+
+fn sgf_to_note(node: &SgfNode) -> Result<Note, String> {
+    let mut note = Note::default();
+    for (key, values) in node.props.iter() {
+        match key.as_str() {
+            "C" => note.comment = values[0].clone(),
+            "TE" => note.annotation = Some(MoveAnnotation::Good),
+            "BM" => note.annotation = Some(MoveAnnotation::Bad),
+            "DO" => note.annotation = Some(MoveAnnotation::Doubtful),
+            "IT" => note.annotation = Some(MoveAnnotation::Interesting),
+            "DM" => note.judgement = Some(Judgement::Even),
+            "GB" => note.judgement = Some(Judgement::GoodForBlack),
+            "GW" => note.judgement = Some(Judgement::GoodForWhite),
+            "UC" => note.judgement = Some(Judgement::Unclear),
+            "TR" | "SQ" | "CR" => {
+                for v in values {
+                    let (x, y) = sgf_to_point(v)?;
+                    let mark = match key.as_str() {
+                        "TR" => Mark::Triangle,
+                        "SQ" => Mark::Square,
+                        _ => Mark::Circle,
+                    };
+                    note.mark(x, y, mark);
+                }
+            }
+            "LB" => {
+                for v in values {
+                    let (point, text) = v.split_once(':').unwrap_or((v, ""));
+                    let (x, y) = sgf_to_point(point)?;
+                    note.mark(x, y, Mark::Label(text.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(note)
+}
+
+fn write_sgf_node(node: &SgfNode, data: &mut String) {
+    data.push(';');
+    for (key, values) in node.props.iter() {
+        data.push_str(key);
+        for v in values {
+            data.push_str(format!("[{}]", escape_value(v)).as_str());
+        }
+    }
+    match node.children.len() {
+        0 => {}
+        1 => write_sgf_node(&node.children[0], data),
+        _ => {
+            for child in node.children.iter() {
+                data.push_str("\n(");
+                write_sgf_node(child, data);
+                data.push(')');
+            }
+        }
+    }
+}
+
+fn tree_to_sgf(g: &Game, cmd: &LinkedTree<Cmd>, note: &LinkedTree<Note>) -> SgfNode {
+    let mut node = SgfNode::default();
+    // players take turns in the game, white first after setup stones, as
+    // two moves of a player in a row are loaded with a pass between
+    let color = match g.player_of_step(cmd.deepth()) {
+        Player::Black => "B",
+        Player::White => "W",
+    };
+    match cmd.val() {
+        Cmd::Start => {}
+        Cmd::Pass => node.push(color, String::new()),
+        Cmd::Step(p) => node.push(color, p),
+    }
+    note_to_sgf(&note.val(), &mut node);
+    for i in 0..cmd.child_len() {
+        let child = tree_to_sgf(g, &cmd.child(i).unwrap(), &note.child(i).unwrap());
+        node.children.push(child);
+    }
+    node
+}
+
+impl Game {
+    // the whole game tree in sgf, with all variations
+    pub fn to_sgf(&self) -> String {
+        let (cmd, note) = self.root_nodes();
+        let mut root = tree_to_sgf(self, &cmd, &note);
+        let size = match self.board_size() {
+            BoardSize::Normal => "19",
+            BoardSize::Medium => "13",
+            BoardSize::Small => "9",
+        };
+        let mut head = vec![
+            (String::from("GM"), vec![String::from("1")]),
+            (String::from("FF"), vec![String::from("4")]),
+            (String::from("CA"), vec![String::from("UTF-8")]),
+            (String::from("AP"), vec![String::from("mapleque/rustgo")]),
+            (String::from("SZ"), vec![size.to_string()]),
+        ];
+        for (key, value) in self.info().fields() {
+            let prop = GameInfo::sgf_key(key).unwrap();
+            head.push((prop.to_string(), vec![value]));
+        }
+        let stones = self.handicap_stones();
+        if !stones.is_empty() {
+            let points = stones.iter().map(|(x, y)| Cmd::point_to_cmd(*x, *y));
+            head.push((String::from("AB"), points.collect()));
+        }
+        // private properties of the undo rule and the steps taken back by
+        // black and white, which are left out for free undo
+        let taken = [Player::Black, Player::White].map(|p| self.undos_taken(&p));
+        if self.undo_rule() != UndoRule::Free || taken != [0, 0] {
+            head.push((String::from("UR"), vec![self.undo_rule().to_string()]));
+            head.push((String::from("UT"), taken.map(|n| n.to_string()).to_vec()));
+        }
+        head.append(&mut root.props);
+        root.props = head;
+
+        let mut data = String::from("(");
+        write_sgf_node(&root, &mut data);
+        data.push_str(")\n");
+        data
+    }
+
+    // load sgf and go to the end of main line
+    pub fn from_sgf(data: &str) -> Result<Game, String> {
+        let root = parse_sgf(data)?;
+        let mut g = match root.first("SZ").unwrap_or("19") {
+            "19" => Game::new(BoardSize::Normal),
+            "13" => Game::new(BoardSize::Medium),
+            "9" => Game::new(BoardSize::Small),
+            other => return Err(format!("sgf: unsupported board size {}", other)),
+        };
+        for (prop, values) in root.props.iter() {
+            if let Some(key) = GameInfo::key_of_sgf(prop) {
+                g.set_info_from_file(key, &values[0])?;
+            }
+        }
+        g.load_sgf_setup(&root)?;
+        g.set_note(sgf_to_note(&root)?);
+        for child in root.children.iter() {
+            g.load_sgf_node(child)?;
+        }
+        while !g.redo_list().is_empty() {
+            g.redo(0)?;
+        }
+        // set at last, the nodes are taken back with free undo on loading
+        if let Some(rule) = root.first("UR") {
+            g.set_undo_rule(rule.parse()?);
+        }
+        if let Some(values) = root.get("UT") {
+            let taken: Vec<usize> = values.iter().filter_map(|v| v.parse().ok()).collect();
+            match taken[..] {
+                [black, white] => g.set_undos_taken([black, white]),
+                _ => return Err(format!("sgf: invalid UT {:?}", values)),
+            }
+        }
+        Ok(g)
+    }
+
+    // black stones put before the first move, of the root or a node after
+    // it. white stones and stones put later can not be kept in the game.
+    fn load_sgf_setup(&mut self, node: &SgfNode) -> Result<(), String> {
+        if node.get("AW").is_some() || node.get("AE").is_some() {
+            return Err(String::from("sgf: only black stones can be set up"));
+        }
+        let Some(values) = node.get("AB") else {
+            return Ok(());
+        };
+        if self.step_count() > 0 {
+            return Err(String::from(
+                "sgf: stones can only be set up before the first move",
+            ));
+        }
+        let mut points = self.handicap_stones();
+        points.extend(sgf_to_points(values)?);
+        self.set_handicap_stones(&points)
+    }
+
+    // the node and the sequence after it are loaded in a loop, only
+    // variations recurse, so the depth is the nesting of variations
+    fn load_sgf_node(&mut self, node: &SgfNode) -> Result<(), String> {
+        let mut node = node;
+        let mut steps = 0;
+        loop {
+            steps += self.load_sgf_props(node)?;
+            match &node.children[..] {
+                [child] => node = child,
+                children => {
+                    for child in children {
+                        self.load_sgf_node(child)?;
+                    }
+                    break;
+                }
+            }
+        }
+        for _ in 0..steps {
+            self.undo()?;
+        }
+        Ok(())
+    }
+
+    // a node of comments or setup is merged into the current node. a player
+    // moving twice in a row is kept with a pass of the opponent between.
+    // count of steps played is returned.
+    fn load_sgf_props(&mut self, node: &SgfNode) -> Result<usize, String> {
+        let (color, value) = match (node.first("B"), node.first("W")) {
+            (Some(v), None) => (Player::Black, v),
+            (None, Some(v)) => (Player::White, v),
+            (None, None) => {
+                self.load_sgf_setup(node)?;
+                let mut note = self.note();
+                merge_note(&mut note, sgf_to_note(node)?);
+                self.set_note(note);
+                return Ok(0);
+            }
+            _ => {
+                return Err(String::from(
+                    "sgf: node should not have moves of both players",
+                ))
+            }
+        };
+        let cmd = match value {
+            // tt is pass for board not larger than 19
+            "" | "tt" => Cmd::Pass,
+            other => {
+                sgf_to_point(other)?;
+                Cmd::Step(other.to_string())
+            }
+        };
+        let mut steps = 1;
+        if color != self.next_player() {
+            // the pass of a sibling variation is shared
+            match self.redo_list().iter().position(|c| *c == Cmd::Pass) {
+                Some(i) => self.redo(i)?,
+                None => self.next(Cmd::Pass)?,
+            }
+            steps += 1;
+        }
+        self.next(cmd)
+            .map_err(|err| format!("sgf: step {}: {}", self.step_count() + 1, err))?;
+        self.load_sgf_setup(node)?;
+        self.set_note(sgf_to_note(node)?);
+        Ok(steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tree_with_variations() {
+        let root = parse_sgf("(;SZ[9]C[root \\] comment];B[aa](;W[bb];B[cc])(;W[dd]))").unwrap();
+        assert!(root.first("SZ") == Some("9"));
+        assert!(root.first("C") == Some("root ] comment"));
+        assert!(root.children.len() == 1);
+        let b = &root.children[0];
+        assert!(b.first("B") == Some("aa"));
+        assert!(b.children.len() == 2);
+        assert!(b.children[0].children[0].first("B") == Some("cc"));
+        assert!(b.children[1].first("W") == Some("dd"));
+        assert!(parse_sgf("(;B[aa]").is_err());
+    }
+
+    #[test]
+    fn round_trip_notes_and_variations() {
+        let mut g = Game::new(BoardSize::Small);
+        g.set_info("black", "Lee [Sedol]").unwrap();
+        g.set_info("komi", "7.5").unwrap();
+        g.set_comment(String::from("opening"));
+        g.next(Cmd::Step(String::from("cc"))).unwrap();
+        g.set_annotation(Some(MoveAnnotation::Good));
+        g.next(Cmd::Step(String::from("gg"))).unwrap();
+        g.set_judgement(Some(Judgement::Even));
+        g.add_mark(String::from("cc"), Mark::Triangle).unwrap();
+        g.add_mark(String::from("ee"), Mark::Label(String::from("A")))
+            .unwrap();
+        g.undo().unwrap();
+        g.next(Cmd::Pass).unwrap();
+        g.set_comment(String::from("tenuki"));
+
+        let data = g.to_sgf();
+        let l = Game::from_sgf(&data).unwrap();
+        assert!(l.to_sgf() == data);
+        assert!(l.step_count() == 2);
+        assert!(l.info().black == "Lee [Sedol]");
+        assert!(l.info().komi == Some(7.5));
+        let note = l.note();
+        assert!(note.judgement == Some(Judgement::Even));
+        assert!(note.mark_at(3, 3) == Some(&Mark::Triangle));
+        assert!(note.mark_at(5, 5) == Some(&Mark::Label(String::from("A"))));
+
+        // the undo rule and the steps taken back are kept
+        let mut g = l;
+        g.set_undo_rule(UndoRule::Once);
+        g.request_undo(Player::White).unwrap();
+        g.accept_undo(Player::Black).unwrap();
+        let l = Game::from_sgf(&g.to_sgf()).unwrap();
+        assert!(l.undo_rule() == UndoRule::Once && l.undos_taken(&Player::White) == 1);
+    }
+
+    #[test]
+    fn load_nodes_without_alternating_moves() {
+        let data = "(;SZ[9]C[root];AB[aa][ba]C[setup];W[ee];C[later]TR[ee];W[gg](;B[cc])(;W[hh]))";
+        let g = Game::from_sgf(data).unwrap();
+        assert!(g.handicap_stones() == vec![(1, 1), (2, 1)]);
+        assert!(
+            g.steps()
+                == vec![
+                    "ee".parse().unwrap(),
+                    Cmd::Pass,
+                    "gg".parse().unwrap(),
+                    "cc".parse().unwrap()
+                ]
+        );
+        let l = Game::from_sgf(&g.to_sgf()).unwrap();
+        assert!(l.to_sgf() == g.to_sgf() && l.board() == g.board());
+        assert!(
+            g.to_sgf().contains("C[root\nsetup]") && g.to_sgf().contains(";W[ee]C[later]TR[ee]")
+        );
+        // the second white move follows another pass of black
+        assert!(g
+            .to_sgf()
+            .ends_with(";B[];W[gg]\n(;B[cc])\n(;B[];W[hh]))\n"));
+        assert!(Game::from_sgf("(;SZ[9];B[aa]AW[bb])").is_err());
+        // unusual info is kept, not failing the load
+        let data = "(;SZ[9]KM[0]HA[1]TM[5400.0]RE[Jigo])";
+        let g = Game::from_sgf(data).unwrap();
+        assert!(g.info().komi == Some(0.0) && g.info().time == Some(5400.0));
+        assert!(g.info().warnings().len() == 2 && g.to_sgf().contains("HA[1]TM[5400]RE[Jigo]"));
+        assert!(Game::from_sgf("(;SZ[9];B[aa];AB[bb])").is_err());
+    }
+
+    #[test]
+    fn deep_files() {
+        let nested = "(;C[x]".repeat(20000);
+        assert!(Game::from_sgf(&nested)
+            .err()
+            .unwrap()
+            .starts_with("sgf: too deeply nested"));
+        let nested = "(;C[x]".repeat(MAX_DEPTH) + &")".repeat(MAX_DEPTH);
+        assert!(Game::from_sgf(&nested).is_ok());
+        let long = format!("(;SZ[9]{})", ";B[];W[]".repeat(1500));
+        let g = Game::from_sgf(&long).unwrap();
+        assert!(g.step_count() == 3000);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::basic::{Game, Mark, MarkedBoard, Stone};
+
+// pixels between two lines
+const CELL: usize = 24;
+const STONE_RADIUS: usize = 11;
+const BOARD_COLOR: &str = "#dcb35c";
+
+impl MarkedBoard<'_> {
+    // standalone svg document, coordinates are drawn around the board
+    pub fn to_svg(&self, coordinates: bool) -> String {
+        let size = self.board.lines();
+        let margin = if coordinates { CELL * 3 / 2 } else { CELL };
+        let width = margin * 2 + (size - 1) * CELL;
+        let pos = |i: usize| margin + (i - 1) * CELL;
+        let last = pos(size);
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{w}\" viewBox=\"0 0 {w} {w}\">\n",
+            w = width
+        );
+        svg.push_str(&format!(
+            "<rect width=\"{w}\" height=\"{w}\" fill=\"{}\"/>\n",
+            BOARD_COLOR,
+            w = width
+        ));
+        for i in 1..=size {
+            svg.push_str(&format!(
+                "<line x1=\"{}\" y1=\"{p}\" x2=\"{}\" y2=\"{p}\" stroke=\"black\"/>\n",
+                margin,
+                last,
+                p = pos(i)
+            ));
+            svg.push_str(&format!(
+                "<line x1=\"{p}\" y1=\"{}\" x2=\"{p}\" y2=\"{}\" stroke=\"black\"/>\n",
+                margin,
+                last,
+                p = pos(i)
+            ));
+        }
+        if coordinates {
+            let (near, far) = (margin - CELL, last + CELL);
+            for i in 1..=size {
+                let column = escape(&self.notation.column_label(i, size));
+                let row = escape(&self.notation.row_label(i, size));
+                for y in [near, far] {
+                    svg.push_str(&text(pos(i), y, "black", &column));
+                }
+                for x in [near, far] {
+                    svg.push_str(&text(x, pos(i), "black", &row));
+                }
+            }
+        }
+
+        for y in 1..=size {
+            for x in 1..=size {
+                let (cx, cy) = (pos(x), pos(y));
+                let stone = self.board.at(x, y).unwrap();
+                let color = match stone {
+                    Stone::Black => "white",
+                    _ => "black",
+                };
+                match stone {
+                    Stone::Empty if self.board.is_star_position(x, y).unwrap() => {
+                        svg.push_str(&format!(
+                            "<circle cx=\"{}\" cy=\"{}\" r=\"3\" fill=\"black\"/>\n",
+                            cx, cy
+                        ));
+                    }
+                    Stone::Empty => {}
+                    other => {
+                        let fill = if other == Stone::Black {
+                            "black"
+                        } else {
+                            "white"
+                        };
+                        svg.push_str(&format!(
+                            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" stroke=\"black\"/>\n",
+                            cx, cy, STONE_RADIUS, fill
+                        ));
+                    }
+                }
+                if stone != Stone::Empty {
+                    if let Some((_, _, n)) =
+                        self.numbers.iter().find(|(nx, ny, _)| *nx == x && *ny == y)
+                    {
+                        svg.push_str(&text(cx, cy, color, &n.to_string()));
+                        continue;
+                    }
+                    if self.last_step == Some((x, y)) {
+                        svg.push_str(&format!(
+                            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>\n",
+                            cx, cy, STONE_RADIUS / 2, color
+                        ));
+                    }
+                }
+                if let Some(m) = self.markup.iter().find(|m| m.x == x && m.y == y) {
+                    svg.push_str(&mark(cx, cy, color, stone, &m.mark));
+                }
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+impl Game {
+    // current position with markup of current node, stones played in steps
+    // from..=to are numbered
+    pub fn to_svg(&self, numbers: Option<(usize, usize)>, coordinates: bool) -> String {
+        let numbers = self.step_numbers_in(numbers);
+        let note = self.note();
+        self.board()
+            .with_markup(&note.markup)
+            .with_notation(self.notation())
+            .with_numbers(numbers)
+            .to_svg(coordinates)
+    }
+}
+
+fn text(x: usize, y: usize, color: &str, content: &str) -> String {
+    let font = if content.chars().count() > 2 { 10 } else { 12 };
+    format!(
+        "<text x=\"{}\" y=\"{}\" fill=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+        x, y, color, font, content
+    )
+}
+
+fn mark(x: usize, y: usize, color: &str, stone: Stone, mark: &Mark) -> String {
+    let r = STONE_RADIUS / 2 + 1;
+    let shape = format!("fill=\"none\" stroke=\"{}\" stroke-width=\"2\"", color);
+    match mark {
+        Mark::Triangle => format!(
+            "<polygon points=\"{},{} {},{} {},{}\" {}/>\n",
+            x,
+            y - r,
+            x - r,
+            y + r * 2 / 3,
+            x + r,
+            y + r * 2 / 3,
+            shape
+        ),
+        Mark::Square => format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{s}\" height=\"{s}\" {}/>\n",
+            x - r * 3 / 4,
+            y - r * 3 / 4,
+            shape,
+            s = r * 3 / 2
+        ),
+        Mark::Circle => format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>\n",
+            x, y, r, shape
+        ),
+        Mark::Label(label) => {
+            let mut svg = String::new();
+            // hide the grid under the label
+            if stone == Stone::Empty {
+                svg.push_str(&format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>\n",
+                    x, y, STONE_RADIUS, BOARD_COLOR
+                ));
+            }
+            svg.push_str(&text(x, y, color, &escape(label)));
+            svg
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::{BoardSize, Cmd};
+
+    #[test]
+    fn game_to_svg() {
+        let mut g = Game::new(BoardSize::Small);
+        for step in ["cc", "gg", "cg"] {
+            g.next(Cmd::Step(step.to_string())).unwrap();
+        }
+        g.add_mark(String::from("ee"), Mark::Label(String::from("<A>")))
+            .unwrap();
+        let svg = g.to_svg(Some((2, 3)), true);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"264\""));
+        assert!(svg.ends_with("</svg>\n"));
+        // 2 black and 1 white stones, 6 empty star points
+        assert!(svg.matches("fill=\"black\" stroke=\"black\"").count() == 2);
+        assert!(svg.matches("fill=\"white\" stroke=\"black\"").count() == 1);
+        assert!(svg.matches("r=\"3\"").count() == 6);
+        assert!(svg
+            .contains("<circle cx=\"180\" cy=\"180\" r=\"11\" fill=\"white\" stroke=\"black\"/>"));
+        assert!(
+            svg.contains(">2</text>") && svg.contains(">3</text>") && !svg.contains(">1</text>")
+        );
+        assert!(svg.contains(">&lt;A&gt;</text>"));
+        // row labels on both sides
+        assert!(svg.matches(">i</text>").count() == 4);
+        let svg = g.to_svg(None, false);
+        assert!(svg.contains("width=\"240\"") && !svg.contains(">i</text>"));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::basic::*;
+use crate::util::{LinkedTree, LinkedTreeOperation};
+use std::fmt;
+use std::str::FromStr;
+
+// the 8 rotations and reflections of a square board, rotations are clockwise
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+    Transpose,
+    AntiTranspose,
+}
+
+pub const SYMMETRIES: [Symmetry; 8] = [
+    Symmetry::Identity,
+    Symmetry::Rotate90,
+    Symmetry::Rotate180,
+    Symmetry::Rotate270,
+    Symmetry::FlipHorizontal,
+    Symmetry::FlipVertical,
+    Symmetry::Transpose,
+    Symmetry::AntiTranspose,
+];
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "identity" => Ok(Symmetry::Identity),
+            "rotate90" => Ok(Symmetry::Rotate90),
+            "rotate180" => Ok(Symmetry::Rotate180),
+            "rotate270" => Ok(Symmetry::Rotate270),
+            "flip-horizontal" => Ok(Symmetry::FlipHorizontal),
+            "flip-vertical" => Ok(Symmetry::FlipVertical),
+            "transpose" => Ok(Symmetry::Transpose),
+            "anti-transpose" => Ok(Symmetry::AntiTranspose),
+            other => Err(format!("invalid symmetry: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Symmetry::Identity => write!(f, "identity"),
+            Symmetry::Rotate90 => write!(f, "rotate90"),
+            Symmetry::Rotate180 => write!(f, "rotate180"),
+            Symmetry::Rotate270 => write!(f, "rotate270"),
+            Symmetry::FlipHorizontal => write!(f, "flip-horizontal"),
+            Symmetry::FlipVertical => write!(f, "flip-vertical"),
+            Symmetry::Transpose => write!(f, "transpose"),
+            Symmetry::AntiTranspose => write!(f, "anti-transpose"),
+        }
+    }
+}
+
+impl Symmetry {
+    // point (x, y) of size * size board moves to
+    pub fn point(&self, x: usize, y: usize, size: usize) -> (usize, usize) {
+        let (rx, ry) = (size + 1 - x, size + 1 - y);
+        match self {
+            Symmetry::Identity => (x, y),
+            Symmetry::Rotate90 => (ry, x),
+            Symmetry::Rotate180 => (rx, ry),
+            Symmetry::Rotate270 => (y, rx),
+            Symmetry::FlipHorizontal => (rx, y),
+            Symmetry::FlipVertical => (x, ry),
+            Symmetry::Transpose => (y, x),
+            Symmetry::AntiTranspose => (ry, rx),
+        }
+    }
+
+    // the one takes points back
+    pub fn inverse(&self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => *other,
+        }
+    }
+}
+
+impl Board {
+    pub fn transform(&self, symmetry: Symmetry) -> Board {
+        let size = self.lines();
+        let mut board = Board::new(self.size());
+        for y in 1..=size {
+            for x in 1..=size {
+                let stone = self.at(x, y).unwrap();
+                if stone != Stone::Empty {
+                    let (tx, ty) = symmetry.point(x, y, size);
+                    board.add(stone, tx, ty).unwrap();
+                }
+            }
+        }
+        board
+    }
+
+    // black stones become white and white ones become black
+    pub fn swap_colors(&self) -> Board {
+        let size = self.lines();
+        let mut board = Board::new(self.size());
+        for y in 1..=size {
+            for x in 1..=size {
+                let stone = self.at(x, y).unwrap();
+                if stone != Stone::Empty {
+                    board.add(stone.another(), x, y).unwrap();
+                }
+            }
+        }
+        board
+    }
+}
+
+impl Cmd {
+    // step is moved, start and pass are kept
+    pub fn transform(&self, symmetry: Symmetry, size: usize) -> Result<Cmd, String> {
+        match self {
+            Cmd::Step(p) => {
+                let (x, y) = Notation::Sgf.parse(p, size)?;
+                let (x, y) = symmetry.point(x, y, size);
+                Ok(Cmd::Step(Cmd::point_to_cmd(x, y)))
+            }
+            other => Ok(other.clone()),
+        }
+    }
+}
+
+impl BoardZip {
+    // fnv-1a hash, which is the same in every run and platform, so it can
+    // be kept in files
+    pub fn hash64(&self) -> u64 {
+        let words: Vec<u128> = match self {
+            BoardZip::Normal(d1, d2, d3, d4, d5, d6) => vec![*d1, *d2, *d3, *d4, *d5, *d6],
+            BoardZip::Medium(d1, d2, d3) => vec![*d1, *d2, *d3],
+            BoardZip::Small(d1, d2) => vec![*d1, *d2 as u128],
+        };
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for word in words {
+            for byte in word.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        hash
+    }
+}
+
+// the smallest zip of all symmetries of the board, and of the colour swapped
+// ones if colors is true. the symmetry and whether colours are swapped to get
+// it are returned too.
+pub fn canonical_zip(board: &Board, colors: bool) -> (BoardZip, Symmetry, bool) {
+    let mut boards = vec![(board.clone(), false)];
+    if colors {
+        boards.push((board.swap_colors(), true));
+    }
+    let mut best: Option<(BoardZip, Symmetry, bool)> = None;
+    for (board, swapped) in boards {
+        for symmetry in SYMMETRIES {
+            let zip = zip_board(&board.transform(symmetry));
+            if best.as_ref().is_none_or(|(b, _, _)| zip < *b) {
+                best = Some((zip, symmetry, swapped));
+            }
+        }
+    }
+    best.unwrap()
+}
+
+// positions equal by symmetry have the same hash
+pub fn canonical_hash(board: &Board, colors: bool) -> u64 {
+    canonical_zip(board, colors).0.hash64()
+}
+
+impl Game {
+    // the whole game tree with setup stones, steps and marks moved, current
+    // node, info and undo rule are kept. autosave is not.
+    pub fn transform(&self, symmetry: Symmetry) -> Result<Game, String> {
+        let size = self.board().lines();
+        let mut g = Game::new(self.board_size());
+        let setup: Vec<(usize, usize)> = self
+            .handicap_stones()
+            .into_iter()
+            .map(|(x, y)| symmetry.point(x, y, size))
+            .collect();
+        g.set_handicap_stones(&setup)?;
+        // as it is, with the values kept from a file
+        g.set_game_info(self.info().clone());
+        g.set_notation(self.notation());
+        g.set_style(self.style().clone());
+        let (cmd, note) = self.root_nodes();
+        transform_node(&mut g, &cmd, &note, symmetry)?;
+        for i in self.current_path() {
+            g.redo(i)?;
+        }
+        // set at last, the tree is played back with free undo
+        g.set_undo_rule(self.undo_rule());
+        g.set_undos_taken([Player::Black, Player::White].map(|p| self.undos_taken(&p)));
+        Ok(g)
+    }
+}
+
+// play the children of node to g and go back
+fn transform_node(
+    g: &mut Game,
+    cmd: &LinkedTree<Cmd>,
+    note: &LinkedTree<Note>,
+    symmetry: Symmetry,
+) -> Result<(), String> {
+    let size = g.board().lines();
+    let mut n = note.val();
+    for m in n.markup.iter_mut() {
+        (m.x, m.y) = symmetry.point(m.x, m.y, size);
+    }
+    g.set_note(n);
+    for i in 0..cmd.child_len() {
+        g.next(cmd.child(i).unwrap().val().transform(symmetry, size)?)?;
+        transform_node(g, &cmd.child(i).unwrap(), &note.child(i).unwrap(), symmetry)?;
+        g.undo()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_go_back_by_inverse() {
+        for symmetry in SYMMETRIES {
+            for (x, y) in [(1, 1), (3, 4), (19, 2)] {
+                let (tx, ty) = symmetry.point(x, y, 19);
+                assert!(symmetry.inverse().point(tx, ty, 19) == (x, y));
+            }
+            assert!(symmetry.to_string().parse::<Symmetry>().unwrap() == symmetry);
+        }
+        assert!(Symmetry::Rotate90.point(1, 1, 19) == (19, 1));
+        assert!(Symmetry::Transpose.point(3, 4, 19) == (4, 3));
+    }
+
+    #[test]
+    fn same_joseki_in_each_corner() {
+        let mut corners = vec![];
+        for steps in [["dd", "fc"], ["pd", "nc"], ["dp", "fq"], ["pp", "qn"]] {
+            let mut g = Game::new(BoardSize::Normal);
+            for step in steps {
+                g.next(Cmd::Step(step.to_string())).unwrap();
+            }
+            corners.push(canonical_hash(g.board(), false));
+        }
+        assert!(corners.iter().all(|h| *h == corners[0]));
+
+        let mut b = Board::new(BoardSize::Small);
+        b.add(Stone::Black, 3, 3).unwrap();
+        let mut w = Board::new(BoardSize::Small);
+        w.add(Stone::White, 7, 3).unwrap();
+        assert!(canonical_hash(&b, false) != canonical_hash(&w, false));
+        assert!(canonical_hash(&b, true) == canonical_hash(&w, true));
+        let (zip, symmetry, swapped) = canonical_zip(&w, true);
+        assert!(w.swap_colors().transform(symmetry) == unzip_board(&zip) && swapped);
+    }
+
+    #[test]
+    fn transform_game_tree() {
+        let mut g = Game::new(BoardSize::Small);
+        g.next(Cmd::Step(String::from("cc"))).unwrap();
+        g.add_mark(String::from("cc"), Mark::Triangle).unwrap();
+        g.next(Cmd::Step(String::from("gc"))).unwrap();
+        g.undo().unwrap();
+        g.next(Cmd::Pass).unwrap();
+        let mut t = g.transform(Symmetry::Rotate90).unwrap();
+        assert!(t.step_count() == 2);
+        assert!(t.board() == &g.board().transform(Symmetry::Rotate90));
+        t.undo().unwrap();
+        assert!(t.note().mark_at(7, 3).is_some());
+        assert!(t.redo_list()[0].to_string() == "gg");
+
+        // setup stones are moved and white is still first
+        let mut g = Game::from_sgf("(;SZ[9]HA[1]AB[gc][cg];W[cc])").unwrap();
+        g.set_undo_rule(UndoRule::Once);
+        let t = g.transform(Symmetry::Rotate90).unwrap();
+        assert!(t.handicap_stones() == vec![(3, 3), (7, 7)]);
+        assert!(t.board().at(7, 3) == Ok(Stone::White) && t.next_player() == Player::Black);
+        assert!(t.info().get("handicap").unwrap() == "1" && t.undo_rule() == UndoRule::Once);
+        assert!(t
+            .to_sgf()
+            .contains("HA[1]AB[cc][gg]UR[once]UT[0][0];W[gc])"));
+    }
+}
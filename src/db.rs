@@ -0,0 +1,9 @@
+mod book;
+mod index;
+mod pattern;
+mod query;
+
+pub use book::*;
+pub use index::*;
+pub use pattern::*;
+pub use query::*;
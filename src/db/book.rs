@@ -0,0 +1,290 @@
+use crate::basic::*;
+use crate::db::Database;
+use crate::util::{LinkedTree, LinkedTreeOperation};
+use std::fmt;
+
+// games through a node of the book and who won them, games without result
+// or with draw are only counted in games
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BookStats {
+    pub games: usize,
+    pub black_wins: usize,
+    pub white_wins: usize,
+}
+
+impl BookStats {
+    fn add(&mut self, result: &str) {
+        self.games += 1;
+        let result = result.to_uppercase();
+        if result.starts_with("B+") {
+            self.black_wins += 1;
+        } else if result.starts_with("W+") {
+            self.white_wins += 1;
+        }
+    }
+
+    // percent of wins in games with a winner
+    pub fn win_rate(&self, stone: Stone) -> Option<usize> {
+        let decided = self.black_wins + self.white_wins;
+        if decided == 0 {
+            return None;
+        }
+        match stone {
+            Stone::Black => Some(self.black_wins * 100 / decided),
+            Stone::White => Some(self.white_wins * 100 / decided),
+            Stone::Empty => None,
+        }
+    }
+}
+
+impl fmt::Display for BookStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} games", self.games)?;
+        if let (Some(b), Some(w)) = (self.win_rate(Stone::Black), self.win_rate(Stone::White)) {
+            write!(f, ", black wins {}%, white wins {}%", b, w)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookNode {
+    pub step: Cmd,
+    pub stats: BookStats,
+}
+
+// tree of the opening steps of games, with a cursor to explore it
+pub struct Book {
+    root: LinkedTree<BookNode>,
+    current: LinkedTree<BookNode>,
+    game: Game,
+}
+
+impl Book {
+    pub fn new(size: BoardSize) -> Book {
+        let root = LinkedTree::new_tree(BookNode {
+            step: Cmd::Start,
+            stats: BookStats::default(),
+        });
+        Book {
+            current: root.ptr(),
+            root,
+            game: Game::new(size),
+        }
+    }
+
+    // the first depth steps of the games of the size in the database. if
+    // symmetric is true, games are turned to the same orientation, so the
+    // same opening in another corner is counted together. games with setup
+    // stones, like handicap, are left out, the book starts from the empty
+    // board with black to play.
+    pub fn from_database(
+        db: &Database,
+        size: BoardSize,
+        depth: usize,
+        symmetric: bool,
+    ) -> Result<Book, String> {
+        let mut book = Book::new(size.clone());
+        let games = db
+            .games()
+            .iter()
+            .filter(|g| g.size == size && g.setup.is_empty());
+        for game in games {
+            let steps = if symmetric {
+                normalize_steps(&game.steps, size.lines())?
+            } else {
+                game.steps.clone()
+            };
+            book.add_game(&steps, &game.info.result, depth);
+        }
+        Ok(book)
+    }
+
+    // steps from the empty board are expected to be legal, which is true
+    // for indexed games without setup
+    pub fn add_game(&mut self, steps: &[Cmd], result: &str, depth: usize) {
+        let mut node = self.root.ptr();
+        add_result(&node, result);
+        for step in steps.iter().take(depth) {
+            let child = (0..node.child_len())
+                .map(|i| node.child(i).unwrap())
+                .find(|c| c.val().step == *step);
+            node = match child {
+                Some(c) => c,
+                None => node.add_child(BookNode {
+                    step: step.clone(),
+                    stats: BookStats::default(),
+                }),
+            };
+            add_result(&node, result);
+        }
+    }
+
+    pub fn stats(&self) -> BookStats {
+        self.current.val().stats
+    }
+
+    // the position reached, with the steps from the empty board
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    // steps played from the current position, the most played first
+    pub fn continuations(&self) -> Vec<(Cmd, BookStats)> {
+        sorted_children(&self.current)
+            .into_iter()
+            .map(|c| {
+                let node = c.val();
+                (node.step, node.stats)
+            })
+            .collect()
+    }
+
+    pub fn play(&mut self, step: &Cmd) -> Result<(), String> {
+        let child = sorted_children(&self.current)
+            .into_iter()
+            .find(|c| c.val().step == *step)
+            .ok_or_else(|| format!("no game continues with {}", step))?;
+        self.game.next(step.clone())?;
+        self.current = child;
+        Ok(())
+    }
+
+    pub fn back(&mut self) -> Result<(), String> {
+        let parent = self
+            .current
+            .parent()
+            .ok_or_else(|| String::from("at the beginning"))?;
+        self.game.undo()?;
+        self.current = parent;
+        Ok(())
+    }
+
+    // the whole book as a game tree, the most played step is the main line,
+    // stats are in comments and continuations are labelled from A
+    pub fn to_game(&self) -> Result<Game, String> {
+        let mut g = Game::new(self.game.board_size());
+        add_node(&mut g, &self.root)?;
+        Ok(g)
+    }
+}
+
+fn add_result(node: &LinkedTree<BookNode>, result: &str) {
+    let mut val = node.val();
+    val.stats.add(result);
+    node.set_val(val);
+}
+
+fn sorted_children(node: &LinkedTree<BookNode>) -> Vec<LinkedTree<BookNode>> {
+    let mut children: Vec<LinkedTree<BookNode>> = (0..node.child_len())
+        .map(|i| node.child(i).unwrap())
+        .collect();
+    children.sort_by(|a, b| {
+        let (a, b) = (a.val(), b.val());
+        b.stats
+            .games
+            .cmp(&a.stats.games)
+            .then(a.step.to_string().cmp(&b.step.to_string()))
+    });
+    children
+}
+
+fn add_node(g: &mut Game, node: &LinkedTree<BookNode>) -> Result<(), String> {
+    let size = g.board().lines();
+    let children = sorted_children(node);
+    let mut note = Note {
+        comment: node.val().stats.to_string(),
+        ..Default::default()
+    };
+    for (i, child) in children.iter().enumerate().take(26) {
+        if let Cmd::Step(p) = child.val().step {
+            let (x, y) = Notation::Sgf.parse(&p, size)?;
+            note.mark(x, y, Mark::Label(((b'A' + i as u8) as char).to_string()));
+        }
+    }
+    g.set_note(note);
+    for child in children {
+        g.next(child.val().step)?;
+        add_node(g, &child)?;
+        g.undo()?;
+    }
+    Ok(())
+}
+
+// turn the steps by the symmetry giving the smallest point at each step,
+// only symmetries keeping the position so far are taken for later steps
+pub fn normalize_steps(steps: &[Cmd], size: usize) -> Result<Vec<Cmd>, String> {
+    let mut symmetries = SYMMETRIES.to_vec();
+    let mut normalized = vec![];
+    for step in steps {
+        let mut best: Option<Cmd> = None;
+        let mut kept = vec![];
+        for symmetry in symmetries {
+            let t = step.transform(symmetry, size)?;
+            let order = |c: &Cmd| c.to_string();
+            match &best {
+                Some(b) if order(&t) > order(b) => continue,
+                Some(b) if order(&t) == order(b) => kept.push(symmetry),
+                _ => {
+                    best = Some(t);
+                    kept = vec![symmetry];
+                }
+            }
+        }
+        symmetries = kept;
+        normalized.push(best.unwrap());
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    fn steps(points: &[&str]) -> Vec<Cmd> {
+        points.iter().map(|p| Cmd::Step(p.to_string())).collect()
+    }
+
+    #[test]
+    fn explore_book() {
+        let mut book = Book::new(BoardSize::Normal);
+        let games = [
+            (["pd", "dp", "pq"], "B+R"),
+            (["dd", "pp", "dq"], "W+1.5"),
+            (["pd", "dd", "pq"], "B+3.5"),
+        ];
+        for (points, result) in games {
+            let normalized = normalize_steps(&steps(&points), 19).unwrap();
+            book.add_game(&normalized, result, 2);
+        }
+        // all first steps are the same star point, the second ones are
+        // the opposite or the next corner
+        assert!(book.stats().games == 3);
+        let next = book.continuations();
+        assert!(next.len() == 1 && next[0].1.win_rate(Stone::Black) == Some(66));
+        book.play(&next[0].0).unwrap();
+        let next = book.continuations();
+        assert!(next.len() == 2 && next[0].1.games == 2 && next[1].1.games == 1);
+        assert!(book.play(&Cmd::Step(String::from("aa"))).is_err());
+        book.play(&next[1].0.clone()).unwrap();
+        assert!(book.game().step_count() == 2 && book.continuations().is_empty());
+        book.back().unwrap();
+        book.back().unwrap();
+        assert!(book.back().is_err());
+
+        let sgf = book.to_game().unwrap().to_sgf();
+        assert!(sgf.contains("C[3 games, black wins 66%, white wins 33%]"));
+        assert!(sgf.contains("LB[") && sgf.matches(";W[").count() == 2);
+
+        // the handicap game is left out
+        let dir = TempDir::new("book");
+        std::fs::write(dir.file("even.sgf"), "(;SZ[9];B[ee];W[cc])").unwrap();
+        std::fs::write(dir.file("handicap.sgf"), "(;SZ[9]AB[gc][cg];W[cc])").unwrap();
+        let mut db = Database::open(dir.path()).unwrap();
+        db.update().unwrap();
+        let book = Book::from_database(&db, BoardSize::Small, 2, false).unwrap();
+        let next = book.continuations();
+        assert!(book.stats().games == 1 && next.len() == 1 && next[0].0.to_string() == "ee");
+    }
+}
@@ -0,0 +1,342 @@
+use crate::basic::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+// index file in the indexed directory:
+//
+//     rustgo/db
+//     version 1
+//     game <modified secs> <size> <path>
+//     info <key> <value>
+//     setup <point> <point> ...
+//     steps <step> <step> ...
+//     positions <hash> <hash> ...
+//
+// path is relative to the directory, setup is the black stones put before
+// the first step in sgf points, steps are the main line in sgf points
+// and pass, positions are canonical hashes of the main line from the empty
+// board, the i-th one is after step i.
+pub const INDEX_FILE: &str = ".rustgo-index";
+const INDEX_MAGIC: &str = "rustgo/db";
+const INDEX_VERSION: usize = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameEntry {
+    pub path: String,
+    pub modified: u64,
+    pub size: BoardSize,
+    pub info: GameInfo,
+    // black stones before the first step, white plays first if there are
+    pub setup: Vec<(usize, usize)>,
+    pub steps: Vec<Cmd>,
+    pub positions: Vec<u64>,
+}
+
+impl GameEntry {
+    fn from_game(path: String, modified: u64, g: &Game) -> GameEntry {
+        GameEntry {
+            path,
+            modified,
+            size: g.board_size(),
+            info: g.info().clone(),
+            setup: g.handicap_stones(),
+            steps: g.steps(),
+            positions: g
+                .positions()
+                .iter()
+                .map(|zb| canonical_hash(&unzip_board(zb), false))
+                .collect(),
+        }
+    }
+
+    // boards of the main line, the first one has the setup stones
+    pub fn boards(&self) -> Result<Vec<Board>, String> {
+        let mut g = Game::new(self.size.clone());
+        g.set_handicap_stones(&self.setup)?;
+        let mut boards = vec![g.board().clone()];
+        for step in self.steps.iter() {
+            g.next(step.clone())?;
+            boards.push(g.board().clone());
+        }
+        Ok(boards)
+    }
+}
+
+// games of a directory, the index is kept in INDEX_FILE of it
+pub struct Database {
+    dir: String,
+    games: Vec<GameEntry>,
+    // position hash to (index of game, step)
+    positions: HashMap<u64, Vec<(usize, usize)>>,
+}
+
+impl Database {
+    // load the index of dir, it is empty if there is no index yet
+    pub fn open(dir: &str) -> Result<Database, String> {
+        let mut db = Database {
+            dir: dir.to_string(),
+            games: vec![],
+            positions: HashMap::new(),
+        };
+        let filename = Path::new(dir).join(INDEX_FILE);
+        if filename.exists() {
+            let data = fs::read_to_string(&filename)
+                .map_err(|err| format!("can not read {}: {}", filename.display(), err))?;
+            db.games = parse_index(&data)
+                .map_err(|err| format!("invalid index {}, {}", filename.display(), err))?;
+        }
+        db.build_positions();
+        Ok(db)
+    }
+
+    pub fn games(&self) -> &[GameEntry] {
+        &self.games
+    }
+
+    // scan sgf and dump files of the directory and its sub directories,
+    // only new and changed files are loaded. errors of files which can not
+    // be loaded are returned, the files are left out of the index.
+    pub fn update(&mut self) -> Result<Vec<String>, String> {
+        let mut files = vec![];
+        list_files(Path::new(&self.dir), &mut files)?;
+        files.sort();
+        let mut old: HashMap<String, GameEntry> = self
+            .games
+            .drain(..)
+            .map(|entry| (entry.path.clone(), entry))
+            .collect();
+        let mut errors = vec![];
+        for file in files {
+            let path = file
+                .strip_prefix(&self.dir)
+                .unwrap_or(&file)
+                .to_string_lossy()
+                .trim_start_matches('/')
+                .to_string();
+            let modified = fs::metadata(&file)
+                .and_then(|m| m.modified())
+                .map(|t| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs())
+                .map_err(|err| err.to_string())?;
+            if let Some(entry) = old.remove(&path) {
+                if entry.modified == modified && entry.positions.len() == entry.steps.len() + 1 {
+                    self.games.push(entry);
+                    continue;
+                }
+            }
+            let data = match fs::read_to_string(&file) {
+                Ok(data) => data,
+                Err(err) => {
+                    errors.push(format!("{}: {}", path, err));
+                    continue;
+                }
+            };
+            if !data.trim_start().starts_with('(') && !data.starts_with(DUMP_MAGIC) {
+                continue;
+            }
+            match Game::load_from(&mut data.as_bytes()) {
+                Ok(g) => self.games.push(GameEntry::from_game(path, modified, &g)),
+                Err(err) => errors.push(format!("{}: {}", path, err)),
+            }
+        }
+        self.build_positions();
+        self.save()?;
+        Ok(errors)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let filename = Path::new(&self.dir).join(INDEX_FILE);
+        fs::write(&filename, dump_index(&self.games))
+            .map_err(|err| format!("can not write {}: {}", filename.display(), err))
+    }
+
+    // games reaching the position in any symmetry, with the steps of it
+    pub fn find_position(&self, board: &Board) -> Vec<(&GameEntry, Vec<usize>)> {
+        let hash = canonical_hash(board, false);
+        let mut found: Vec<(&GameEntry, Vec<usize>)> = vec![];
+        for &(i, step) in self.positions.get(&hash).into_iter().flatten() {
+            let game = &self.games[i];
+            if game.size != board.size() {
+                continue;
+            }
+            match found.last_mut() {
+                Some((last, steps)) if std::ptr::eq(*last, game) => steps.push(step),
+                _ => found.push((game, vec![step])),
+            }
+        }
+        found
+    }
+
+    fn build_positions(&mut self) {
+        self.positions.clear();
+        for (i, game) in self.games.iter().enumerate() {
+            for (step, hash) in game.positions.iter().enumerate() {
+                self.positions.entry(*hash).or_default().push((i, step));
+            }
+        }
+    }
+}
+
+fn list_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) -> Result<(), String> {
+    let entries =
+        fs::read_dir(dir).map_err(|err| format!("can not read {}: {}", dir.display(), err))?;
+    for entry in entries {
+        let path = entry.map_err(|err| err.to_string())?.path();
+        if path.is_dir() {
+            list_files(&path, files)?;
+        } else if path.file_name().is_some_and(|name| name != INDEX_FILE) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn dump_index(games: &[GameEntry]) -> String {
+    let mut data = format!("{}\nversion {}\n", INDEX_MAGIC, INDEX_VERSION);
+    for game in games {
+        data.push_str(&format!(
+            "game {} {} {}\n",
+            game.modified,
+            game.size.lines(),
+            escape(&game.path)
+        ));
+        for (key, value) in game.info.fields() {
+            data.push_str(&format!("info {} {}\n", key, escape(&value)));
+        }
+        if !game.setup.is_empty() {
+            let points: Vec<String> = game
+                .setup
+                .iter()
+                .map(|(x, y)| Cmd::point_to_cmd(*x, *y))
+                .collect();
+            data.push_str(&format!("setup {}\n", points.join(" ")));
+        }
+        let steps: Vec<String> = game.steps.iter().map(|s| s.to_string()).collect();
+        data.push_str(&format!("steps {}\n", steps.join(" ")));
+        let hashes: Vec<String> = game.positions.iter().map(|h| format!("{:x}", h)).collect();
+        data.push_str(&format!("positions {}\n", hashes.join(" ")));
+    }
+    data
+}
+
+fn parse_index(data: &str) -> Result<Vec<GameEntry>, String> {
+    let mut lines = data.lines().enumerate();
+    if lines.next().map(|(_, l)| l) != Some(INDEX_MAGIC) {
+        return Err(String::from("line 1: not a rustgo index"));
+    }
+    let mut games: Vec<GameEntry> = vec![];
+    for (n, line) in lines {
+        let at = |err: String| format!("line {}: {}", n + 1, err);
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "version" => {
+                let version: usize = value
+                    .parse()
+                    .map_err(|_| at(format!("invalid version {}", value)))?;
+                if version > INDEX_VERSION {
+                    return Err(at(format!("unsupported version {}", version)));
+                }
+            }
+            "game" => {
+                let mut fields = value.splitn(3, ' ');
+                let modified = fields.next().unwrap_or("");
+                let modified = modified
+                    .parse()
+                    .map_err(|_| at(format!("invalid modified time {}", modified)))?;
+                let size = match fields.next() {
+                    Some("19") => BoardSize::Normal,
+                    Some("13") => BoardSize::Medium,
+                    Some("9") => BoardSize::Small,
+                    other => return Err(at(format!("invalid board size {:?}", other))),
+                };
+                let path = unescape(fields.next().unwrap_or(""));
+                games.push(GameEntry {
+                    path,
+                    modified,
+                    size,
+                    info: GameInfo::default(),
+                    setup: vec![],
+                    steps: vec![],
+                    positions: vec![],
+                });
+            }
+            "info" | "setup" | "steps" | "positions" => {
+                let game = games
+                    .last_mut()
+                    .ok_or_else(|| at(format!("{} before game", key)))?;
+                if key == "info" {
+                    let (key, value) = value.split_once(' ').unwrap_or((value, ""));
+                    game.info.set_from_file(key, &unescape(value)).map_err(at)?;
+                    continue;
+                }
+                if key == "setup" {
+                    for point in value.split_whitespace() {
+                        let size = game.size.lines();
+                        game.setup
+                            .push(Notation::Sgf.parse(point, size).map_err(at)?);
+                    }
+                    continue;
+                }
+                if key == "steps" {
+                    for step in value.split_whitespace() {
+                        game.steps.push(match step {
+                            "pass" => Cmd::Pass,
+                            other => Cmd::Step(other.to_string()),
+                        });
+                    }
+                    continue;
+                }
+                for hash in value.split_whitespace() {
+                    let hash = u64::from_str_radix(hash, 16)
+                        .map_err(|_| at(format!("invalid hash {}", hash)))?;
+                    game.positions.push(hash);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn index_directory() {
+        let temp = TempDir::new("index");
+        let dir = temp.path();
+        let mut g = Game::new(BoardSize::Small);
+        g.set_info("black", "Honinbo Shusaku").unwrap();
+        for step in ["cc", "gg", "cg"] {
+            g.next(Cmd::Step(step.to_string())).unwrap();
+        }
+        g.dump_to(&format!("{}/a.sgf", dir)).unwrap();
+        fs::create_dir(format!("{}/more", dir)).unwrap();
+        g.dump_to(&format!("{}/more/b.txt", dir)).unwrap();
+        fs::write(format!("{}/notes.txt", dir), "not a game").unwrap();
+        fs::write(format!("{}/bad.sgf", dir), "(;SZ[9];B[zz])").unwrap();
+        // free placement, which is not at the handicap points
+        fs::write(format!("{}/setup.sgf", dir), "(;SZ[9]AB[aa][ba];W[ee])").unwrap();
+
+        let mut db = Database::open(dir).unwrap();
+        let errors = db.update().unwrap();
+        assert!(errors.len() == 1 && errors[0].starts_with("bad.sgf: "));
+        assert!(db.games().len() == 3 && db.games()[2].setup == vec![(1, 1), (2, 1)]);
+        assert!(db.games()[0].path == "a.sgf" && db.games()[1].path == "more/b.txt");
+        assert!(db.games()[0].positions.len() == 4);
+        assert!(db.games()[0].boards().unwrap()[3] == *g.board());
+
+        let loaded = Database::open(dir).unwrap();
+        assert!(loaded.games() == db.games());
+        assert!(loaded.games()[1].info.black == "Honinbo Shusaku");
+        let boards = loaded.games()[2].boards().unwrap();
+        assert!(boards[1].at(1, 1) == Ok(Stone::Black) && boards[1].at(5, 5) == Ok(Stone::White));
+        let mut board = Board::new(BoardSize::Small);
+        board.add(Stone::Black, 7, 7).unwrap();
+        let found = loaded.find_position(&board);
+        assert!(found.len() == 2 && found[0].1 == vec![1]);
+    }
+}
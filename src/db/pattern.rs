@@ -0,0 +1,452 @@
+use crate::basic::*;
+use crate::db::{Database, Match, Query};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Cell {
+    Black,
+    White,
+    Empty,
+    Any,
+}
+
+// a rectangle of cells, which can be matched at any place of the board
+// unless it is bounded by edges of the board. in text, X is black, O is
+// white, . is empty, ? is any, a line of - is the top or bottom edge and
+// | at the start or end of rows is the left or right edge:
+//
+//     ------
+//     | . . . ?
+//     | . . X .
+//     | . O . ?
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    // top, right, bottom, left
+    edges: [bool; 4],
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rows: Vec<Vec<Cell>> = vec![];
+        let mut edges = [false; 4];
+        for line in s.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            if line.chars().all(|c| c == '-' || c == '+') {
+                edges[if rows.is_empty() { 0 } else { 2 }] = true;
+                continue;
+            }
+            let mut row = vec![];
+            let chars: Vec<char> = line.chars().filter(|c| *c != ' ').collect();
+            for (i, c) in chars.iter().enumerate() {
+                match c {
+                    'X' | 'x' => row.push(Cell::Black),
+                    'O' | 'o' => row.push(Cell::White),
+                    '.' | ',' => row.push(Cell::Empty),
+                    '?' | '*' => row.push(Cell::Any),
+                    '|' if i == 0 => edges[3] = true,
+                    '|' if i == chars.len() - 1 => edges[1] = true,
+                    other => return Err(format!("invalid pattern cell: {}", other)),
+                }
+            }
+            if !rows.is_empty() && rows[0].len() != row.len() {
+                return Err(String::from("rows of pattern are not in the same length"));
+            }
+            rows.push(row);
+        }
+        let cells: Vec<Cell> = rows.concat();
+        if !cells.iter().any(|c| *c == Cell::Black || *c == Cell::White) {
+            return Err(String::from("pattern has no stone"));
+        }
+        Ok(Pattern {
+            width: rows[0].len(),
+            height: rows.len(),
+            cells,
+            edges,
+        })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line = format!("{}\n", "-".repeat(self.width * 2 + 1));
+        if self.edges[0] {
+            write!(f, "{}", line)?;
+        }
+        for j in 0..self.height {
+            let row: Vec<&str> = (0..self.width)
+                .map(|i| match self.at(i, j) {
+                    Cell::Black => "X",
+                    Cell::White => "O",
+                    Cell::Empty => ".",
+                    Cell::Any => "?",
+                })
+                .collect();
+            let left = if self.edges[3] { "| " } else { "" };
+            let right = if self.edges[1] { " |" } else { "" };
+            writeln!(f, "{}{}{}", left, row.join(" "), right)?;
+        }
+        if self.edges[2] {
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Pattern {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // cell at column i and row j, 0 based
+    pub fn at(&self, i: usize, j: usize) -> Cell {
+        self.cells[j * self.width + i]
+    }
+
+    pub fn transform(&self, symmetry: Symmetry) -> Pattern {
+        let (width, height) = match symmetry {
+            Symmetry::Rotate90
+            | Symmetry::Rotate270
+            | Symmetry::Transpose
+            | Symmetry::AntiTranspose => (self.height, self.width),
+            _ => (self.width, self.height),
+        };
+        let mut cells = vec![Cell::Any; width * height];
+        for j in 0..self.height {
+            for i in 0..self.width {
+                let (ti, tj) = cell_point(symmetry, i, j, self.width, self.height);
+                cells[tj * width + ti] = self.at(i, j);
+            }
+        }
+        let [top, right, bottom, left] = self.edges;
+        let edges = match symmetry {
+            Symmetry::Identity => [top, right, bottom, left],
+            Symmetry::Rotate90 => [left, top, right, bottom],
+            Symmetry::Rotate180 => [bottom, left, top, right],
+            Symmetry::Rotate270 => [right, bottom, left, top],
+            Symmetry::FlipHorizontal => [top, left, bottom, right],
+            Symmetry::FlipVertical => [bottom, right, top, left],
+            Symmetry::Transpose => [left, bottom, right, top],
+            Symmetry::AntiTranspose => [right, top, left, bottom],
+        };
+        Pattern {
+            width,
+            height,
+            cells,
+            edges,
+        }
+    }
+
+    pub fn swap_colors(&self) -> Pattern {
+        let cells = self
+            .cells
+            .iter()
+            .map(|c| match c {
+                Cell::Black => Cell::White,
+                Cell::White => Cell::Black,
+                other => *other,
+            })
+            .collect();
+        Pattern {
+            cells,
+            ..self.clone()
+        }
+    }
+
+    // different patterns of all symmetries, and of colour swapped ones if
+    // colors is true, with the symmetry and whether colours are swapped
+    pub fn variants(&self, colors: bool) -> Vec<(Pattern, Symmetry, bool)> {
+        let mut variants: Vec<(Pattern, Symmetry, bool)> = vec![];
+        for swapped in [false, true] {
+            if swapped && !colors {
+                break;
+            }
+            for symmetry in SYMMETRIES {
+                let mut p = self.transform(symmetry);
+                if swapped {
+                    p = p.swap_colors();
+                }
+                if !variants.iter().any(|(v, _, _)| *v == p) {
+                    variants.push((p, symmetry, swapped));
+                }
+            }
+        }
+        variants
+    }
+
+    // whether the pattern matches with its top left cell at (x, y)
+    pub fn matches_at(&self, board: &Board, x: usize, y: usize) -> bool {
+        let size = board.lines();
+        if x < 1 || y < 1 || x + self.width - 1 > size || y + self.height - 1 > size {
+            return false;
+        }
+        let [top, right, bottom, left] = self.edges;
+        if (top && y != 1)
+            || (left && x != 1)
+            || (bottom && y + self.height - 1 != size)
+            || (right && x + self.width - 1 != size)
+        {
+            return false;
+        }
+        for j in 0..self.height {
+            for i in 0..self.width {
+                let stone = board.at(x + i, y + j).unwrap();
+                let matched = match self.at(i, j) {
+                    Cell::Black => stone == Stone::Black,
+                    Cell::White => stone == Stone::White,
+                    Cell::Empty => stone == Stone::Empty,
+                    Cell::Any => true,
+                };
+                if !matched {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    // top left points where the pattern matches
+    pub fn find(&self, board: &Board) -> Vec<(usize, usize)> {
+        let size = board.lines();
+        let mut found = vec![];
+        for y in 1..=size {
+            for x in 1..=size {
+                if self.matches_at(board, x, y) {
+                    found.push((x, y));
+                }
+            }
+        }
+        found
+    }
+}
+
+// cell (i, j) of width * height rectangle moves to
+fn cell_point(
+    symmetry: Symmetry,
+    i: usize,
+    j: usize,
+    width: usize,
+    height: usize,
+) -> (usize, usize) {
+    match symmetry {
+        Symmetry::Identity => (i, j),
+        Symmetry::Rotate90 => (height - 1 - j, i),
+        Symmetry::Rotate180 => (width - 1 - i, height - 1 - j),
+        Symmetry::Rotate270 => (j, width - 1 - i),
+        Symmetry::FlipHorizontal => (width - 1 - i, j),
+        Symmetry::FlipVertical => (i, height - 1 - j),
+        Symmetry::Transpose => (j, i),
+        Symmetry::AntiTranspose => (height - 1 - j, width - 1 - i),
+    }
+}
+
+// step played after the pattern appears, point is the cell of the pattern
+// as it is given, none if the step is out of the pattern or pass. stone is
+// the colour in the pattern as it is given.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NextMove {
+    pub point: Option<(usize, usize)>,
+    pub stone: Stone,
+    pub count: usize,
+}
+
+pub struct PatternResult<'a> {
+    // steps are where the pattern appears
+    pub matches: Vec<Match<'a>>,
+    // the most played first
+    pub next_moves: Vec<NextMove>,
+}
+
+impl Database {
+    // games matching query which contain the pattern in any symmetry, and in
+    // swapped colours if colors is true. only points changed by each step
+    // are checked, so a pattern staying on the board is found once.
+    pub fn search_pattern(
+        &self,
+        query: &Query,
+        pattern: &Pattern,
+        colors: bool,
+    ) -> Result<PatternResult<'_>, String> {
+        let variants = pattern.variants(colors);
+        let mut matches = vec![];
+        let mut next: HashMap<(Option<(usize, usize)>, Stone), usize> = HashMap::new();
+        for m in self.search(query) {
+            let boards = m.game.boards()?;
+            let size = boards[0].lines();
+            let mut steps = vec![];
+            for s in 1..boards.len() {
+                let (before, after) = (&boards[s - 1], &boards[s]);
+                let mut found: HashSet<(usize, usize, usize)> = HashSet::new();
+                for (cx, cy) in changed_points(before, after) {
+                    for (v, (variant, _, _)) in variants.iter().enumerate() {
+                        for y in cy.saturating_sub(variant.height - 1).max(1)..=cy {
+                            for x in cx.saturating_sub(variant.width - 1).max(1)..=cx {
+                                if variant.matches_at(after, x, y)
+                                    && !variant.matches_at(before, x, y)
+                                {
+                                    found.insert((v, x, y));
+                                }
+                            }
+                        }
+                    }
+                }
+                if found.is_empty() {
+                    continue;
+                }
+                steps.push(s);
+                // the step after, in the cells of the given pattern
+                let Some(step) = m.game.steps.get(s) else {
+                    continue;
+                };
+                // black plays odd steps unless setup stones are put
+                let black_first = m.game.setup.is_empty();
+                let stone = if (s % 2 == 0) == black_first {
+                    Stone::Black
+                } else {
+                    Stone::White
+                };
+                for (v, x, y) in found {
+                    let (variant, symmetry, swapped) = &variants[v];
+                    let point = match step {
+                        Cmd::Step(p) => Notation::Sgf.parse(p, size).ok().and_then(|(px, py)| {
+                            if px < x
+                                || py < y
+                                || px >= x + variant.width
+                                || py >= y + variant.height
+                            {
+                                return None;
+                            }
+                            Some(cell_point(
+                                symmetry.inverse(),
+                                px - x,
+                                py - y,
+                                variant.width,
+                                variant.height,
+                            ))
+                        }),
+                        _ => None,
+                    };
+                    let stone = if *swapped { stone.another() } else { stone };
+                    *next.entry((point, stone)).or_default() += 1;
+                }
+            }
+            if !steps.is_empty() {
+                matches.push(Match {
+                    game: m.game,
+                    steps,
+                });
+            }
+        }
+        let mut next_moves: Vec<NextMove> = next
+            .into_iter()
+            .map(|((point, stone), count)| NextMove {
+                point,
+                stone,
+                count,
+            })
+            .collect();
+        next_moves.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(a.point.cmp(&b.point))
+                .then((a.stone == Stone::White).cmp(&(b.stone == Stone::White)))
+        });
+        Ok(PatternResult {
+            matches,
+            next_moves,
+        })
+    }
+}
+
+fn changed_points(before: &Board, after: &Board) -> Vec<(usize, usize)> {
+    let size = before.lines();
+    let mut points = vec![];
+    for y in 1..=size {
+        for x in 1..=size {
+            if before.at(x, y).unwrap() != after.at(x, y).unwrap() {
+                points.push((x, y));
+            }
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn pattern_variants() {
+        let p: Pattern = "---\n| . X\n| . .".parse().unwrap();
+        assert!(p.to_string() == "-----\n| . X\n| . .\n");
+        assert!(p.variants(false).len() == 8);
+        assert!(p.variants(true).len() == 16);
+        let r = p.transform(Symmetry::Rotate90);
+        assert!(r.to_string() == "-----\n. . |\n. X |\n");
+        assert!(r.transform(Symmetry::Rotate270) == p);
+        assert!("..\n..".parse::<Pattern>().is_err());
+
+        let mut b = Board::new(BoardSize::Small);
+        b.add(Stone::Black, 2, 1).unwrap();
+        b.add(Stone::Black, 9, 2).unwrap();
+        assert!(p.find(&b) == vec![(1, 1)]);
+        assert!(r.find(&b) == vec![(8, 1)]);
+        // not at the edge
+        let p: Pattern = ". X\n. .".parse().unwrap();
+        assert!(p.find(&b) == vec![(1, 1), (8, 2)]);
+    }
+
+    #[test]
+    fn search_pattern_in_games() {
+        let dir = TempDir::new("pattern");
+        // the same shape in 2 corners with the same next step
+        let games = [
+            ("a.sgf", ["dd", "cc", "dc", "pp"]),
+            ("b.sgf", ["pp", "qq", "pq", "dc"]),
+            ("c.sgf", ["dc", "cc", "pd", "ec"]),
+        ];
+        for (file, steps) in games {
+            let mut g = Game::new(BoardSize::Normal);
+            for step in steps {
+                g.next(Cmd::Step(step.to_string())).unwrap();
+            }
+            g.dump_to(&dir.file(file)).unwrap();
+        }
+        let mut db = Database::open(dir.path()).unwrap();
+        db.update().unwrap();
+
+        // black stone at 4-4 with white at 3-3 under it
+        let p: Pattern = "? ? ?\n? O ?\n? ? X".parse().unwrap();
+        let result = db.search_pattern(&Query::default(), &p, false).unwrap();
+        let found: Vec<(&str, Vec<usize>)> = result
+            .matches
+            .iter()
+            .map(|m| (m.game.path.as_str(), m.steps.clone()))
+            .collect();
+        assert!(found == vec![("a.sgf", vec![2]), ("b.sgf", vec![2])]);
+        assert!(
+            result.next_moves
+                == vec![NextMove {
+                    point: Some((2, 1)),
+                    stone: Stone::Black,
+                    count: 2,
+                }]
+        );
+        // a white stone between black ones is found with colours swapped
+        let p: Pattern = "X O X".parse().unwrap();
+        let result = db.search_pattern(&Query::default(), &p, false).unwrap();
+        assert!(result.matches.is_empty());
+        let result = db.search_pattern(&Query::default(), &p, true).unwrap();
+        assert!(result.matches.len() == 1 && result.matches[0].steps == vec![4]);
+    }
+}
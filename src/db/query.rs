@@ -0,0 +1,162 @@
+use crate::basic::*;
+use crate::db::{Database, GameEntry};
+
+// conditions of games, none of them means all games
+//  - player, black, white: part of the name, in any case
+//  - date: prefix like 2019-03, or range like 2018..2019-06
+//  - result: prefix like B+, W+R or 0
+//  - position: games reach the position in any symmetry
+#[derive(Default)]
+pub struct Query {
+    pub player: Option<String>,
+    pub black: Option<String>,
+    pub white: Option<String>,
+    pub date: Option<String>,
+    pub result: Option<String>,
+    pub position: Option<Board>,
+}
+
+// a game found, steps are where the position is reached
+pub struct Match<'a> {
+    pub game: &'a GameEntry,
+    pub steps: Vec<usize>,
+}
+
+impl Database {
+    pub fn search(&self, query: &Query) -> Vec<Match<'_>> {
+        let candidates: Vec<Match> = match &query.position {
+            Some(board) => self
+                .find_position(board)
+                .into_iter()
+                .map(|(game, steps)| Match { game, steps })
+                .collect(),
+            None => self
+                .games()
+                .iter()
+                .map(|game| Match {
+                    game,
+                    steps: vec![],
+                })
+                .collect(),
+        };
+        candidates
+            .into_iter()
+            .filter(|m| query.matches(&m.game.info))
+            .collect()
+    }
+}
+
+impl Query {
+    fn matches(&self, info: &GameInfo) -> bool {
+        let name = |pattern: &Option<String>, names: &[&str]| match pattern {
+            Some(p) => {
+                let p = p.to_lowercase();
+                names.iter().any(|n| n.to_lowercase().contains(&p))
+            }
+            None => true,
+        };
+        name(&self.player, &[&info.black, &info.white])
+            && name(&self.black, &[&info.black])
+            && name(&self.white, &[&info.white])
+            && self
+                .date
+                .as_ref()
+                .is_none_or(|d| date_matches(d, &info.date))
+            && self
+                .result
+                .as_ref()
+                .is_none_or(|r| info.result.to_uppercase().starts_with(&r.to_uppercase()))
+    }
+}
+
+// the end of a range matches all dates starting with it
+fn date_matches(pattern: &str, date: &str) -> bool {
+    if date.is_empty() {
+        return false;
+    }
+    match pattern.split_once("..") {
+        Some((from, to)) => date >= from && (to.is_empty() || date <= to || date.starts_with(to)),
+        None => date.starts_with(pattern),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn search_games() {
+        let dir = TempDir::new("search");
+        let games = [
+            (
+                "a.sgf",
+                "Go Seigen",
+                "Kitani Minoru",
+                "1933-10-16",
+                "B+2",
+                "dd",
+            ),
+            (
+                "b.sgf",
+                "Kitani Minoru",
+                "Go Seigen",
+                "1934-01-05",
+                "W+R",
+                "pd",
+            ),
+            (
+                "c.sgf",
+                "Honinbo Shusaku",
+                "Gennan Inseki",
+                "1846-09-11",
+                "B+2",
+                "cc",
+            ),
+        ];
+        for (file, black, white, date, result, step) in games {
+            let mut g = Game::new(BoardSize::Normal);
+            g.set_info("black", black).unwrap();
+            g.set_info("white", white).unwrap();
+            g.set_info("date", date).unwrap();
+            g.set_info("result", result).unwrap();
+            g.next(Cmd::Step(step.to_string())).unwrap();
+            g.dump_to(&dir.file(file)).unwrap();
+        }
+        let mut db = Database::open(dir.path()).unwrap();
+        db.update().unwrap();
+        let paths = |query: &Query| -> Vec<String> {
+            db.search(query)
+                .iter()
+                .map(|m| m.game.path.clone())
+                .collect()
+        };
+
+        let query = Query {
+            player: Some(String::from("go seigen")),
+            ..Default::default()
+        };
+        assert!(paths(&query) == vec!["a.sgf", "b.sgf"]);
+        let query = Query {
+            black: Some(String::from("Go")),
+            result: Some(String::from("b+")),
+            ..Default::default()
+        };
+        assert!(paths(&query) == vec!["a.sgf"]);
+        let query = Query {
+            date: Some(String::from("1800..1933")),
+            ..Default::default()
+        };
+        assert!(paths(&query) == vec!["a.sgf", "c.sgf"]);
+
+        // star point in any corner
+        let mut board = Board::new(BoardSize::Normal);
+        board.add(Stone::Black, 16, 16).unwrap();
+        let query = Query {
+            position: Some(board),
+            ..Default::default()
+        };
+        let found = db.search(&query);
+        assert!(found.len() == 2 && found[1].game.path == "b.sgf" && found[1].steps == vec![1]);
+    }
+}
@@ -0,0 +1,21 @@
+mod arena;
+mod base;
+mod gtp;
+mod katago;
+mod mcts;
+mod playout;
+mod policy;
+mod random;
+mod review;
+mod selfplay;
+
+pub use arena::*;
+pub use base::*;
+pub use gtp::*;
+pub use katago::*;
+pub use mcts::*;
+pub use playout::*;
+pub use policy::*;
+pub use random::*;
+pub use review::*;
+pub use selfplay::*;
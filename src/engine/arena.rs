@@ -0,0 +1,278 @@
+use crate::basic::*;
+use crate::engine::{
+    AnalysisClient, Engine, EngineMove, GtpEngine, KataGoEngine, MctsEngine, RandomEngine,
+};
+use crate::util::Rng;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchOptions {
+    pub games: usize,
+    pub size: BoardSize,
+    pub komi: f64,
+    // games are saved in dir as game-<n>.sgf if given
+    pub dir: Option<String>,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        MatchOptions {
+            games: 10,
+            size: BoardSize::Small,
+            komi: 6.5,
+            dir: None,
+        }
+    }
+}
+
+// wins of the first and the second engine of a match
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MatchStats {
+    pub games: usize,
+    pub wins: [usize; 2],
+    pub draws: usize,
+    pub black_wins: usize,
+    pub white_wins: usize,
+}
+
+impl MatchStats {
+    // engine is 0 for the first one, which played the colour
+    fn add(&mut self, engine: usize, black: bool, result: &str) {
+        self.games += 1;
+        let winner = match result.chars().next() {
+            Some('B') => Player::Black,
+            Some('W') => Player::White,
+            _ => {
+                self.draws += 1;
+                return;
+            }
+        };
+        if winner == Player::Black {
+            self.black_wins += 1;
+        } else {
+            self.white_wins += 1;
+        }
+        if (winner == Player::Black) == black {
+            self.wins[engine] += 1;
+        } else {
+            self.wins[1 - engine] += 1;
+        }
+    }
+
+    // win rate of the engine in percent
+    pub fn win_rate(&self, engine: usize) -> usize {
+        self.wins[engine] * 100 / self.games.max(1)
+    }
+}
+
+// playouts of mcts when not given
+const DEFAULT_PLAYOUTS: usize = 1000;
+
+// random, mcts[:<playouts>], mcts-uniform[:<playouts>] of random playouts,
+// gtp:<command> or katago:<command> of a json analysis engine. the built-in
+// engines take their random numbers from rng.
+pub fn create_engine(spec: &str, rng: Rng) -> Result<Box<dyn Engine>, String> {
+    let (kind, option) = match spec.split_once(':') {
+        Some((kind, option)) => (kind, Some(option)),
+        None => (spec, None),
+    };
+    match (kind, option) {
+        ("random", None) => Ok(Box::new(RandomEngine::new(rng))),
+        ("mcts" | "mcts-uniform", _) => {
+            let playouts = match option {
+                Some(playouts) => playouts
+                    .parse()
+                    .map_err(|_| format!("invalid playouts: {}", playouts))?,
+                None => DEFAULT_PLAYOUTS,
+            };
+            if kind == "mcts" {
+                Ok(Box::new(MctsEngine::new(playouts, rng)))
+            } else {
+                Ok(Box::new(MctsEngine::uniform(playouts, rng)))
+            }
+        }
+        ("gtp", Some(command)) => Ok(Box::new(GtpEngine::spawn(command)?)),
+        ("katago", Some(command)) => {
+            let client = AnalysisClient::spawn(command)?;
+            Ok(Box::new(KataGoEngine::new(client, None)))
+        }
+        _ => Err(format!("invalid engine: {}", spec)),
+    }
+}
+
+// play one game to the end. it is over by resign, 2 passes scored by area,
+// or a forfeit like B+F of an engine which plays an illegal step or fails.
+pub fn play_game(
+    black: &mut dyn Engine,
+    white: &mut dyn Engine,
+    size: BoardSize,
+    komi: f64,
+) -> Result<Game, String> {
+    let mut g = Game::new(size);
+    g.set_info("black", &black.name())?;
+    g.set_info("white", &white.name())?;
+    g.set_info("komi", &komi.to_string())?;
+    play_out(&mut g, black, white)?;
+    Ok(g)
+}
+
+// play a new game to the end like play_game, with the board size, komi and
+// handicap stones of it, which are told to the engines as steps of black
+pub fn play_out(
+    g: &mut Game,
+    black: &mut dyn Engine,
+    white: &mut dyn Engine,
+) -> Result<(), String> {
+    let size = g.board_size();
+    let komi = g.info().komi.unwrap_or_default();
+    // an engine which fails loses by forfeit, the error is the comment
+    let forfeit = |g: &mut Game, loser: &Player, err: String| {
+        let winner = if *loser == Player::Black { "W" } else { "B" };
+        g.set_comment(format!("{} failed: {}", loser, err));
+        g.set_info("result", &format!("{}+F", winner))
+    };
+    let stones = g.handicap_stones();
+    for player in [Player::Black, Player::White] {
+        let engine: &mut dyn Engine = match player {
+            Player::Black => &mut *black,
+            Player::White => &mut *white,
+        };
+        let ready = engine
+            .set_boardsize(size.clone())
+            .and_then(|_| engine.set_komi(komi))
+            .and_then(|_| engine.clear())
+            .and_then(|_| {
+                stones.iter().try_for_each(|(x, y)| {
+                    engine.play(Player::Black, Cmd::Step(Cmd::point_to_cmd(*x, *y)))
+                })
+            });
+        if let Err(err) = ready {
+            return forfeit(g, &player, err);
+        }
+    }
+    let lines = size.lines();
+    let mut passes = 0;
+    // games with ko may never end
+    while g.step_count() < lines * lines * 3 {
+        let player = g.next_player();
+        let (mover, other): (&mut dyn Engine, &mut dyn Engine) = match player {
+            Player::Black => (&mut *black, &mut *white),
+            Player::White => (&mut *white, &mut *black),
+        };
+        let step = match mover.genmove(player.clone()) {
+            Ok(EngineMove::Resign) => {
+                let winner = if player == Player::Black { "W" } else { "B" };
+                return g.set_info("result", &format!("{}+R", winner));
+            }
+            Ok(EngineMove::Play(step)) => step,
+            Err(err) => return forfeit(g, &player, err),
+        };
+        if let Err(err) = g.next(step.clone()) {
+            return forfeit(g, &player, format!("illegal step {}: {}", step, err));
+        }
+        if let Err(err) = other.play(player.clone(), step.clone()) {
+            return forfeit(g, &player.another(), err);
+        }
+        passes = if step == Cmd::Pass { passes + 1 } else { 0 };
+        if passes == 2 {
+            break;
+        }
+    }
+    let score = bouzy(g.board()).score(komi);
+    g.set_info("result", &format_score(score))?;
+    Ok(())
+}
+
+// play games between 2 engines, the first one takes black in odd games.
+// on_game is called after each game with its number from 1.
+pub fn run_match(
+    first: &mut dyn Engine,
+    second: &mut dyn Engine,
+    options: &MatchOptions,
+    mut on_game: impl FnMut(usize, &Game),
+) -> Result<MatchStats, String> {
+    if let Some(dir) = &options.dir {
+        fs::create_dir_all(dir).map_err(|err| format!("can not create {}: {}", dir, err))?;
+    }
+    let mut stats = MatchStats::default();
+    for n in 1..=options.games {
+        let first_black = n % 2 == 1;
+        let mut g = if first_black {
+            play_game(first, second, options.size.clone(), options.komi)?
+        } else {
+            play_game(second, first, options.size.clone(), options.komi)?
+        };
+        g.set_info("round", &n.to_string())?;
+        stats.add(0, first_black, &g.info().result);
+        if let Some(dir) = &options.dir {
+            let filename = Path::new(dir).join(format!("game-{:03}.sgf", n));
+            g.dump_to(&filename.to_string_lossy())?;
+        }
+        on_game(n, &g);
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn random_match() {
+        let mut first = RandomEngine::new(Rng::new(1));
+        let mut second = RandomEngine::new(Rng::new(2));
+        let dir = TempDir::new("match");
+        let options = MatchOptions {
+            games: 2,
+            dir: Some(dir.path().to_string()),
+            ..MatchOptions::default()
+        };
+        let mut results = vec![];
+        let stats = run_match(&mut first, &mut second, &options, |_, g| {
+            results.push(g.info().result.clone())
+        })
+        .unwrap();
+        assert!(stats.games == 2 && results.len() == 2);
+        assert!(stats.wins[0] + stats.wins[1] + stats.draws == 2);
+        assert!(stats.black_wins + stats.white_wins + stats.draws == 2);
+        let g = Game::load(dir.file("game-002.sgf")).unwrap();
+        assert!(g.info().round == "2" && g.info().result == results[1]);
+    }
+
+    #[test]
+    fn gtp_engine_process() {
+        // plays C7 and resigns, then answers in no protocol, and hangs at
+        // quit
+        let script = "n=0
+while read -r line; do
+  case \"$line\" in
+    name) printf '= stub\\n\\n' ;;
+    genmove*) n=$((n+1)); case $n in
+      1) printf '= C7\\n\\n' ;;
+      2) printf '= resign\\n\\n' ;;
+      *) printf '黒\\n\\n' ;;
+    esac ;;
+    play*) printf '=\\n\\n' ;;
+    quit) sleep 10 ;;
+    *) printf '=\\n\\n' ;;
+  esac
+done
+";
+        let dir = TempDir::new("gtp");
+        let path = dir.file("stub.sh");
+        fs::write(&path, script).unwrap();
+        let spec = format!("gtp:sh {}", path);
+        let mut stub = create_engine(&spec, Rng::new(1)).unwrap();
+        assert!(stub.name() == "stub");
+        let mut random = RandomEngine::new(Rng::new(2));
+        let g = play_game(stub.as_mut(), &mut random, BoardSize::Small, 6.5).unwrap();
+        assert!(g.steps()[0] == Cmd::Step(String::from("cc")) && g.info().result == "W+R");
+        let g = play_game(stub.as_mut(), &mut random, BoardSize::Small, 6.5).unwrap();
+        assert!(g.info().result == "W+F" && g.note().comment.starts_with("black failed: "));
+        let start = std::time::Instant::now();
+        drop(stub);
+        assert!(start.elapsed().as_secs() < 5);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::basic::*;
+use crate::engine::FastBoard;
+
+// what an engine answers to genmove
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineMove {
+    Play(Cmd),
+    Resign,
+}
+
+// what an engine thinks of the position. win rate is of the player to
+// move, score is the lead of black if the engine tells it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    pub best: Cmd,
+    pub win_rate: f64,
+    pub score: Option<f64>,
+    // the best step and the steps expected after it
+    pub variation: Vec<Cmd>,
+    // playouts of the steps searched, empty if the engine does not tell
+    pub visits: Vec<(Cmd, usize)>,
+}
+
+// a go program which is told the steps and asked for the next one, the
+// commands follow gtp. steps are in sgf notation, a colour can play twice
+// in a row which is taken as the other one passed between.
+pub trait Engine {
+    fn name(&self) -> String;
+
+    fn set_boardsize(&mut self, size: BoardSize) -> Result<(), String>;
+
+    fn set_komi(&mut self, komi: f64) -> Result<(), String>;
+
+    // empty board with the size and komi kept
+    fn clear(&mut self) -> Result<(), String>;
+
+    fn play(&mut self, player: Player, step: Cmd) -> Result<(), String>;
+
+    // the step of the player, which is played on the engine's board too
+    fn genmove(&mut self, player: Player) -> Result<EngineMove, String>;
+
+    // the position with the player to move, nothing is played
+    fn analyze(&mut self, _player: Player) -> Result<Analysis, String> {
+        Err(format!("{} can not analyze", self.name()))
+    }
+}
+
+// the game followed by built-in engines
+pub(crate) struct Position {
+    pub game: Game,
+    pub komi: f64,
+}
+
+impl Position {
+    pub fn new() -> Position {
+        Position {
+            game: Game::new(BoardSize::Normal),
+            komi: 7.5,
+        }
+    }
+
+    pub fn set_boardsize(&mut self, size: BoardSize) {
+        self.game = Game::new(size);
+    }
+
+    pub fn clear(&mut self) {
+        self.game = Game::new(self.game.board_size());
+    }
+
+    // a pass is put before if the other colour is to play
+    pub fn turn(&mut self, player: &Player) -> Result<(), String> {
+        if self.game.next_player() != *player {
+            self.game.next(Cmd::Pass)?;
+        }
+        Ok(())
+    }
+
+    pub fn play(&mut self, player: Player, step: Cmd) -> Result<(), String> {
+        self.turn(&player)?;
+        self.game.next(step)
+    }
+
+    // points legal for the next player which are not its own eyes
+    pub fn candidates(&self) -> Vec<(usize, usize)> {
+        let board = FastBoard::new(self.game.board());
+        let size = board.size();
+        let stone = self.game.next_player().stone();
+        let mut moves = self.game.legal_moves();
+        moves.retain(|(x, y)| !board.is_eye((y - 1) * size + x - 1, stone));
+        moves
+    }
+}
@@ -0,0 +1,154 @@
+use crate::basic::*;
+use crate::engine::{Engine, EngineMove};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+// time an engine is given to exit after quit
+const QUIT_WAIT_MS: u64 = 1000;
+
+// an external program speaking go text protocol on stdin and stdout, like
+// gnugo --mode gtp
+pub struct GtpEngine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    name: String,
+    size: usize,
+}
+
+impl GtpEngine {
+    // run the command line, arguments are split by whitespace
+    pub fn spawn(command: &str) -> Result<GtpEngine, String> {
+        let mut args = command.split_whitespace();
+        let program = args
+            .next()
+            .ok_or_else(|| String::from("empty engine command"))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("can not run {}: {}", program, err))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut engine = GtpEngine {
+            child,
+            stdin,
+            stdout,
+            name: program.to_string(),
+            size: 19,
+        };
+        if let Ok(name) = engine.send("name") {
+            engine.name = name;
+        }
+        Ok(engine)
+    }
+
+    // send a command and wait for the response, which is the text after =
+    // or an error of the text after ?
+    pub fn send(&mut self, command: &str) -> Result<String, String> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|err| format!("can not send to {}: {}", self.name, err))?;
+        let mut response = String::new();
+        loop {
+            let mut line = String::new();
+            let n = self
+                .stdout
+                .read_line(&mut line)
+                .map_err(|err| format!("can not read from {}: {}", self.name, err))?;
+            if n == 0 {
+                return Err(format!("{} exited", self.name));
+            }
+            // empty lines before the response are skipped
+            if line.trim().is_empty() {
+                if response.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            response.push_str(&line);
+        }
+        let response = response.trim_end();
+        if let Some(text) = response.strip_prefix('=') {
+            Ok(text.trim().to_string())
+        } else if let Some(text) = response.strip_prefix('?') {
+            Err(format!("{}: {}", command, text.trim()))
+        } else {
+            Err(format!("invalid response of {}: {}", command, response))
+        }
+    }
+
+    fn vertex(&self, step: &Cmd) -> Result<String, String> {
+        match step {
+            Cmd::Step(p) => {
+                let (x, y) = Notation::Sgf.parse(p, self.size)?;
+                Notation::Gtp.format(x, y, self.size)
+            }
+            _ => Ok(String::from("pass")),
+        }
+    }
+}
+
+fn color(player: &Player) -> &'static str {
+    match player {
+        Player::Black => "b",
+        Player::White => "w",
+    }
+}
+
+impl Engine for GtpEngine {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn set_boardsize(&mut self, size: BoardSize) -> Result<(), String> {
+        self.size = size.lines();
+        self.send(&format!("boardsize {}", self.size)).map(|_| ())
+    }
+
+    fn set_komi(&mut self, komi: f64) -> Result<(), String> {
+        self.send(&format!("komi {}", komi)).map(|_| ())
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        self.send("clear_board").map(|_| ())
+    }
+
+    fn play(&mut self, player: Player, step: Cmd) -> Result<(), String> {
+        let vertex = self.vertex(&step)?;
+        self.send(&format!("play {} {}", color(&player), vertex))
+            .map(|_| ())
+    }
+
+    fn genmove(&mut self, player: Player) -> Result<EngineMove, String> {
+        let vertex = self.send(&format!("genmove {}", color(&player)))?;
+        match vertex.to_lowercase().as_str() {
+            "resign" => Ok(EngineMove::Resign),
+            "pass" => Ok(EngineMove::Play(Cmd::Pass)),
+            _ => {
+                let (x, y) = Notation::Gtp.parse(&vertex, self.size)?;
+                Ok(EngineMove::Play(Cmd::Step(Cmd::point_to_cmd(x, y))))
+            }
+        }
+    }
+}
+
+impl Drop for GtpEngine {
+    // the response of quit is not read, and an engine which has not exited
+    // in a while is killed, so a hung one does not hang the caller
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "quit").and_then(|_| self.stdin.flush());
+        for _ in 0..QUIT_WAIT_MS / 10 {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
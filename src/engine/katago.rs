@@ -0,0 +1,509 @@
+use crate::basic::*;
+use crate::engine::{Analysis, Engine, EngineMove};
+use crate::util::Json;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+// a query of katago's json analysis engine, one line of json each. points
+// are gtp vertexes like D4.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnalysisQuery {
+    pub id: String,
+    pub size: usize,
+    pub komi: f64,
+    pub rules: String,
+    // stones before the first step, like handicap stones
+    pub initial_stones: Vec<(Player, (usize, usize))>,
+    pub initial_player: Player,
+    pub moves: Vec<(Player, Cmd)>,
+    // positions to analyze, by the count of moves played, the last one if
+    // empty
+    pub analyze_turns: Vec<usize>,
+    pub max_visits: Option<usize>,
+    pub include_ownership: bool,
+}
+
+impl AnalysisQuery {
+    // the steps from the first position to current node of the game
+    pub fn from_game(id: &str, g: &Game) -> AnalysisQuery {
+        let steps = g.steps();
+        AnalysisQuery {
+            id: id.to_string(),
+            size: g.board().lines(),
+            komi: g.info().komi.unwrap_or_default(),
+            rules: katago_rules(&g.info().rules).to_string(),
+            initial_stones: g
+                .handicap_stones()
+                .into_iter()
+                .map(|point| (Player::Black, point))
+                .collect(),
+            initial_player: g.player_of_step(1),
+            moves: (1..=steps.len())
+                .map(|n| (g.player_of_step(n), steps[n - 1].clone()))
+                .collect(),
+            analyze_turns: vec![],
+            max_visits: None,
+            include_ownership: false,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<Json, String> {
+        let size = self.size;
+        let stone = |player: &Player, vertex: String| {
+            Json::from(vec![Json::from(colour(player)), Json::from(vertex)])
+        };
+        let initial_stones = self
+            .initial_stones
+            .iter()
+            .map(|(player, (x, y))| Ok(stone(player, Notation::Gtp.format(*x, *y, size)?)))
+            .collect::<Result<Vec<Json>, String>>()?;
+        let moves = self
+            .moves
+            .iter()
+            .map(|(player, step)| Ok(stone(player, vertex(step, size)?)))
+            .collect::<Result<Vec<Json>, String>>()?;
+        let turns = if self.analyze_turns.is_empty() {
+            vec![self.moves.len()]
+        } else {
+            self.analyze_turns.clone()
+        };
+        let mut query = Json::object([
+            ("id", self.id.as_str().into()),
+            ("boardXSize", size.into()),
+            ("boardYSize", size.into()),
+            ("komi", self.komi.into()),
+            ("rules", self.rules.as_str().into()),
+            ("initialStones", initial_stones.into()),
+            ("initialPlayer", colour(&self.initial_player).into()),
+            ("moves", moves.into()),
+            (
+                "analyzeTurns",
+                turns
+                    .into_iter()
+                    .map(Json::from)
+                    .collect::<Vec<Json>>()
+                    .into(),
+            ),
+            ("includeOwnership", self.include_ownership.into()),
+            // rates and scores are of black whatever the engine is set to
+            (
+                "overrideSettings",
+                Json::object([("reportAnalysisWinratesAs", "BLACK".into())]),
+            ),
+        ]);
+        if let (Some(visits), Json::Object(members)) = (self.max_visits, &mut query) {
+            members.push((String::from("maxVisits"), visits.into()));
+        }
+        Ok(query)
+    }
+}
+
+// katago's name of the rules of a game, which are chinese if unknown
+fn katago_rules(rules: &str) -> &'static str {
+    match rules.trim().to_lowercase().as_str() {
+        "japanese" | "jp" => "japanese",
+        "korean" => "korean",
+        "aga" => "aga",
+        "bga" => "bga",
+        "nz" | "new zealand" | "new-zealand" => "new-zealand",
+        "tromp-taylor" | "tromp taylor" | "tt" => "tromp-taylor",
+        _ => "chinese",
+    }
+}
+
+fn colour(player: &Player) -> &'static str {
+    match player {
+        Player::Black => "B",
+        Player::White => "W",
+    }
+}
+
+fn vertex(step: &Cmd, size: usize) -> Result<String, String> {
+    match step {
+        Cmd::Step(p) => {
+            let (x, y) = Notation::Sgf.parse(p, size)?;
+            Notation::Gtp.format(x, y, size)
+        }
+        _ => Ok(String::from("pass")),
+    }
+}
+
+fn parse_vertex(vertex: &str, size: usize) -> Result<Cmd, String> {
+    if vertex.eq_ignore_ascii_case("pass") {
+        return Ok(Cmd::Pass);
+    }
+    let (x, y) = Notation::Gtp.parse(vertex, size)?;
+    Ok(Cmd::Step(Cmd::point_to_cmd(x, y)))
+}
+
+// a candidate step, rates and scores are of black
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveInfo {
+    pub step: Cmd,
+    pub visits: usize,
+    pub win_rate: f64,
+    pub score_lead: f64,
+    pub prior: f64,
+    // rank of the step from 0 for the best
+    pub order: usize,
+    pub variation: Vec<Cmd>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RootInfo {
+    pub visits: usize,
+    pub win_rate: f64,
+    pub score_lead: f64,
+    pub current_player: Player,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnalysisResponse {
+    pub id: String,
+    pub turn: usize,
+    // a report before the search ends
+    pub during_search: bool,
+    pub move_infos: Vec<MoveInfo>,
+    pub root: RootInfo,
+    // owner of each point by rows from the top, 1 for black and -1 for
+    // white
+    pub ownership: Option<Vec<f64>>,
+}
+
+impl AnalysisResponse {
+    // an error response of the engine is returned as the error
+    pub fn from_json(json: &Json, size: usize) -> Result<AnalysisResponse, String> {
+        let id = json.get("id").and_then(|v| v.as_str()).unwrap_or("");
+        if let Some(error) = json.get("error") {
+            let error = error.as_str().unwrap_or("unknown error");
+            return Err(format!("query {}: {}", id, error));
+        }
+        let field = |json: &Json, key: &str| {
+            json.get(key)
+                .cloned()
+                .ok_or_else(|| format!("no {} in response {}", key, id))
+        };
+        let number = |json: &Json, key: &str| {
+            field(json, key)?
+                .as_f64()
+                .ok_or_else(|| format!("invalid {} in response {}", key, id))
+        };
+        let root = field(json, "rootInfo")?;
+        let current_player = match root.get("currentPlayer").and_then(|v| v.as_str()) {
+            Some("W") => Player::White,
+            _ => Player::Black,
+        };
+        let mut move_infos = vec![];
+        for info in field(json, "moveInfos")?.as_array().unwrap_or(&[]) {
+            let step = field(info, "move")?;
+            let variation = match info.get("pv").and_then(|v| v.as_array()) {
+                Some(pv) => pv
+                    .iter()
+                    .map(|v| parse_vertex(v.as_str().unwrap_or(""), size))
+                    .collect::<Result<Vec<Cmd>, String>>()?,
+                None => vec![],
+            };
+            move_infos.push(MoveInfo {
+                step: parse_vertex(step.as_str().unwrap_or(""), size)?,
+                visits: number(info, "visits")? as usize,
+                win_rate: number(info, "winrate")?,
+                score_lead: number(info, "scoreLead")?,
+                prior: number(info, "prior").unwrap_or(0.0),
+                order: number(info, "order").unwrap_or(0.0) as usize,
+                variation,
+            });
+        }
+        move_infos.sort_by_key(|m| m.order);
+        let ownership = json
+            .get("ownership")
+            .and_then(|v| v.as_array())
+            .map(|values| values.iter().filter_map(|v| v.as_f64()).collect());
+        Ok(AnalysisResponse {
+            id: id.to_string(),
+            turn: number(json, "turnNumber")? as usize,
+            during_search: json
+                .get("isDuringSearch")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            move_infos,
+            root: RootInfo {
+                visits: number(&root, "visits")? as usize,
+                win_rate: number(&root, "winrate")?,
+                score_lead: number(&root, "scoreLead")?,
+                current_player,
+            },
+            ownership,
+        })
+    }
+}
+
+// a process of katago analysis or another engine of the same protocol,
+// like katago analysis -config analysis.cfg -model model.bin.gz
+pub struct AnalysisClient {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+    // responses read while waiting for another query
+    pending: Vec<Json>,
+}
+
+impl AnalysisClient {
+    // run the command line, arguments are split by whitespace
+    pub fn spawn(command: &str) -> Result<AnalysisClient, String> {
+        let mut args = command.split_whitespace();
+        let program = args
+            .next()
+            .ok_or_else(|| String::from("empty engine command"))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("can not run {}: {}", program, err))?;
+        let stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Ok(AnalysisClient {
+            child,
+            stdin,
+            stdout,
+            pending: vec![],
+        })
+    }
+
+    pub fn send(&mut self, query: &AnalysisQuery) -> Result<(), String> {
+        let line = query.to_json()?.to_string();
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| String::from("the engine is closed"))?;
+        writeln!(stdin, "{}", line)
+            .and_then(|_| stdin.flush())
+            .map_err(|err| format!("can not send query {}: {}", query.id, err))
+    }
+
+    // the next line of the engine, warnings are skipped
+    fn read(&mut self) -> Result<Json, String> {
+        loop {
+            let mut line = String::new();
+            let n = self
+                .stdout
+                .read_line(&mut line)
+                .map_err(|err| format!("can not read from engine: {}", err))?;
+            if n == 0 {
+                return Err(String::from("the engine exited"));
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            let json: Json = line.trim().parse()?;
+            if json.get("warning").is_none() {
+                return Ok(json);
+            }
+        }
+    }
+
+    // the next response of the query, responses of others are kept for
+    // their turn. an error without id, like of a query which can not be
+    // read, fails the query waited for.
+    pub fn receive(&mut self, id: &str, size: usize) -> Result<AnalysisResponse, String> {
+        let is_for = |json: &Json| json.get("id").and_then(|v| v.as_str()) == Some(id);
+        let json = match self.pending.iter().position(is_for) {
+            Some(i) => self.pending.remove(i),
+            None => loop {
+                let json = self.read()?;
+                if is_for(&json) {
+                    break json;
+                }
+                match (json.get("id"), json.get("error")) {
+                    (Some(_), _) => self.pending.push(json),
+                    (None, Some(err)) => {
+                        let err = err.as_str().map(String::from).unwrap_or(err.to_string());
+                        return Err(format!("query {}: {}", id, err));
+                    }
+                    // no query waits for it
+                    (None, None) => {}
+                }
+            },
+        };
+        AnalysisResponse::from_json(&json, size)
+    }
+
+    // send the query and wait for the final response of each turn, in the
+    // order of turns
+    pub fn analyze(&mut self, query: &AnalysisQuery) -> Result<Vec<AnalysisResponse>, String> {
+        self.send(query)?;
+        let count = query.analyze_turns.len().max(1);
+        let mut responses = vec![];
+        while responses.len() < count {
+            let response = self.receive(&query.id, query.size)?;
+            if !response.during_search {
+                responses.push(response);
+            }
+        }
+        responses.sort_by_key(|r| r.turn);
+        Ok(responses)
+    }
+}
+
+impl Drop for AnalysisClient {
+    fn drop(&mut self) {
+        // the engine exits at the end of its input
+        drop(self.stdin.take());
+        let _ = self.child.wait();
+    }
+}
+
+// an analysis engine as a player, each step is one query of the whole game
+pub struct KataGoEngine {
+    client: AnalysisClient,
+    game: Game,
+    komi: f64,
+    max_visits: Option<usize>,
+    queries: usize,
+}
+
+impl KataGoEngine {
+    pub fn new(client: AnalysisClient, max_visits: Option<usize>) -> KataGoEngine {
+        KataGoEngine {
+            client,
+            game: Game::new(BoardSize::Normal),
+            komi: 7.5,
+            max_visits,
+            queries: 0,
+        }
+    }
+}
+
+impl Engine for KataGoEngine {
+    fn name(&self) -> String {
+        String::from("katago")
+    }
+
+    fn set_boardsize(&mut self, size: BoardSize) -> Result<(), String> {
+        self.game = Game::new(size);
+        Ok(())
+    }
+
+    fn set_komi(&mut self, komi: f64) -> Result<(), String> {
+        self.komi = komi;
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        self.game = Game::new(self.game.board_size());
+        Ok(())
+    }
+
+    fn play(&mut self, player: Player, step: Cmd) -> Result<(), String> {
+        if self.game.next_player() != player {
+            self.game.next(Cmd::Pass)?;
+        }
+        self.game.next(step)
+    }
+
+    fn genmove(&mut self, player: Player) -> Result<EngineMove, String> {
+        let analysis = self.analyze(player.clone())?;
+        self.play(player, analysis.best.clone())?;
+        Ok(EngineMove::Play(analysis.best))
+    }
+
+    fn analyze(&mut self, player: Player) -> Result<Analysis, String> {
+        if self.game.next_player() != player {
+            self.game.next(Cmd::Pass)?;
+        }
+        self.queries += 1;
+        let mut query = AnalysisQuery::from_game(&self.queries.to_string(), &self.game);
+        query.komi = self.komi;
+        query.max_visits = self.max_visits;
+        let response = self.client.analyze(&query)?.remove(0);
+        let best = response
+            .move_infos
+            .first()
+            .ok_or_else(|| String::from("no move infos"))?;
+        let (win_rate, score) = (response.root.win_rate, response.root.score_lead);
+        Ok(Analysis {
+            best: best.step.clone(),
+            win_rate: match player {
+                Player::Black => win_rate,
+                Player::White => 1.0 - win_rate,
+            },
+            score: Some(score),
+            variation: best.variation.clone(),
+            visits: response
+                .move_infos
+                .iter()
+                .map(|m| (m.step.clone(), m.visits))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn stub_analysis_engine() {
+        // answers each query by its id, with a warning and a report during
+        // search before
+        let script = r#"while read -r line; do
+  id=$(printf '%s' "$line" | sed 's/.*"id":"\([^"]*\)".*/\1/')
+  printf '{"warning":"unused field","field":"x"}\n'
+  if [ "$id" = bad ]; then
+    printf '{"id":"bad","error":"illegal move"}\n'
+    continue
+  fi
+  if [ "$id" = lost ]; then
+    printf '{"error":"could not parse query"}\n'
+    continue
+  fi
+  printf '{"id":"%s","isDuringSearch":true,"turnNumber":2,"moveInfos":[],"rootInfo":{"visits":1,"winrate":0.5,"scoreLead":0}}\n' "$id"
+  printf '{"id":"%s","isDuringSearch":false,"turnNumber":2,"moveInfos":[{"move":"pass","visits":3,"winrate":0.2,"scoreLead":-4,"prior":0.01,"order":1,"pv":["pass"]},{"move":"C3","visits":40,"winrate":0.65,"scoreLead":2.5,"prior":0.3,"order":0,"pv":["C3","G7"]}],"rootInfo":{"visits":43,"winrate":0.6,"scoreLead":2,"currentPlayer":"B"},"ownership":[%s]}\n' "$id" "$(seq -s, 81 | sed 's/[0-9]*/0.5/g')"
+done
+"#;
+        let dir = TempDir::new("katago");
+        let path = dir.file("stub.sh");
+        fs::write(&path, script).unwrap();
+        let command = format!("sh {}", path);
+
+        let mut g = Game::new(BoardSize::Small);
+        g.set_info("komi", "6.5").unwrap();
+        g.set_info("rules", "Japanese").unwrap();
+        g.next(Cmd::Step(String::from("ee"))).unwrap();
+        g.next(Cmd::Pass).unwrap();
+        let mut query = AnalysisQuery::from_game("q1", &g);
+        query.include_ownership = true;
+        let json = query.to_json().unwrap();
+        assert!(json.get("moves").unwrap().to_string() == r#"[["B","E5"],["W","pass"]]"#);
+        assert!(json.get("analyzeTurns").unwrap().to_string() == "[2]");
+        assert!(json.get("rules") == Some(&"japanese".into()));
+
+        let mut client = AnalysisClient::spawn(&command).unwrap();
+        let responses = client.analyze(&query).unwrap();
+        let r = &responses[0];
+        assert!(responses.len() == 1 && r.id == "q1" && r.turn == 2 && !r.during_search);
+        assert!(r.move_infos[0].step == Cmd::Step(String::from("cg")));
+        assert!(r.move_infos[0].variation.len() == 2 && r.move_infos[1].step == Cmd::Pass);
+        assert!(r.root.visits == 43 && r.root.current_player == Player::Black);
+        assert!(r.ownership.as_ref().is_some_and(|o| o.len() == 81));
+        query.id = String::from("bad");
+        assert!(client.analyze(&query).unwrap_err() == "query bad: illegal move");
+        query.id = String::from("lost");
+        assert!(client.analyze(&query).unwrap_err() == "query lost: could not parse query");
+
+        // white to move sees the rate of black turned over
+        let mut engine = KataGoEngine::new(AnalysisClient::spawn(&command).unwrap(), Some(50));
+        engine.set_boardsize(BoardSize::Small).unwrap();
+        engine
+            .play(Player::Black, Cmd::Step(String::from("ee")))
+            .unwrap();
+        let analysis = engine.analyze(Player::White).unwrap();
+        assert!(analysis.best == Cmd::Step(String::from("cg")));
+        assert!((analysis.win_rate - 0.4).abs() < 1e-9 && analysis.score == Some(2.0));
+        drop(client);
+        drop(engine);
+    }
+}
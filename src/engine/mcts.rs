@@ -0,0 +1,329 @@
+use crate::basic::*;
+use crate::engine::{Analysis, Engine, EngineMove, FastBoard, Policy, Position};
+use crate::util::Rng;
+
+// exploration weight of uct
+const EXPLORATION: f64 = 1.0;
+// resign when the best step wins less than this
+const RESIGN_RATE: f64 = 0.05;
+// playouts a new node is taken to have had, won by its prior
+const PRIOR_VISITS: f64 = 10.0;
+
+// monte carlo tree search with uct, each playout is a game to the end
+// scored by area. steps of playouts and priors of new nodes are by the
+// policy of patterns, or random without.
+pub struct MctsEngine {
+    position: Position,
+    playouts: usize,
+    rng: Rng,
+    policy: Option<Policy>,
+}
+
+impl MctsEngine {
+    pub fn new(playouts: usize, rng: Rng) -> MctsEngine {
+        MctsEngine {
+            position: Position::new(),
+            playouts: playouts.max(1),
+            rng,
+            policy: Some(Policy::default()),
+        }
+    }
+
+    // pure random playouts and no priors
+    pub fn uniform(playouts: usize, rng: Rng) -> MctsEngine {
+        MctsEngine {
+            policy: None,
+            ..MctsEngine::new(playouts, rng)
+        }
+    }
+}
+
+struct Node {
+    // point of the step into the node, none for pass
+    step: Option<usize>,
+    // colour of the step into the node
+    stone: Stone,
+    board: FastBoard,
+    // passes in a row until the node, the game is over at 2
+    passes: usize,
+    children: Vec<usize>,
+    untried: Vec<Option<usize>>,
+    // weights of untried steps by the policy
+    priors: Vec<f64>,
+    visits: f64,
+    // playouts won by the colour of the node
+    wins: f64,
+}
+
+impl Node {
+    // steps of the next colour are limited to allowed points if given
+    fn new(
+        step: Option<usize>,
+        stone: Stone,
+        board: FastBoard,
+        passes: usize,
+        allowed: Option<&[usize]>,
+        policy: Option<&Policy>,
+    ) -> Node {
+        let mut untried = vec![];
+        let next = stone.another();
+        if passes < 2 {
+            let points = (0..board.size() * board.size()).filter(|i| {
+                !board.is_eye(*i, next)
+                    && board.is_legal(*i, next)
+                    && allowed.is_none_or(|a| a.contains(i))
+            });
+            untried.extend(points.map(Some));
+            // pass only if nothing else can be played or the opponent passed
+            if untried.is_empty() || passes > 0 {
+                untried.push(None);
+            }
+        }
+        let size = board.size();
+        let priors = untried
+            .iter()
+            .map(|step| match (step, policy) {
+                (Some(i), Some(policy)) => policy.prior(&board, i % size + 1, i / size + 1, next),
+                _ => 1.0,
+            })
+            .collect();
+        Node {
+            step,
+            stone,
+            board,
+            passes,
+            children: vec![],
+            untried,
+            priors,
+            visits: 0.0,
+            wins: 0.0,
+        }
+    }
+
+    // an untried step picked by its prior, with the prior
+    fn pick(&mut self, rng: &mut Rng) -> (Option<usize>, f64) {
+        let total: f64 = self.priors.iter().sum();
+        let mut left = rng.next_f64() * total;
+        let mut k = self.priors.len() - 1;
+        for (i, prior) in self.priors.iter().enumerate() {
+            if left < *prior {
+                k = i;
+                break;
+            }
+            left -= prior;
+        }
+        (self.untried.swap_remove(k), self.priors.swap_remove(k))
+    }
+}
+
+impl MctsEngine {
+    // the most visited step with the win rate of all playouts, which is
+    // steadier than the rate of a single step when playouts are few
+    fn search(&mut self, stone: Stone) -> Analysis {
+        let game = &self.position.game;
+        let size = game.board().lines();
+        let passes = match game.steps().last() {
+            Some(Cmd::Pass) => 1,
+            _ => 0,
+        };
+        // the whole history is checked for ko at root
+        let legal: Vec<usize> = self
+            .position
+            .candidates()
+            .iter()
+            .map(|(x, y)| (y - 1) * size + x - 1)
+            .collect();
+        let root = Node::new(
+            None,
+            stone.another(),
+            FastBoard::new(game.board()),
+            passes,
+            Some(&legal),
+            self.policy.as_ref(),
+        );
+        let mut nodes = vec![root];
+        let mut score = 0.0;
+        for _ in 0..self.playouts {
+            let mut path = vec![0];
+            let mut i = 0;
+            while nodes[i].untried.is_empty() && !nodes[i].children.is_empty() {
+                i = self.select(&nodes, i);
+                path.push(i);
+            }
+            if !nodes[i].untried.is_empty() {
+                let (step, prior) = nodes[i].pick(&mut self.rng);
+                let stone = nodes[i].stone.another();
+                let mut board = nodes[i].board.clone();
+                let passes = match step {
+                    Some(p) => {
+                        board.play(p, stone);
+                        0
+                    }
+                    None => {
+                        board.pass();
+                        nodes[i].passes + 1
+                    }
+                };
+                let policy = self.policy.as_ref();
+                let mut node = Node::new(step, stone, board, passes, None, policy);
+                if policy.is_some() {
+                    node.visits = PRIOR_VISITS;
+                    node.wins = PRIOR_VISITS * prior / (prior + 1.0);
+                }
+                nodes.push(node);
+                let child = nodes.len() - 1;
+                nodes[i].children.push(child);
+                path.push(child);
+                i = child;
+            }
+            let mut end = nodes[i].board.clone();
+            if nodes[i].passes < 2 {
+                let last = nodes[i].step;
+                end.playout(
+                    nodes[i].stone.another(),
+                    last,
+                    self.policy.as_ref(),
+                    &mut self.rng,
+                );
+            }
+            score += end.area_score() - self.position.komi;
+            let winner = if end.area_score() > self.position.komi {
+                Stone::Black
+            } else {
+                Stone::White
+            };
+            for n in path {
+                nodes[n].visits += 1.0;
+                if nodes[n].stone == winner {
+                    nodes[n].wins += 1.0;
+                }
+            }
+        }
+        let to_cmd = |step: Option<usize>| match step {
+            Some(p) => Cmd::Step(Cmd::point_to_cmd(p % size + 1, p / size + 1)),
+            None => Cmd::Pass,
+        };
+        // playouts through each step from root, without the prior ones
+        let prior = if self.policy.is_some() {
+            PRIOR_VISITS
+        } else {
+            0.0
+        };
+        let visits = nodes[0]
+            .children
+            .iter()
+            .map(|c| (to_cmd(nodes[*c].step), (nodes[*c].visits - prior) as usize))
+            .collect();
+        // the most visited steps from root
+        let mut variation = vec![];
+        let mut i = 0;
+        while let Some(best) = nodes[i]
+            .children
+            .iter()
+            .max_by(|a, b| nodes[**a].visits.total_cmp(&nodes[**b].visits))
+        {
+            if !variation.is_empty() && nodes[*best].visits < 2.0 {
+                break;
+            }
+            i = *best;
+            variation.push(to_cmd(nodes[i].step));
+        }
+        Analysis {
+            best: variation.first().cloned().unwrap_or(Cmd::Pass),
+            win_rate: 1.0 - nodes[0].wins / nodes[0].visits,
+            score: Some(score / self.playouts as f64),
+            variation,
+            visits,
+        }
+    }
+
+    fn select(&self, nodes: &[Node], i: usize) -> usize {
+        let parent = nodes[i].visits.max(1.0).ln();
+        let uct = |c: &usize| {
+            let n = &nodes[*c];
+            n.wins / n.visits + EXPLORATION * (parent / n.visits).sqrt()
+        };
+        *nodes[i]
+            .children
+            .iter()
+            .max_by(|a, b| uct(a).total_cmp(&uct(b)))
+            .unwrap()
+    }
+}
+
+impl Engine for MctsEngine {
+    fn name(&self) -> String {
+        match self.policy {
+            Some(_) => String::from("mcts"),
+            None => String::from("mcts-uniform"),
+        }
+    }
+
+    fn set_boardsize(&mut self, size: BoardSize) -> Result<(), String> {
+        self.position.set_boardsize(size);
+        Ok(())
+    }
+
+    fn set_komi(&mut self, komi: f64) -> Result<(), String> {
+        self.position.komi = komi;
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        self.position.clear();
+        Ok(())
+    }
+
+    fn play(&mut self, player: Player, step: Cmd) -> Result<(), String> {
+        self.position.play(player, step)
+    }
+
+    fn genmove(&mut self, player: Player) -> Result<EngineMove, String> {
+        let analysis = self.analyze(player.clone())?;
+        if analysis.win_rate < RESIGN_RATE {
+            return Ok(EngineMove::Resign);
+        }
+        self.position.play(player, analysis.best.clone())?;
+        Ok(EngineMove::Play(analysis.best))
+    }
+
+    fn analyze(&mut self, player: Player) -> Result<Analysis, String> {
+        self.position.turn(&player)?;
+        Ok(self.search(player.stone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_in_atari() {
+        // black wins by capturing the white stone at de, which can only
+        // escape by ee
+        let rows = [
+            ".XXX.OOO.",
+            "XXXX.OOOO",
+            "X.XX.OO.O",
+            "XXXX.OOOO",
+            "XXXO.OOOO",
+            "XXXX.OOOO",
+            "X.XX.OO.O",
+            "XXXX.OOOO",
+            ".XXX.OOO.",
+        ];
+        let mut engine = MctsEngine::new(200, Rng::new(3));
+        engine.set_boardsize(BoardSize::Small).unwrap();
+        engine.set_komi(0.5).unwrap();
+        for (c, player) in [('X', Player::Black), ('O', Player::White)] {
+            for (y, row) in rows.iter().enumerate() {
+                for (x, _) in row.char_indices().filter(|(_, s)| *s == c) {
+                    let step = Cmd::Step(Cmd::point_to_cmd(x + 1, y + 1));
+                    engine.play(player.clone(), step).unwrap();
+                }
+            }
+        }
+        let step = engine.genmove(Player::Black).unwrap();
+        assert!(step == EngineMove::Play(Cmd::Step(String::from("ee"))));
+    }
+}
@@ -0,0 +1,254 @@
+use crate::basic::*;
+use crate::engine::Policy;
+use crate::util::Rng;
+
+// a board for fast random games, points are indexes y * size + x from 0.
+// only the simple ko of one stone is checked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FastBoard {
+    size: usize,
+    stones: Vec<Stone>,
+    // the point which can not be played back at once
+    ko: Option<usize>,
+}
+
+impl FastBoard {
+    pub fn new(board: &Board) -> FastBoard {
+        let size = board.lines();
+        let stones = (0..size * size)
+            .map(|i| board.at(i % size + 1, i / size + 1).unwrap())
+            .collect();
+        FastBoard {
+            size,
+            stones,
+            ko: None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn at(&self, i: usize) -> Stone {
+        self.stones[i]
+    }
+
+    pub fn neighbours(&self, i: usize) -> impl Iterator<Item = usize> {
+        let size = self.size;
+        let (x, y) = (i % size, i / size);
+        [
+            (x > 0).then(|| i - 1),
+            (x + 1 < size).then_some(i + 1),
+            (y > 0).then(|| i - size),
+            (y + 1 < size).then_some(i + size),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    // stones of the string at i, and count of its liberties
+    pub fn string(&self, i: usize) -> (Vec<usize>, usize) {
+        let stone = self.stones[i];
+        let mut seen = vec![false; self.stones.len()];
+        let mut string = vec![i];
+        let mut liberties = 0;
+        seen[i] = true;
+        let mut k = 0;
+        while k < string.len() {
+            for n in self.neighbours(string[k]) {
+                if seen[n] {
+                    continue;
+                }
+                if self.stones[n] == stone {
+                    seen[n] = true;
+                    string.push(n);
+                } else if self.stones[n] == Stone::Empty {
+                    seen[n] = true;
+                    liberties += 1;
+                }
+            }
+            k += 1;
+        }
+        (string, liberties)
+    }
+
+    pub fn liberties(&self, i: usize) -> usize {
+        self.liberties_up_to(i, usize::MAX)
+    }
+
+    // liberties of the string at i, the counting stops at limit. no memory
+    // is allocated as this is called for most points of every playout step.
+    pub fn liberties_up_to(&self, i: usize, limit: usize) -> usize {
+        let stone = self.stones[i];
+        let mut seen = [0u64; 6];
+        let mut stack = [0u16; 19 * 19];
+        let mut top = 1;
+        stack[0] = i as u16;
+        seen[i / 64] |= 1 << (i % 64);
+        let mut liberties = 0;
+        while top > 0 {
+            top -= 1;
+            for n in self.neighbours(stack[top] as usize) {
+                if seen[n / 64] & (1 << (n % 64)) != 0 {
+                    continue;
+                }
+                seen[n / 64] |= 1 << (n % 64);
+                if self.stones[n] == Stone::Empty {
+                    liberties += 1;
+                    if liberties >= limit {
+                        return liberties;
+                    }
+                } else if self.stones[n] == stone {
+                    stack[top] = n as u16;
+                    top += 1;
+                }
+            }
+        }
+        liberties
+    }
+
+    // empty point surrounded by stones of the colour only
+    pub fn is_eye(&self, i: usize, stone: Stone) -> bool {
+        self.neighbours(i).all(|n| self.stones[n] == stone)
+    }
+
+    pub fn is_legal(&self, i: usize, stone: Stone) -> bool {
+        if self.stones[i] != Stone::Empty || self.ko == Some(i) {
+            return false;
+        }
+        self.neighbours(i).any(|n| match self.stones[n] {
+            Stone::Empty => true,
+            s if s == stone => self.liberties_up_to(n, 2) > 1,
+            _ => self.liberties_up_to(n, 2) == 1,
+        })
+    }
+
+    // put the stone which must be legal, and take the strings captured
+    pub fn play(&mut self, i: usize, stone: Stone) {
+        self.stones[i] = stone;
+        let mut captured = vec![];
+        for n in self.neighbours(i).collect::<Vec<usize>>() {
+            if self.stones[n] == stone.another() && self.liberties_up_to(n, 1) == 0 {
+                let (string, _) = self.string(n);
+                for s in string.iter() {
+                    self.stones[*s] = Stone::Empty;
+                }
+                captured.extend(string);
+            }
+        }
+        self.ko = match captured[..] {
+            [one]
+                if self.neighbours(i).all(|n| self.stones[n] != stone)
+                    && self.liberties_up_to(i, 2) == 1 =>
+            {
+                Some(one)
+            }
+            _ => None,
+        };
+    }
+
+    pub fn pass(&mut self) {
+        self.ko = None;
+    }
+
+    // black area minus white area, empty points count for the colour
+    // around them only
+    pub fn area_score(&self) -> f64 {
+        let mut score = 0.0;
+        for (i, stone) in self.stones.iter().enumerate() {
+            score += match stone {
+                Stone::Black => 1.0,
+                Stone::White => -1.0,
+                Stone::Empty if self.is_eye(i, Stone::Black) => 1.0,
+                Stone::Empty if self.is_eye(i, Stone::White) => -1.0,
+                Stone::Empty => 0.0,
+            };
+        }
+        score
+    }
+
+    // a random step of the stone which does not fill its own eyes, none to
+    // pass
+    pub fn random_step(&self, stone: Stone, rng: &mut Rng) -> Option<usize> {
+        let mut empty: Vec<usize> = (0..self.stones.len())
+            .filter(|i| self.stones[*i] == Stone::Empty)
+            .collect();
+        while !empty.is_empty() {
+            let i = empty.swap_remove(rng.below(empty.len()));
+            if !self.is_eye(i, stone) && self.is_legal(i, stone) {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    // play steps of the policy, or random ones without, until both pass.
+    // stone plays first after the last step.
+    pub fn playout(
+        &mut self,
+        mut stone: Stone,
+        mut last: Option<usize>,
+        policy: Option<&Policy>,
+        rng: &mut Rng,
+    ) {
+        let mut passes = 0;
+        // games with ko may never end
+        for _ in 0..self.stones.len() * 3 {
+            let step = match policy {
+                Some(policy) => policy.playout_step(self, stone, last, rng),
+                None => self.random_step(stone, rng),
+            };
+            last = step;
+            match step {
+                Some(i) => {
+                    self.play(i, stone);
+                    passes = 0;
+                }
+                None => {
+                    self.pass();
+                    passes += 1;
+                    if passes == 2 {
+                        break;
+                    }
+                }
+            }
+            stone = stone.another();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_and_ko() {
+        // white takes the black stone at cb by bb, which is a ko
+        let mut b = Board::new(BoardSize::Small);
+        for (stone, x, y) in [
+            (Stone::Black, 2, 1),
+            (Stone::Black, 1, 2),
+            (Stone::Black, 2, 3),
+            (Stone::White, 3, 1),
+            (Stone::White, 4, 2),
+            (Stone::White, 3, 3),
+            (Stone::Black, 3, 2),
+        ] {
+            b.add(stone, x, y).unwrap();
+        }
+        let mut fb = FastBoard::new(&b);
+        let (bb, cb) = (9 + 1, 9 + 2);
+        assert!(fb.liberties(cb) == 1 && fb.is_legal(bb, Stone::White));
+        fb.play(bb, Stone::White);
+        assert!(fb.at(cb) == Stone::Empty && fb.liberties(bb) == 1);
+        // black can not take back at once
+        assert!(!fb.is_legal(cb, Stone::Black));
+        fb.pass();
+        assert!(fb.is_legal(cb, Stone::Black));
+
+        let mut rng = Rng::new(5);
+        fb.playout(Stone::Black, None, None, &mut rng);
+        let empty = (0..81).filter(|i| fb.at(*i) == Stone::Empty).count();
+        assert!(empty < 20 && fb.area_score().abs() <= 81.0);
+    }
+}
@@ -0,0 +1,455 @@
+use crate::basic::*;
+use crate::engine::FastBoard;
+use crate::util::Rng;
+
+// patterns around the step at the centre, a weight above 1 is better than a
+// plain point. X is a stone of either colour and O of the other one, x is
+// not X, o is not O, . is empty, # is off the board and ? is anything.
+// 3x3 ones are the patterns of mogo, which are looked up in playouts, the
+// larger ones are for priors only.
+const PATTERNS: &str = "
+# hane
+XOX ... ??? 10
+XO. ... ?.? 10
+XO? X.. x.? 10
+XOO ... ?.? 8
+# cut
+XO? O.o ?o? 10
+XO? O.X ??? 10
+?X? O.O ooo 10
+OX? o.O ??? 8
+# edge
+X.? O.? ### 6
+OX? X.O ### 6
+?X? x.O ### 6
+?OX X.O ### 6
+# one-space jump and knight's move
+??X?? ??.?? ??.?? ????? ????? 2
+?X??? ????? ??.?? ????? ????? 1.5
+# first and second line in an empty area
+..... ..... ..... ##### ##### 0.2
+..... ..... ..... ..... ##### 0.5
+";
+
+// a capture, an escape from atari and a step into atari multiply the
+// weight of a point by these
+const CAPTURE: f64 = 20.0;
+const ESCAPE: f64 = 10.0;
+const SELF_ATARI: f64 = 0.1;
+
+// points are (x, y) from 1 like board, the rest is off the board
+pub trait Neighbourhood {
+    fn lines(&self) -> usize;
+
+    fn stone_at(&self, x: usize, y: usize) -> Stone;
+
+    // liberties of the block at the point, counted up to limit at least
+    fn liberties_up_to(&self, x: usize, y: usize, limit: usize) -> usize;
+}
+
+impl Neighbourhood for Board {
+    fn lines(&self) -> usize {
+        Board::lines(self)
+    }
+
+    fn stone_at(&self, x: usize, y: usize) -> Stone {
+        self.at(x, y).unwrap_or(Stone::Empty)
+    }
+
+    fn liberties_up_to(&self, x: usize, y: usize, _limit: usize) -> usize {
+        liberties(self, x, y)
+    }
+}
+
+impl Neighbourhood for FastBoard {
+    fn lines(&self) -> usize {
+        self.size()
+    }
+
+    fn stone_at(&self, x: usize, y: usize) -> Stone {
+        self.at((y - 1) * self.size() + x - 1)
+    }
+
+    fn liberties_up_to(&self, x: usize, y: usize, limit: usize) -> usize {
+        FastBoard::liberties_up_to(self, (y - 1) * self.size() + x - 1, limit)
+    }
+}
+
+// a cell of a pattern relative to the player to move
+const EMPTY: u64 = 0;
+const OWN: u64 = 1;
+const OTHER: u64 = 2;
+const EDGE: u64 = 3;
+
+// offsets of the square around the centre by rows, without the centre
+fn cells(radius: isize) -> Vec<(isize, isize)> {
+    let mut cells = vec![];
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if (dx, dy) != (0, 0) {
+                cells.push((dx, dy));
+            }
+        }
+    }
+    cells
+}
+
+// 2 bits for each cell around the point
+fn key<N: Neighbourhood>(
+    board: &N,
+    x: usize,
+    y: usize,
+    stone: Stone,
+    cells: &[(isize, isize)],
+) -> u64 {
+    let lines = board.lines() as isize;
+    let mut key = 0;
+    for (i, (dx, dy)) in cells.iter().enumerate() {
+        let (cx, cy) = (x as isize + dx, y as isize + dy);
+        let cell = if cx < 1 || cy < 1 || cx > lines || cy > lines {
+            EDGE
+        } else {
+            match board.stone_at(cx as usize, cy as usize) {
+                Stone::Empty => EMPTY,
+                s if s == stone => OWN,
+                _ => OTHER,
+            }
+        };
+        key |= cell << (2 * i);
+    }
+    key
+}
+
+// weights of patterns, 3x3 ones by every key and larger ones by mask
+pub struct PatternTable {
+    small: Vec<f64>,
+    large: Vec<(u64, u64, f64)>,
+}
+
+impl Default for PatternTable {
+    fn default() -> Self {
+        PatternTable::parse(PATTERNS).unwrap()
+    }
+}
+
+impl PatternTable {
+    // lines of the rows of a pattern and its weight, see PATTERNS. each
+    // pattern is taken in any rotation, reflection and colour.
+    pub fn parse(text: &str) -> Result<PatternTable, String> {
+        let mut table = PatternTable {
+            small: vec![1.0; 1 << 16],
+            large: vec![],
+        };
+        for line in text.lines().map(|l| l.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (rows, weight) = line
+                .rsplit_once(' ')
+                .ok_or_else(|| format!("invalid pattern: {}", line))?;
+            let weight: f64 = weight
+                .parse()
+                .map_err(|_| format!("invalid weight: {}", line))?;
+            let rows: String = rows.split_whitespace().collect();
+            let radius = match rows.len() {
+                9 => 1,
+                25 => 2,
+                _ => return Err(format!("invalid pattern size: {}", line)),
+            };
+            if rows.chars().nth(rows.len() / 2) != Some('.') {
+                return Err(format!("the centre must be empty: {}", line));
+            }
+            // cells of the pattern and the values each one allows
+            let centre = rows.len() / 2;
+            let pattern: Vec<((isize, isize), Vec<u64>)> = cells(radius)
+                .into_iter()
+                .zip(rows.chars().enumerate().filter(|(i, _)| *i != centre))
+                .map(|(cell, (_, c))| Ok((cell, allowed(c)?)))
+                .collect::<Result<_, String>>()?;
+            for swap in [false, true] {
+                for symmetry in 0..8 {
+                    let variant: Vec<((isize, isize), Vec<u64>)> = pattern
+                        .iter()
+                        .map(|(cell, values)| {
+                            let values = values.iter().map(|v| swap_colour(*v, swap)).collect();
+                            (transform(*cell, symmetry), values)
+                        })
+                        .collect();
+                    table.add(radius, &variant, weight);
+                }
+            }
+        }
+        // symmetric patterns give the same entries
+        table
+            .large
+            .sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)).then(a.2.total_cmp(&b.2)));
+        table.large.dedup();
+        Ok(table)
+    }
+
+    fn add(&mut self, radius: isize, variant: &[((isize, isize), Vec<u64>)], weight: f64) {
+        let order = cells(radius);
+        let values: Vec<&Vec<u64>> = order
+            .iter()
+            .map(|cell| &variant.iter().find(|(c, _)| c == cell).unwrap().1)
+            .collect();
+        if radius == 1 {
+            let mut keys = vec![0u64];
+            for (i, allowed) in values.iter().enumerate() {
+                keys = keys
+                    .iter()
+                    .flat_map(|k| allowed.iter().map(move |v| k | v << (2 * i)))
+                    .collect();
+            }
+            for k in keys {
+                self.small[k as usize] = weight;
+            }
+            return;
+        }
+        // anything is left out of the mask, the other sets are spread
+        let mut entries = vec![(0u64, 0u64)];
+        for (i, allowed) in values.iter().enumerate() {
+            if allowed.len() == 4 {
+                continue;
+            }
+            entries = entries
+                .iter()
+                .flat_map(|(mask, value)| {
+                    allowed
+                        .iter()
+                        .map(move |v| (mask | 3 << (2 * i), value | v << (2 * i)))
+                })
+                .collect();
+        }
+        self.large.extend(
+            entries
+                .into_iter()
+                .map(|(mask, value)| (mask, value, weight)),
+        );
+    }
+
+    // weight of the 3x3 pattern around the point
+    pub fn small_weight<N: Neighbourhood>(
+        &self,
+        board: &N,
+        x: usize,
+        y: usize,
+        stone: Stone,
+    ) -> f64 {
+        self.small[key(board, x, y, stone, &SMALL_CELLS) as usize]
+    }
+
+    // weight of the 3x3 pattern times the larger pattern farthest from 1
+    pub fn weight<N: Neighbourhood>(&self, board: &N, x: usize, y: usize, stone: Stone) -> f64 {
+        let large = key(board, x, y, stone, &cells(2));
+        let matched = self
+            .large
+            .iter()
+            .filter(|(mask, value, _)| large & mask == *value)
+            .map(|(_, _, weight)| *weight)
+            .max_by(|a, b| a.ln().abs().total_cmp(&b.ln().abs()))
+            .unwrap_or(1.0);
+        self.small_weight(board, x, y, stone) * matched
+    }
+}
+
+const SMALL_CELLS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+fn allowed(c: char) -> Result<Vec<u64>, String> {
+    match c {
+        '.' => Ok(vec![EMPTY]),
+        'X' => Ok(vec![OWN]),
+        'O' => Ok(vec![OTHER]),
+        'x' => Ok(vec![EMPTY, OTHER]),
+        'o' => Ok(vec![EMPTY, OWN]),
+        '#' => Ok(vec![EDGE]),
+        '?' => Ok(vec![EMPTY, OWN, OTHER, EDGE]),
+        other => Err(format!("invalid pattern cell: {}", other)),
+    }
+}
+
+fn swap_colour(value: u64, swap: bool) -> u64 {
+    match value {
+        OWN if swap => OTHER,
+        OTHER if swap => OWN,
+        v => v,
+    }
+}
+
+// one of the 8 rotations and reflections
+fn transform((dx, dy): (isize, isize), symmetry: usize) -> (isize, isize) {
+    let (dx, dy) = if symmetry & 4 != 0 {
+        (dy, dx)
+    } else {
+        (dx, dy)
+    };
+    let dx = if symmetry & 1 != 0 { -dx } else { dx };
+    let dy = if symmetry & 2 != 0 { -dy } else { dy };
+    (dx, dy)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tactic {
+    // takes a block in atari
+    Capture,
+    // gives an own block in atari more liberties
+    Escape,
+    // leaves the new block one liberty
+    SelfAtari,
+}
+
+// what the step does to blocks next to it. liberties after the step are
+// guessed by adding those of the blocks joined, shared ones may be counted
+// twice.
+pub fn tactic<N: Neighbourhood>(board: &N, x: usize, y: usize, stone: Stone) -> Option<Tactic> {
+    let lines = board.lines();
+    let mut liberties = 0;
+    let mut in_atari = false;
+    let mut capture = false;
+    for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+        if nx < 1 || ny < 1 || nx > lines || ny > lines {
+            continue;
+        }
+        match board.stone_at(nx, ny) {
+            Stone::Empty => liberties += 1,
+            s if s == stone => {
+                let own = board.liberties_up_to(nx, ny, 3);
+                in_atari |= own == 1;
+                liberties += own - 1;
+            }
+            _ => capture |= board.liberties_up_to(nx, ny, 2) == 1,
+        }
+    }
+    if capture {
+        Some(Tactic::Capture)
+    } else if in_atari && liberties >= 2 {
+        Some(Tactic::Escape)
+    } else if liberties <= 1 {
+        Some(Tactic::SelfAtari)
+    } else {
+        None
+    }
+}
+
+// patterns and tactics as the policy of playouts and the priors of search
+#[derive(Default)]
+pub struct Policy {
+    patterns: PatternTable,
+}
+
+impl Policy {
+    pub fn new(patterns: PatternTable) -> Policy {
+        Policy { patterns }
+    }
+
+    // how good the step looks before any search, 1 for a plain point
+    pub fn prior<N: Neighbourhood>(&self, board: &N, x: usize, y: usize, stone: Stone) -> f64 {
+        let weight = self.patterns.weight(board, x, y, stone);
+        match tactic(board, x, y, stone) {
+            Some(Tactic::Capture) => weight * CAPTURE,
+            Some(Tactic::Escape) => weight * ESCAPE,
+            Some(Tactic::SelfAtari) => weight * SELF_ATARI,
+            None => weight,
+        }
+    }
+
+    // captures and escapes next to the last step first, then a point next
+    // to it by 3x3 patterns, a random step otherwise
+    pub fn playout_step(
+        &self,
+        board: &FastBoard,
+        stone: Stone,
+        last: Option<usize>,
+        rng: &mut Rng,
+    ) -> Option<usize> {
+        if let Some(last) = last {
+            let size = board.size();
+            let (lx, ly) = (last % size + 1, last / size + 1);
+            let mut urgent = vec![];
+            let mut shaped = vec![];
+            for (dx, dy) in SMALL_CELLS {
+                let (x, y) = (lx as isize + dx, ly as isize + dy);
+                if x < 1 || y < 1 || x > size as isize || y > size as isize {
+                    continue;
+                }
+                let (x, y) = (x as usize, y as usize);
+                let i = (y - 1) * size + x - 1;
+                if board.is_eye(i, stone) || !board.is_legal(i, stone) {
+                    continue;
+                }
+                match tactic(board, x, y, stone) {
+                    Some(Tactic::Capture) | Some(Tactic::Escape) => urgent.push(i),
+                    Some(Tactic::SelfAtari) => {}
+                    None => {
+                        let weight = self.patterns.small_weight(board, x, y, stone);
+                        if weight > 1.0 {
+                            shaped.push((i, weight));
+                        }
+                    }
+                }
+            }
+            if !urgent.is_empty() {
+                return Some(urgent[rng.below(urgent.len())]);
+            }
+            let total: f64 = shaped.iter().map(|(_, w)| w).sum();
+            let mut pick = rng.next_f64() * total;
+            for (i, weight) in shaped {
+                if pick < weight {
+                    return Some(i);
+                }
+                pick -= weight;
+            }
+        }
+        board.random_step(stone, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hane_and_capture() {
+        // black at dc is hane on white dd, the white stone at gc is in
+        // atari
+        let mut b = Board::new(BoardSize::Small);
+        for (stone, x, y) in [
+            (Stone::Black, 3, 3),
+            (Stone::White, 4, 3),
+            (Stone::Black, 5, 3),
+            (Stone::White, 7, 3),
+            (Stone::Black, 6, 3),
+            (Stone::Black, 7, 2),
+            (Stone::Black, 8, 3),
+        ] {
+            b.add(stone, x, y).unwrap();
+        }
+        let table = PatternTable::default();
+        // below the enclosed white stone, in any colour and rotation
+        assert!(table.small_weight(&b, 4, 4, Stone::Black) == 10.0);
+        assert!(table.small_weight(&b, 4, 4, Stone::White) == 10.0);
+        assert!(table.small_weight(&b, 2, 8, Stone::Black) == 1.0);
+        assert!(table.weight(&b, 4, 9, Stone::Black) == 0.2);
+
+        assert!(liberties(&b, 7, 3) == 1 && liberties(&b, 4, 3) == 2);
+        assert!(tactic(&b, 7, 4, Stone::Black) == Some(Tactic::Capture));
+        assert!(tactic(&b, 7, 4, Stone::White) == Some(Tactic::Escape));
+        let policy = Policy::default();
+        assert!(policy.prior(&b, 7, 4, Stone::Black) > policy.prior(&b, 2, 2, Stone::Black));
+
+        // the playout takes the stone at once next to the last step at hc
+        let fb = FastBoard::new(&b);
+        let mut rng = Rng::new(1);
+        let step = policy.playout_step(&fb, Stone::Black, Some(2 * 9 + 7), &mut rng);
+        assert!(step == Some(3 * 9 + 6));
+    }
+}
@@ -0,0 +1,56 @@
+use crate::basic::*;
+use crate::engine::{Engine, EngineMove, Position};
+use crate::util::Rng;
+
+// plays any legal point but its own eyes, and passes when none is left
+pub struct RandomEngine {
+    position: Position,
+    rng: Rng,
+}
+
+impl RandomEngine {
+    pub fn new(rng: Rng) -> RandomEngine {
+        RandomEngine {
+            position: Position::new(),
+            rng,
+        }
+    }
+}
+
+impl Engine for RandomEngine {
+    fn name(&self) -> String {
+        String::from("random")
+    }
+
+    fn set_boardsize(&mut self, size: BoardSize) -> Result<(), String> {
+        self.position.set_boardsize(size);
+        Ok(())
+    }
+
+    fn set_komi(&mut self, komi: f64) -> Result<(), String> {
+        self.position.komi = komi;
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), String> {
+        self.position.clear();
+        Ok(())
+    }
+
+    fn play(&mut self, player: Player, step: Cmd) -> Result<(), String> {
+        self.position.play(player, step)
+    }
+
+    fn genmove(&mut self, player: Player) -> Result<EngineMove, String> {
+        self.position.turn(&player)?;
+        let moves = self.position.candidates();
+        let step = if moves.is_empty() {
+            Cmd::Pass
+        } else {
+            let (x, y) = moves[self.rng.below(moves.len())];
+            Cmd::Step(Cmd::point_to_cmd(x, y))
+        };
+        self.position.play(player, step.clone())?;
+        Ok(EngineMove::Play(step))
+    }
+}
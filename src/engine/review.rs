@@ -0,0 +1,273 @@
+use crate::basic::*;
+use crate::engine::{Analysis, Engine};
+
+// win rate lost by a bad step, and by a doubtful one
+const BAD_LOSS: f64 = 0.2;
+const DOUBTFUL_LOSS: f64 = 0.1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepReview {
+    // step number from 1
+    pub number: usize,
+    pub player: Player,
+    pub step: Cmd,
+    // analysis of the position before the step
+    pub analysis: Analysis,
+    // win rate of the player after the step
+    pub win_rate: f64,
+}
+
+impl StepReview {
+    // win rate lost by the step compared to the best one
+    pub fn loss(&self) -> f64 {
+        (self.analysis.win_rate - self.win_rate).max(0.0)
+    }
+
+    pub fn is_best(&self) -> bool {
+        self.step == self.analysis.best
+    }
+
+    fn annotation(&self) -> Option<MoveAnnotation> {
+        if self.loss() >= BAD_LOSS {
+            Some(MoveAnnotation::Bad)
+        } else if self.loss() >= DOUBTFUL_LOSS {
+            Some(MoveAnnotation::Doubtful)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerSummary {
+    pub steps: usize,
+    // steps which are the best of the engine
+    pub best: usize,
+    pub average_loss: f64,
+    pub doubtful: usize,
+    pub bad: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Review {
+    pub steps: Vec<StepReview>,
+}
+
+impl Review {
+    // doubtful and bad steps from the biggest loss
+    pub fn mistakes(&self, count: usize) -> Vec<&StepReview> {
+        let mut mistakes: Vec<&StepReview> = self
+            .steps
+            .iter()
+            .filter(|s| s.annotation().is_some())
+            .collect();
+        mistakes.sort_by(|a, b| b.loss().total_cmp(&a.loss()));
+        mistakes.truncate(count);
+        mistakes
+    }
+
+    pub fn summary(&self, player: &Player) -> PlayerSummary {
+        let steps: Vec<&StepReview> = self.steps.iter().filter(|s| s.player == *player).collect();
+        let annotated = |a| steps.iter().filter(|s| s.annotation() == Some(a)).count();
+        PlayerSummary {
+            steps: steps.len(),
+            best: steps.iter().filter(|s| s.is_best()).count(),
+            average_loss: steps.iter().map(|s| s.loss()).sum::<f64>() / steps.len().max(1) as f64,
+            doubtful: annotated(MoveAnnotation::Doubtful),
+            bad: annotated(MoveAnnotation::Bad),
+        }
+    }
+}
+
+// analyze every position of the path to current node, which is the main
+// line of a loaded game. each step gets a comment of its win rate and is
+// annotated doubtful or bad by its loss, and the variation of the engine
+// is added beside the steps which are not the best. on_step is called
+// with the position number and the count of steps before each analysis.
+pub fn review_game(
+    g: &mut Game,
+    engine: &mut dyn Engine,
+    mut on_step: impl FnMut(usize, usize),
+) -> Result<Review, String> {
+    let steps = g.steps();
+    engine.set_boardsize(g.board_size())?;
+    engine.set_komi(g.info().komi.unwrap_or_default())?;
+    engine.clear()?;
+    for (x, y) in g.handicap_stones() {
+        engine.play(Player::Black, Cmd::Step(Cmd::point_to_cmd(x, y)))?;
+    }
+    // walk the path again from the first position
+    let undo_rule = g.undo_rule();
+    g.set_undo_rule(UndoRule::Free);
+    for _ in 0..steps.len() {
+        g.undo()?;
+    }
+    let mut review = Review::default();
+    let mut before: Option<Analysis> = None;
+    for n in 0..=steps.len() {
+        let player = g.next_player();
+        on_step(n, steps.len());
+        let analysis = engine.analyze(player.clone())?;
+        if let Some(before) = before {
+            let step = StepReview {
+                number: n,
+                player: player.another(),
+                step: steps[n - 1].clone(),
+                analysis: before,
+                win_rate: 1.0 - analysis.win_rate,
+            };
+            annotate(g, &step, &analysis);
+            review.steps.push(step);
+        }
+        if let Some(step) = steps.get(n) {
+            let main = g
+                .redo_list()
+                .iter()
+                .position(|c| c == step)
+                .ok_or_else(|| format!("step {} is not found", n + 1))?;
+            if analysis.best != *step {
+                add_variation(g, &player, &analysis)?;
+            }
+            engine.play(player, step.clone())?;
+            g.redo(main)?;
+        }
+        before = Some(analysis);
+    }
+    g.set_undo_rule(undo_rule);
+    Ok(review)
+}
+
+// comment like "black 42.0% (-12.5%), best dd, score W+2.5" on the node of
+// the step, after what was there
+fn annotate(g: &mut Game, step: &StepReview, after: &Analysis) {
+    let mut comment = format!("{} {:.1}%", step.player, step.win_rate * 100.0);
+    if step.loss() > 0.0 {
+        comment.push_str(&format!(" (-{:.1}%)", step.loss() * 100.0));
+    }
+    if !step.is_best() {
+        comment.push_str(&format!(", best {}", step.analysis.best));
+    }
+    if let Some(score) = after.score {
+        comment.push_str(&format!(
+            ", score {}",
+            format_score((score * 2.0).round() / 2.0)
+        ));
+    }
+    let mut note = g.note();
+    if !note.comment.is_empty() {
+        note.comment.push('\n');
+    }
+    note.comment.push_str(&comment);
+    if note.annotation.is_none() {
+        note.annotation = step.annotation();
+    }
+    g.set_note(note);
+}
+
+// steps of the engine as a branch of current node, as far as they are legal
+fn add_variation(g: &mut Game, player: &Player, analysis: &Analysis) -> Result<(), String> {
+    let mut played = 0;
+    for step in analysis.variation.iter() {
+        if g.next(step.clone()).is_err() {
+            break;
+        }
+        if played == 0 {
+            g.set_comment(format!(
+                "best for {} {:.1}%",
+                player,
+                analysis.win_rate * 100.0
+            ));
+        }
+        played += 1;
+    }
+    for _ in 0..played {
+        g.undo()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineMove;
+
+    // win rates of the player to move in each position, and ee is the
+    // best step always
+    struct Scripted {
+        rates: Vec<f64>,
+        played: usize,
+    }
+
+    impl Engine for Scripted {
+        fn name(&self) -> String {
+            String::from("scripted")
+        }
+
+        fn set_boardsize(&mut self, _size: BoardSize) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn set_komi(&mut self, _komi: f64) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn clear(&mut self) -> Result<(), String> {
+            self.played = 0;
+            Ok(())
+        }
+
+        fn play(&mut self, _player: Player, _step: Cmd) -> Result<(), String> {
+            self.played += 1;
+            Ok(())
+        }
+
+        fn genmove(&mut self, _player: Player) -> Result<EngineMove, String> {
+            Err(String::from("not used"))
+        }
+
+        fn analyze(&mut self, _player: Player) -> Result<Analysis, String> {
+            let best = Cmd::Step(String::from("ee"));
+            Ok(Analysis {
+                best: best.clone(),
+                win_rate: self.rates[self.played],
+                score: Some(-3.2),
+                variation: vec![best, Cmd::Step(String::from("ec"))],
+                visits: vec![],
+            })
+        }
+    }
+
+    #[test]
+    fn white_blunders() {
+        let mut g = Game::new(BoardSize::Small);
+        for step in ["aa", "ee", "cc"] {
+            g.next(Cmd::Step(step.to_string())).unwrap();
+        }
+        g.set_comment(String::from("last"));
+        let mut engine = Scripted {
+            rates: vec![0.5, 0.5, 0.9, 0.1],
+            played: 0,
+        };
+        let mut calls = 0;
+        let review = review_game(&mut g, &mut engine, |_, _| calls += 1).unwrap();
+        assert!(calls == 4 && review.steps.len() == 3 && g.step_count() == 3);
+        let mistakes = review.mistakes(5);
+        assert!(mistakes.len() == 1 && mistakes[0].number == 2);
+        assert!(mistakes[0].player == Player::White && (mistakes[0].loss() - 0.4).abs() < 1e-9);
+        let white = review.summary(&Player::White);
+        assert!(white.steps == 1 && white.best == 1 && white.bad == 1);
+        assert!(g.note().comment == "last\nblack 90.0%, best ee, score W+3");
+
+        g.undo().unwrap();
+        assert!(g.note().annotation == Some(MoveAnnotation::Bad));
+        g.undo().unwrap();
+        g.undo().unwrap();
+        // ee is beside aa, there is none beside ee which is the best
+        assert!(g.redo_list().len() == 2);
+        g.redo(1).unwrap();
+        assert!(g.note().comment == "best for black 50.0%" && g.redo_list().len() == 1);
+        g.undo().unwrap();
+        g.redo(0).unwrap();
+        assert!(g.redo_list().len() == 1);
+    }
+}
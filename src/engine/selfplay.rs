@@ -0,0 +1,414 @@
+use crate::basic::*;
+use crate::engine::{create_engine, Analysis};
+use crate::util::Rng;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+// records file, little endian:
+//
+//     b"rustgo/selfplay\n" <version u8> <lines u8>
+//     record...
+//
+// record of a position, all of the same length for the board:
+//
+//     <game u32> <flags u8> <result i8> <own plane> <opponent plane> <visits>
+//
+// flags are 1 for white to move and 2 for the last record of a game. result
+// is 1 if the player to move won, -1 if lost and 0 for a draw. planes are
+// one bit for each point y * lines + x from 0, visits are u16 of each point
+// then pass.
+const RECORDS_MAGIC: &[u8] = b"rustgo/selfplay\n";
+const RECORDS_VERSION: u8 = 1;
+const WHITE_FLAG: u8 = 1;
+const LAST_FLAG: u8 = 2;
+
+// steps at the start of a game picked in proportion to visits, so that
+// games do not all play the same opening
+const SAMPLED_STEPS: usize = 20;
+// resign when the player to move wins less than this, after the sampled
+// steps
+const RESIGN_RATE: f64 = 0.05;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfPlayOptions {
+    // games the file has when done, games already in it are kept
+    pub games: usize,
+    pub size: BoardSize,
+    pub komi: f64,
+    // game n is played with seed + n, so the same games are made again
+    pub seed: u64,
+    // each position is written in all 8 symmetries
+    pub augment: bool,
+}
+
+impl Default for SelfPlayOptions {
+    fn default() -> Self {
+        SelfPlayOptions {
+            games: 100,
+            size: BoardSize::Small,
+            komi: 7.5,
+            seed: 0,
+            augment: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainingRecord {
+    // game number from 0
+    pub game: usize,
+    pub player: Player,
+    pub last: bool,
+    pub result: i8,
+    // stones of the player to move and of the opponent, y * lines + x from 0
+    pub own: Vec<bool>,
+    pub opponent: Vec<bool>,
+    // playouts of each point and then pass
+    pub visits: Vec<u16>,
+}
+
+impl TrainingRecord {
+    fn new(game: usize, board: &Board, player: Player, analysis: &Analysis) -> TrainingRecord {
+        let lines = board.lines();
+        let stones = |stone: Stone| {
+            (0..lines * lines)
+                .map(|i| board.at(i % lines + 1, i / lines + 1) == Ok(stone))
+                .collect()
+        };
+        let mut visits = vec![0; lines * lines + 1];
+        for (step, count) in analysis.visits.iter() {
+            let i = match step {
+                Cmd::Step(p) => match Notation::Sgf.parse(p, lines) {
+                    Ok((x, y)) => (y - 1) * lines + x - 1,
+                    Err(_) => continue,
+                },
+                _ => lines * lines,
+            };
+            visits[i] = (*count).min(u16::MAX as usize) as u16;
+        }
+        TrainingRecord {
+            game,
+            own: stones(player.stone()),
+            opponent: stones(player.stone().another()),
+            player,
+            last: false,
+            result: 0,
+            visits,
+        }
+    }
+
+    pub fn transform(&self, symmetry: Symmetry, lines: usize) -> TrainingRecord {
+        let mut record = self.clone();
+        for i in 0..lines * lines {
+            let (x, y) = symmetry.point(i % lines + 1, i / lines + 1, lines);
+            let j = (y - 1) * lines + x - 1;
+            record.own[j] = self.own[i];
+            record.opponent[j] = self.opponent[i];
+            record.visits[j] = self.visits[i];
+        }
+        record
+    }
+
+    fn encode(&self, data: &mut Vec<u8>) {
+        data.extend((self.game as u32).to_le_bytes());
+        let mut flags = 0;
+        if self.player == Player::White {
+            flags |= WHITE_FLAG;
+        }
+        if self.last {
+            flags |= LAST_FLAG;
+        }
+        data.push(flags);
+        data.push(self.result as u8);
+        for plane in [&self.own, &self.opponent] {
+            for bits in plane.chunks(8) {
+                let byte = bits
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |b, (i, bit)| b | ((*bit as u8) << i));
+                data.push(byte);
+            }
+        }
+        for count in self.visits.iter() {
+            data.extend(count.to_le_bytes());
+        }
+    }
+
+    fn decode(data: &[u8], lines: usize) -> TrainingRecord {
+        let points = lines * lines;
+        let plane_len = points.div_ceil(8);
+        let plane = |at: usize| {
+            (0..points)
+                .map(|i| data[at + i / 8] & (1 << (i % 8)) != 0)
+                .collect()
+        };
+        let visits_at = 6 + plane_len * 2;
+        TrainingRecord {
+            game: u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize,
+            player: if data[4] & WHITE_FLAG != 0 {
+                Player::White
+            } else {
+                Player::Black
+            },
+            last: data[4] & LAST_FLAG != 0,
+            result: data[5] as i8,
+            own: plane(6),
+            opponent: plane(6 + plane_len),
+            visits: (0..=points)
+                .map(|i| u16::from_le_bytes([data[visits_at + i * 2], data[visits_at + i * 2 + 1]]))
+                .collect(),
+        }
+    }
+}
+
+fn header_len() -> usize {
+    RECORDS_MAGIC.len() + 2
+}
+
+fn record_len(lines: usize) -> usize {
+    let points = lines * lines;
+    6 + points.div_ceil(8) * 2 + (points + 1) * 2
+}
+
+// lines of the board and the records of a file
+pub fn read_records(filename: &str) -> Result<(usize, Vec<TrainingRecord>), String> {
+    let data = fs::read(filename).map_err(|err| format!("can not read {}: {}", filename, err))?;
+    let lines =
+        parse_header(&data).map_err(|err| format!("invalid records {}, {}", filename, err))?;
+    let records = data[header_len()..]
+        .chunks_exact(record_len(lines))
+        .map(|r| TrainingRecord::decode(r, lines))
+        .collect();
+    Ok((lines, records))
+}
+
+fn parse_header(data: &[u8]) -> Result<usize, String> {
+    if data.len() < header_len() || !data.starts_with(RECORDS_MAGIC) {
+        return Err(String::from("not rustgo selfplay records"));
+    }
+    let version = data[RECORDS_MAGIC.len()];
+    if version > RECORDS_VERSION {
+        return Err(format!("unsupported version {}", version));
+    }
+    Ok(data[RECORDS_MAGIC.len() + 1] as usize)
+}
+
+// the file of records opened to add games. records after the last whole
+// game, which are left by a run stopped in the middle, are cut off.
+struct RecordFile {
+    file: File,
+    next_game: usize,
+}
+
+impl RecordFile {
+    fn open(filename: &str, lines: usize) -> Result<RecordFile, String> {
+        let mut next_game = 0;
+        let mut keep = header_len();
+        if Path::new(filename).exists() {
+            let data =
+                fs::read(filename).map_err(|err| format!("can not read {}: {}", filename, err))?;
+            let found = parse_header(&data)
+                .map_err(|err| format!("invalid records {}, {}", filename, err))?;
+            if found != lines {
+                return Err(format!(
+                    "records of {} are for {} * {} boards",
+                    filename, found, found
+                ));
+            }
+            let len = record_len(lines);
+            for (n, r) in data[header_len()..].chunks_exact(len).enumerate() {
+                let record = TrainingRecord::decode(r, lines);
+                if record.last {
+                    next_game = record.game + 1;
+                    keep = header_len() + (n + 1) * len;
+                }
+            }
+        } else {
+            let mut header = RECORDS_MAGIC.to_vec();
+            header.extend([RECORDS_VERSION, lines as u8]);
+            fs::write(filename, header)
+                .map_err(|err| format!("can not write {}: {}", filename, err))?;
+        }
+        let file = OpenOptions::new()
+            .append(true)
+            .open(filename)
+            .map_err(|err| format!("can not open {}: {}", filename, err))?;
+        file.set_len(keep as u64)
+            .map_err(|err| format!("can not write {}: {}", filename, err))?;
+        Ok(RecordFile { file, next_game })
+    }
+
+    // the records of a game at once
+    fn add(&mut self, records: &[TrainingRecord]) -> Result<(), String> {
+        let mut data = vec![];
+        for record in records {
+            record.encode(&mut data);
+        }
+        self.file
+            .write_all(&data)
+            .and_then(|_| self.file.flush())
+            .map_err(|err| format!("can not write records: {}", err))
+    }
+}
+
+// a game of the engine against itself with a record of each position,
+// the results of records are set when the game ends
+pub fn self_play_game(
+    spec: &str,
+    size: BoardSize,
+    komi: f64,
+    game: usize,
+    rng: &mut Rng,
+) -> Result<(Game, Vec<TrainingRecord>), String> {
+    let mut engine = create_engine(spec, Rng::new(rng.next_u64()))?;
+    let mut g = Game::new(size.clone());
+    g.set_info("black", &engine.name())?;
+    g.set_info("white", &engine.name())?;
+    g.set_info("komi", &komi.to_string())?;
+    engine.set_boardsize(size.clone())?;
+    engine.set_komi(komi)?;
+    engine.clear()?;
+    let lines = size.lines();
+    let mut records = vec![];
+    let mut passes = 0;
+    let mut winner = None;
+    // games with ko may never end
+    while g.step_count() < lines * lines * 3 {
+        let player = g.next_player();
+        let analysis = engine.analyze(player.clone())?;
+        if analysis.visits.is_empty() {
+            return Err(format!("{} does not tell visits", engine.name()));
+        }
+        let sampled = g.step_count() < SAMPLED_STEPS;
+        if !sampled && analysis.win_rate < RESIGN_RATE {
+            let result = if player == Player::Black {
+                "W+R"
+            } else {
+                "B+R"
+            };
+            g.set_info("result", result)?;
+            winner = Some(player.another());
+            break;
+        }
+        let step = if sampled {
+            sample(&analysis, rng)
+        } else {
+            analysis.best.clone()
+        };
+        records.push(TrainingRecord::new(
+            game,
+            g.board(),
+            player.clone(),
+            &analysis,
+        ));
+        g.next(step.clone())
+            .map_err(|err| format!("{} played {}: {}", engine.name(), step, err))?;
+        engine.play(player, step.clone())?;
+        passes = if step == Cmd::Pass { passes + 1 } else { 0 };
+        if passes == 2 {
+            break;
+        }
+    }
+    if winner.is_none() {
+        let score = bouzy(g.board()).score(komi);
+        g.set_info("result", &format_score(score))?;
+        if score != 0.0 {
+            winner = Some(if score > 0.0 {
+                Player::Black
+            } else {
+                Player::White
+            });
+        }
+    }
+    for record in records.iter_mut() {
+        record.result = match &winner {
+            Some(w) if *w == record.player => 1,
+            Some(_) => -1,
+            None => 0,
+        };
+    }
+    Ok((g, records))
+}
+
+// a step picked in proportion to its visits
+fn sample(analysis: &Analysis, rng: &mut Rng) -> Cmd {
+    let total: usize = analysis.visits.iter().map(|(_, n)| n).sum();
+    if total == 0 {
+        return analysis.best.clone();
+    }
+    let mut left = rng.below(total);
+    for (step, n) in analysis.visits.iter() {
+        if left < *n {
+            return step.clone();
+        }
+        left -= n;
+    }
+    analysis.best.clone()
+}
+
+// play games of the engine against itself and add their records to the
+// file until it has options.games games. on_game is called after each
+// game with its number from 0 and the count of records written.
+pub fn run_self_play(
+    spec: &str,
+    filename: &str,
+    options: &SelfPlayOptions,
+    mut on_game: impl FnMut(usize, &Game, usize),
+) -> Result<usize, String> {
+    let lines = options.size.lines();
+    let mut file = RecordFile::open(filename, lines)?;
+    let first = file.next_game;
+    for n in first..options.games {
+        let mut rng = Rng::new(options.seed.wrapping_add(n as u64));
+        let (g, positions) = self_play_game(spec, options.size.clone(), options.komi, n, &mut rng)?;
+        let mut records = vec![];
+        for record in positions.iter() {
+            if options.augment {
+                records.extend(SYMMETRIES.iter().map(|s| record.transform(*s, lines)));
+            } else {
+                records.push(record.clone());
+            }
+        }
+        if let Some(last) = records.last_mut() {
+            last.last = true;
+        }
+        file.add(&records)?;
+        on_game(n, &g, records.len());
+    }
+    Ok(options.games.saturating_sub(first))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn resume_after_stopped() {
+        let dir = TempDir::new("selfplay");
+        let filename = dir.file("records");
+        let options = SelfPlayOptions {
+            games: 2,
+            seed: 7,
+            augment: true,
+            ..SelfPlayOptions::default()
+        };
+        let played = run_self_play("mcts-uniform:2", &filename, &options, |_, _, _| {}).unwrap();
+        let whole = fs::read(&filename).unwrap();
+        let (lines, records) = read_records(&filename).unwrap();
+        assert!(played == 2 && lines == 9 && records.len() % 8 == 0);
+        assert!(records.iter().filter(|r| r.last).count() == 2);
+        assert!(records.last().unwrap().game == 1 && records[0].player == Player::Black);
+        // the first position is empty and the sampled step has a visit
+        assert!(records[0].own.iter().all(|s| !s) && records[0].visits.iter().any(|v| *v > 0));
+        let rotated = &records[1];
+        assert!(*rotated == records[0].transform(Symmetry::Rotate90, 9));
+        assert!(records[8].player == Player::White && records[8].result == -records[0].result);
+
+        // stopped in the middle of the second game, which is played again
+        fs::write(&filename, &whole[..whole.len() - 100]).unwrap();
+        let played = run_self_play("mcts-uniform:2", &filename, &options, |_, _, _| {}).unwrap();
+        assert!(played == 1 && fs::read(&filename).unwrap() == whole);
+    }
+}
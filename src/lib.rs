@@ -0,0 +1,7 @@
+pub mod basic;
+pub mod db;
+pub mod engine;
+pub mod net;
+pub mod tournament;
+pub mod tui;
+pub mod util;
@@ -1,51 +1,1036 @@
-mod basic;
-mod util;
-
-use crate::basic::{BoardSize, Cmd, Game, Player};
+use rustgo::basic::{
+    bouzy, format_score, monte_carlo, parse_sensei, Board, BoardSize, Cmd, Game, Mark, Markup,
+    Notation, Player, ReplayOptions, Stone, Style, UndoRule, INFO_KEYS,
+};
+use rustgo::db::{Book, Database, NextMove, Pattern, Query};
+use rustgo::engine::{
+    create_engine, play_out, review_game, run_match, run_self_play, MatchOptions, SelfPlayOptions,
+};
+use rustgo::net::{ApiServer, Client, GameEvent, Message, Messages, Server, Watcher};
+use rustgo::tournament::{Ratings, System, Tournament};
+use rustgo::util::Rng;
 use std::env;
-use std::io;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
 use std::process::exit;
+use std::sync::mpsc;
+use std::thread;
+
+// autosave file, which is removed when exit normally
+const RECOVERY_FILE: &str = "rustgo_recovery.txt";
 
 fn show_usage() {
     println!("Usage: <command> [options]");
-    println!("");
+    println!();
     println!("option list:");
-    println!("\t{}: \t\t{}", "new", "start 19 * 19 game");
-    println!("\t{}: \t{}", "medium", "start 13 * 13 game");
-    println!("\t{}: \t\t{}", "small", "start 9 * 9 game");
-    println!("");
+    println!("\tnew: \t\tstart 19 * 19 game");
+    println!("\tmedium: \tstart 13 * 13 game");
+    println!("\tsmall: \t\tstart 9 * 9 game");
+    println!();
+    println!("\tload <dump-file-path>: \tstart by loading a dumped file or a .sgf file");
+    println!("\ttui <option>: \tplay in full screen with cursor and mouse, like tui small");
+    println!(
+        "\tconvert <from> <to>: \tconvert between dump and .sgf files, old dump files are upgraded"
+    );
+    println!(
+        "\texport-svg <file> <svg-path> [<from>-<to>] [nocoords]: \tsave the final position of"
+    );
+    println!("\t\ta dump or .sgf file as svg, stones played in steps from-to are numbered");
     println!(
-        "\t{}: \t{}",
-        "load <dump-file-path>", "start by loading a dumped file"
+        "\tsearch <dir> [<condition> <value>]...: \tsearch games in sgf and dump files of dir,"
+    );
+    println!("\t\tconditions are player, black, white, date, result and position, like");
+    println!("\t\tsearch games player Shusaku date 1840..1850 result B+");
+    println!("\t\tposition is a $$ diagram or game file, found in any rotation or reflection");
+    println!("\t\tpattern is a file of X O . ? rows, with - and | for edges, found anywhere in");
+    println!("\t\tany rotation or reflection, and with colours swapped if swap is yes");
+    println!("\texplore <dir> [<depth>] [nosymmetry]: \tbrowse the opening steps of the games");
+    println!("\t\tin dir with counts and win rates, the same opening in any corner is merged");
+    println!("\t\tunless nosymmetry is given, depth is 30 steps by default");
+    println!("\tserve [<port>] [small|medium|new] [<komi>] [none|once|unlimited]: \thost a game");
+    println!("\t\ton port, 7070 by default, and play it with the one joined by connect,");
+    println!("\t\tsteps taken back are limited by the last option, unlimited by default");
+    println!("\tconnect <host:port> [black|white] [<name>]: \tjoin a game hosted by serve");
+    println!("\treconnect <host:port> <token>: \tcome back to the game after disconnected");
+    println!("\twatch <host:port>[/<path>]: \tfollow a game hosted by serve, or the events of");
+    println!("\t\ta game of serve-http like watch localhost:8080/games/1/events");
+    println!("\tserve-http [<port>] [<dir>]: \tserve games as json over http on port, 8080 by");
+    println!("\t\tdefault, games are kept in dir as <id>.sgf if given");
+    println!("\tmatch <engine> <engine> [<games>] [small|medium|new] [<komi>] [<dir>]: \tplay");
+    println!("\t\tgames between engines, which are random, mcts[:<playouts>] with patterns,");
+    println!("\t\tmcts-uniform[:<playouts>] with random playouts, gtp:<command> like");
+    println!("\t\t\"gtp:gnugo --mode gtp\" or katago:<command> of a json analysis");
+    println!("\t\tengine like \"katago:katago analysis -config a.cfg -model m.bin.gz\",");
+    println!("\t\tthey take turns at black, 10 games on 9 * 9 by default, games are saved");
+    println!("\t\tin dir if given");
+    println!("\treview <file> [<engine>] [<output>]: \tanalyze each step of the main line by the");
+    println!("\t\tengine, mcts by default, and save the game with win rates in comments,");
+    println!("\t\tmistakes annotated and better steps as variations to output, which is");
+    println!("\t\t<file>-review.sgf by default, then show the biggest mistakes");
+    println!("\ttournament <file> <command>: \trun a tournament kept in file, commands are");
+    println!("\t\tnew <round-robin|swiss|mcmahon> [<rounds>] [small|medium|new] [<komi>]");
+    println!("\t\t[handicap] [bar <rank>], add <rank> <name> like add 3k Ann, pair for the");
+    println!("\t\tnext round, result <board> <result> like result 2 B+R, record <sgf>...,");
+    println!("\t\tgame <board> <file> to save the game of the round with handicap set,");
+    println!("\t\tplay [<dir>] to play the games of the round between engines, players");
+    println!("\t\tadded by engine names like add 1k mcts:500, standings, and rate");
+    println!("\t\t<ratings-file> to update elo and glicko ratings by the games once the");
+    println!("\t\ttournament is over, which is done once");
+    println!("\tselfplay <file> [<games>] [<engine>] [small|medium|new] [<komi>] [seed <n>]");
+    println!("\t\t[augment]: \tplay games of the engine, mcts:200 by default, against itself");
+    println!("\t\tand add a training record of each position to file, with stones, the");
+    println!("\t\tvisits of steps and the result, 100 games with seed 0 by default. each");
+    println!("\t\tposition is written in all 8 symmetries if augment is given. the same");
+    println!("\t\tseed makes the same games, and a stopped run goes on from the last game");
+    println!("\tdiagram <file>: \tshow the position of a sensei's library $$ diagram");
+    println!("\texport-replay <file> <path> [<delay-ms>] [<pixels>]: \tsave the main line of");
+    println!(
+        "\t\ta dump or .sgf file as animated image, apng if path ends with .png, gif otherwise"
     );
 }
 
 fn show_operator_usage() {
     println!("System Operators:");
-    println!("\t{}: \t{}", "help", "show this.");
+    println!("\thelp: \tshow this.");
+    println!("\texit: \texit game immediately, without saving.");
+    println!("\tdump [<path>]: dump current steps, which can be load anytime.");
+    println!("\t\tthe path ends with .sgf is dumped as sgf.");
+    println!("\tsgf: \tdump the whole game tree as sgf.");
+    println!("\texport-svg <path> [<from>-<to>] [nocoords]: \tsave current position as svg,");
+    println!("\t\tstones played in steps from-to are numbered.");
+    println!("\tdiagram <sensei|igo> [<from>-<to>] [nocoords]: \tprint current position as");
+    println!("\t\tsensei's library $$ diagram or latex igo commands.");
+    println!("\texport-replay <path> [<delay-ms>] [<pixels>]: \tsave steps until now as animated");
+    println!("\t\timage, apng if path ends with .png, gif otherwise.");
     println!(
-        "\t{}: \t{}",
-        "exit", "exit game immediately, without saving."
+        "\tautosave <on|off>: \tsave to {} after every step, on by default.",
+        RECOVERY_FILE
     );
+    println!("\tinfo [<key> <value>]: \tshow game info, or set one of:");
     println!(
-        "\t{}: {}",
-        "dump", "dump current steps, which can be load anytime."
+        "\t\t{}",
+        INFO_KEYS
+            .iter()
+            .map(|(k, _)| *k)
+            .collect::<Vec<&str>>()
+            .join(", ")
     );
     println!("Game Operators:");
+    println!("\tpass: \tlet another player step without any stone put in.");
+    println!(
+        "\tundo: \tget back stone just put in, the opponent is asked unless undo rule is free."
+    );
+    println!(
+        "\tundo-rule <free|none|once|unlimited>: \tfree to undo any time, or asked by the one"
+    );
+    println!("\t\tjust played and accepted by the opponent, never, once for each or any times.");
+    println!("\tredo: \tredo the undo step.");
+    println!("\t**: \tlike aa, bc, etc., put the stone on that point.");
+    println!("\tnotation <sgf|gtp|numeric|japanese>: \tpoint notation, like bc, B18, 2,3 or 18-3.");
+    println!("\ttransform <symmetry>: \trotate or flip the whole game, one of identity,");
+    println!("\t\trotate90, rotate180, rotate270, flip-horizontal, flip-vertical, transpose,");
+    println!("\t\tanti-transpose.");
+    println!("Display Operators:");
+    println!("\ttheme <classic|ascii|unicode|color>: \thow the board is drawn.");
+    println!("\tlastmove <on|off>: \tmark the stone of last step.");
+    println!("\tnumbers <from|off>: \tshow step numbers on stones played from the step.");
+    println!("\testimate [montecarlo [<playouts>]]: \tshow owner of points and estimated score,");
+    println!("\t\tb and w for territory, triangles for dead stones.");
+    println!("Review Operators:");
+    println!("\tcomment <text>: \tset comment of current step, empty to clear.");
+    println!("\tannotate <good|bad|doubtful|interesting|none>: \tannotate current step.");
+    println!("\tjudge <even|black|white|unclear|none>: \tjudge current position.");
+    println!("\tmark <triangle|square|circle> **: \tmark the point.");
+    println!("\tlabel ** <text>: \tlabel the point with letters or numbers.");
+    println!("\tunmark **: \tremove mark of the point.");
+}
+
+// review operators, return false if input is not one of them
+fn review(g: &mut Game, input: &str) -> bool {
+    let (op, arg) = input.split_once(' ').unwrap_or((input, ""));
+    let arg = arg.trim();
+    let ret = match op {
+        "comment" => {
+            g.set_comment(arg.to_string());
+            Ok(())
+        }
+        "annotate" => match arg {
+            "none" => {
+                g.set_annotation(None);
+                Ok(())
+            }
+            other => other.parse().map(|a| g.set_annotation(Some(a))),
+        },
+        "judge" => match arg {
+            "none" => {
+                g.set_judgement(None);
+                Ok(())
+            }
+            other => other.parse().map(|j| g.set_judgement(Some(j))),
+        },
+        "mark" => {
+            let (kind, point) = arg.split_once(' ').unwrap_or((arg, ""));
+            let mark = match kind {
+                "triangle" => Ok(Mark::Triangle),
+                "square" => Ok(Mark::Square),
+                "circle" => Ok(Mark::Circle),
+                other => Err(format!("invalid mark: {}", other)),
+            };
+            mark.and_then(|m| g.add_mark(g.sgf_point(point)?, m))
+        }
+        "label" => {
+            let (point, text) = arg.split_once(' ').unwrap_or((arg, ""));
+            g.sgf_point(point)
+                .and_then(|p| g.add_mark(p, Mark::Label(text.trim().to_string())))
+        }
+        "unmark" => g.sgf_point(arg).and_then(|p| g.remove_mark(p)),
+        _ => return false,
+    };
+    if let Err(err) = ret {
+        println!("invalid review input: {}", err);
+    }
+    true
+}
+
+// options are [<port>] [small|medium|new] [<komi>], the host plays too
+fn serve(options: &[String]) -> Result<(), String> {
+    let mut port = 7070;
+    let mut size = BoardSize::Normal;
+    let mut komi = 6.5;
+    let mut undo_rule = UndoRule::Unlimited;
+    for (i, option) in options.iter().enumerate() {
+        match option.as_str() {
+            "none" | "once" | "unlimited" => undo_rule = option.parse()?,
+            "small" => size = BoardSize::Small,
+            "medium" => size = BoardSize::Medium,
+            "new" => size = BoardSize::Normal,
+            other if i == 0 => {
+                port = other
+                    .parse()
+                    .map_err(|_| format!("invalid port: {}", other))?
+            }
+            other => {
+                komi = other
+                    .parse()
+                    .map_err(|_| format!("invalid komi: {}", other))?
+            }
+        }
+    }
+    let mut server = Server::bind(&format!("0.0.0.0:{}", port), size, komi)?;
+    server.set_undo_rule(undo_rule);
+    println!(
+        "serving on port {}, the opponent joins by connect <host>:{}",
+        port, port
+    );
+    println!("spectators follow the game by watch <host>:{}", port);
+    // the game ends when the host leaves
+    let stopper = server.stopper();
+    let handle = thread::spawn(move || server.run().and_then(|g| g.dump("sgf")));
+    match Client::join(&format!("127.0.0.1:{}", port), None, "host") {
+        Ok((client, messages)) => play_online(client, messages),
+        Err(err) => {
+            stopper.stop();
+            return Err(err);
+        }
+    }
+    stopper.stop();
+    match handle.join() {
+        Ok(Ok(filename)) => println!("game saved to {}", filename),
+        Ok(Err(err)) => println!("can not save game: {}", err),
+        Err(_) => println!("server failed"),
+    }
+    Ok(())
+}
+
+// options are [<games>] [<engine>] [small|medium|new] [<komi>] [seed <n>]
+// [augment]
+fn self_play(filename: &str, options: &[String]) -> Result<(), String> {
+    let mut spec = "mcts:200";
+    let mut self_play_options = SelfPlayOptions::default();
+    let mut options = options.iter().enumerate();
+    while let Some((i, option)) = options.next() {
+        match option.as_str() {
+            "small" => self_play_options.size = BoardSize::Small,
+            "medium" => self_play_options.size = BoardSize::Medium,
+            "new" => self_play_options.size = BoardSize::Normal,
+            "augment" => self_play_options.augment = true,
+            "seed" => {
+                let seed = options.next().map_or("", |(_, s)| s.as_str());
+                self_play_options.seed =
+                    seed.parse().map_err(|_| format!("invalid seed {}", seed))?;
+            }
+            other if i == 0 && other.parse::<usize>().is_ok() => {
+                self_play_options.games = other.parse().unwrap()
+            }
+            other if other.parse::<f64>().is_ok() => {
+                self_play_options.komi = other.parse().unwrap()
+            }
+            other => spec = other,
+        }
+    }
+    let played = run_self_play(spec, filename, &self_play_options, |n, g, records| {
+        println!(
+            "game {}: {} in {} steps, {} records",
+            n + 1,
+            g.info().result,
+            g.step_count(),
+            records
+        );
+    })?;
+    println!(
+        "{} games played, {} has {} games",
+        played, filename, self_play_options.games
+    );
+    Ok(())
+}
+
+// options are [<games>] [small|medium|new] [<komi>] [<dir>]
+fn play_match(first: &str, second: &str, options: &[String]) -> Result<(), String> {
+    let mut match_options = MatchOptions::default();
+    for (i, option) in options.iter().enumerate() {
+        match option.as_str() {
+            "small" => match_options.size = BoardSize::Small,
+            "medium" => match_options.size = BoardSize::Medium,
+            "new" => match_options.size = BoardSize::Normal,
+            other if i == 0 && other.parse::<usize>().is_ok() => {
+                match_options.games = other.parse().unwrap()
+            }
+            other if other.parse::<f64>().is_ok() => match_options.komi = other.parse().unwrap(),
+            other => match_options.dir = Some(other.to_string()),
+        }
+    }
+    let mut rng = Rng::from_time();
+    let mut engines = [
+        create_engine(first, Rng::new(rng.next_u64()))?,
+        create_engine(second, Rng::new(rng.next_u64()))?,
+    ];
+    let names = [engines[0].name(), engines[1].name()];
+    let [a, b] = &mut engines;
+    let stats = run_match(a.as_mut(), b.as_mut(), &match_options, |n, g| {
+        let info = g.info();
+        println!(
+            "game {}: {} (black) vs {} (white), {} in {} steps",
+            n,
+            info.black,
+            info.white,
+            info.result,
+            g.step_count()
+        );
+    })?;
+    for (i, name) in names.iter().enumerate() {
+        println!("{}: {} wins, {}%", name, stats.wins[i], stats.win_rate(i));
+    }
+    println!(
+        "black wins {}, white wins {}, draws {}",
+        stats.black_wins, stats.white_wins, stats.draws
+    );
+    Ok(())
+}
+
+// steps of the main line are analyzed and the game with the analysis is
+// saved to output
+fn review_file(filename: &str, spec: &str, output: Option<&String>) -> Result<(), String> {
+    let mut g = Game::load(filename.to_string())?;
+    let mut engine = create_engine(spec, Rng::from_time())?;
+    let review = review_game(&mut g, engine.as_mut(), |n, count| {
+        print!("\ranalyzing {}/{}", n, count);
+        let _ = io::stdout().flush();
+    })?;
+    println!();
+    let output = match output {
+        Some(output) => output.clone(),
+        None => {
+            let stem = filename.strip_suffix(".sgf").unwrap_or(filename);
+            format!("{}-review.sgf", stem)
+        }
+    };
+    g.dump_to(&output)?;
+    println!("saved to {}", output);
+    for player in [Player::Black, Player::White] {
+        let s = review.summary(&player);
+        println!(
+            "{}: {} steps, {} best, average loss {:.1}%, {} doubtful, {} bad",
+            player,
+            s.steps,
+            s.best,
+            s.average_loss * 100.0,
+            s.doubtful,
+            s.bad
+        );
+    }
+    let mistakes = review.mistakes(5);
+    if !mistakes.is_empty() {
+        println!("biggest mistakes:");
+    }
+    let size = g.board().lines();
+    let point = |step: &Cmd| match step {
+        Cmd::Step(p) => Notation::Sgf
+            .parse(p, size)
+            .and_then(|(x, y)| Notation::Gtp.format(x, y, size))
+            .unwrap_or_else(|_| p.clone()),
+        other => other.to_string(),
+    };
+    for m in mistakes {
+        println!(
+            "\tstep {} {} {}: -{:.1}%, best {}",
+            m.number,
+            m.player,
+            point(&m.step),
+            m.loss() * 100.0,
+            point(&m.analysis.best)
+        );
+    }
+    Ok(())
+}
+
+fn show_round(t: &Tournament) {
+    let players = t.players();
+    let n = t.played().len();
+    if n == 0 {
+        println!("no round is paired yet");
+        return;
+    }
+    println!("round {}", n);
+    for (i, g) in t.played()[n - 1].games.iter().enumerate() {
+        let (black, white) = (&players[g.black], &players[g.white]);
+        print!(
+            "{}: {} {} (black) vs {} {} (white)",
+            i + 1,
+            black.name,
+            black.rank,
+            white.name,
+            white.rank
+        );
+        if g.handicap > 0 {
+            print!(", handicap {}", g.handicap);
+        }
+        print!(", komi {}", g.komi);
+        if let Some(result) = &g.result {
+            print!(", {}", result);
+        }
+        println!();
+    }
+    if let Some(p) = t.played()[n - 1].bye {
+        println!("bye: {}", players[p].name);
+    }
+}
+
+// commands on the tournament file, see show_usage
+fn tournament(filename: &str, command: &str, options: &[String]) -> Result<(), String> {
+    if command == "new" {
+        let system: System = options
+            .first()
+            .ok_or_else(|| String::from("no system"))?
+            .parse()?;
+        let name = Path::new(filename)
+            .file_stem()
+            .map_or(String::new(), |s| s.to_string_lossy().to_string());
+        let mut t = Tournament::new(&name, system, 5);
+        let mut options = options[1..].iter();
+        while let Some(option) = options.next() {
+            match option.as_str() {
+                "small" => t.size = BoardSize::Small,
+                "medium" => t.size = BoardSize::Medium,
+                "new" => t.size = BoardSize::Normal,
+                "handicap" => t.handicap = true,
+                "bar" => {
+                    let bar = options.next().ok_or_else(|| String::from("no bar"))?;
+                    t.bar = Some(bar.parse()?)
+                }
+                other if other.parse::<usize>().is_ok() => t.rounds = other.parse().unwrap(),
+                other => {
+                    t.komi = other
+                        .parse()
+                        .map_err(|_| format!("invalid option: {}", other))?
+                }
+            }
+        }
+        return t.save(filename);
+    }
+    let mut t = Tournament::load(filename)?;
+    match command {
+        "add" if options.len() >= 2 => {
+            t.add_player(&options[1..].join(" "), options[0].parse()?)?;
+            println!("{} players", t.players().len());
+        }
+        "pair" => {
+            t.pair_round()?;
+            show_round(&t);
+        }
+        "result" if options.len() == 2 => {
+            let round = t.played().len();
+            let board: usize = options[0]
+                .parse()
+                .map_err(|_| format!("invalid board: {}", options[0]))?;
+            let g = t.new_game(round, board)?;
+            let info = g.info();
+            t.record(round, &info.black, &info.white, &options[1])?;
+            show_round(&t);
+        }
+        "record" => {
+            for file in options {
+                let g = Game::load(file.to_string())?;
+                show_warnings(file, &g);
+                t.record_game(&g)
+                    .map_err(|err| format!("{}: {}", file, err))?;
+            }
+            show_round(&t);
+        }
+        "game" if options.len() == 2 => {
+            let board: usize = options[0]
+                .parse()
+                .map_err(|_| format!("invalid board: {}", options[0]))?;
+            let g = t.new_game(t.played().len(), board)?;
+            g.dump_to(&options[1])?;
+            println!("saved to {}", options[1]);
+        }
+        "standings" => {
+            println!("place\tscore\tsos\tsodos\tname");
+            for (i, s) in t.standings().iter().enumerate() {
+                let p = &t.players()[s.player];
+                println!(
+                    "{}\t{}\t{}\t{}\t{} {}",
+                    i + 1,
+                    s.score,
+                    s.sos,
+                    s.sodos,
+                    p.name,
+                    p.rank
+                );
+            }
+            if t.is_over() {
+                println!("the tournament is over");
+            }
+        }
+        "play" => {
+            let round = t.played().len();
+            let boards = t.played().last().map_or(0, |r| r.games.len());
+            let mut rng = Rng::from_time();
+            for board in 1..=boards {
+                if t.played()[round - 1].games[board - 1].result.is_some() {
+                    continue;
+                }
+                let mut g = t.new_game(round, board)?;
+                let (black, white) = (g.info().black.clone(), g.info().white.clone());
+                // players who are not engines play their games by hand
+                let engines = (
+                    create_engine(&black, Rng::new(rng.next_u64())),
+                    create_engine(&white, Rng::new(rng.next_u64())),
+                );
+                let (Ok(mut black), Ok(mut white)) = engines else {
+                    continue;
+                };
+                play_out(&mut g, black.as_mut(), white.as_mut())?;
+                t.record_game(&g)?;
+                println!("board {}: {}", board, g.info().result);
+                if let Some(dir) = options.first() {
+                    fs::create_dir_all(dir)
+                        .map_err(|err| format!("can not create {}: {}", dir, err))?;
+                    let filename =
+                        Path::new(dir).join(format!("round-{}-board-{}.sgf", round, board));
+                    g.dump_to(&filename.to_string_lossy())?;
+                }
+            }
+            show_round(&t);
+        }
+        "rate" if options.len() == 1 => {
+            let mut ratings = Ratings::load(&options[0])?;
+            t.rate(&mut ratings)?;
+            ratings.save(&options[0])?;
+            println!("elo\tglicko\tgames\tname");
+            for (name, r) in ratings.ranking() {
+                println!(
+                    "{:.0}\t{:.0}±{:.0}\t{}\t{}",
+                    r.elo, r.glicko, r.deviation, r.games, name
+                );
+            }
+        }
+        other => return Err(format!("invalid command: {}", other)),
+    }
+    t.save(filename)
+}
+
+// the player who just played asks, and the opponent answers at the keyboard
+fn ask_undo(g: &mut Game) -> Result<(), String> {
+    let player = g.player_of_step(g.step_count());
+    g.request_undo(player.clone())?;
+    let opponent = player.another();
     println!(
-        "\t{}: \t{}",
-        "pass", "let another player step without any stone put in."
+        "{} asks to take back the last step, {} accept? (yes/no)",
+        player, opponent
     );
-    println!("\t{}: \t{}", "undo", "get back stone just put in.");
-    println!("\t{}: \t{}", "redo", "redo the undo step.");
+    let mut buffer = String::new();
+    io::stdin()
+        .read_line(&mut buffer)
+        .map_err(|err| err.to_string())?;
+    if buffer.trim() == "yes" {
+        g.accept_undo(opponent)
+    } else {
+        println!("{} rejected", opponent);
+        g.reject_undo(opponent)
+    }
+}
+
+// print the board on every event until the game is over
+fn watch(target: &str) -> Result<(), String> {
+    let target = target.trim_start_matches("ws://");
+    let (addr, path) = match target.find('/') {
+        Some(i) => target.split_at(i),
+        None => (target, "/"),
+    };
+    let mut watcher = Watcher::connect(addr, path)?;
+    while let Some(event) = watcher.next_event()? {
+        match event {
+            GameEvent::Start { black, white, .. } => {
+                println!("{} (black) vs {} (white)", black, white)
+            }
+            GameEvent::Next(player, step) => println!("{} plays {}", player, step),
+            GameEvent::Undo(count) => println!("{} steps are taken back", count),
+            GameEvent::Result(result) => println!("result {}", result),
+        }
+        print!("{}", watcher.game());
+    }
+    println!("the stream is over");
+    Ok(())
+}
+
+enum Input {
+    Net(Option<Result<Message, String>>),
+    User(Option<String>),
+}
+
+// play until the game is over or the connection is closed
+fn play_online(mut client: Client, messages: Messages) {
+    let (tx, rx) = mpsc::channel();
+    let net = tx.clone();
+    thread::spawn(move || {
+        for message in messages {
+            if net.send(Input::Net(Some(message))).is_err() {
+                return;
+            }
+        }
+        let _ = net.send(Input::Net(None));
+    });
+    thread::spawn(move || loop {
+        let mut buffer = String::new();
+        let line = match io::stdin().read_line(&mut buffer) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(buffer.trim().to_string()),
+        };
+        let end = line.is_none();
+        if tx.send(Input::User(line)).is_err() || end {
+            return;
+        }
+    });
+
     println!(
-        "\t{}: \t{}",
-        "**", "like aa, bc, etc., put the stone on that point."
+        "you are {}, use reconnect <host:port> {} to come back if disconnected",
+        client.player(),
+        client.token()
     );
+    println!("input a point, pass, resign, undo, accept, reject, result <result> or exit");
+    print!("{}", client.game());
+    for input in rx {
+        let message = match input {
+            Input::User(None) => return,
+            Input::User(Some(line)) => match line.as_str() {
+                "exit" => return,
+                "" => continue,
+                "pass" => Message::Play(Cmd::Pass),
+                "resign" => Message::Resign,
+                "undo" => Message::Undo,
+                "accept" => Message::Accept,
+                "reject" => Message::Reject,
+                other if other.starts_with("result ") => {
+                    Message::Result(other["result ".len()..].trim().to_string())
+                }
+                other => match client.game().sgf_point(other) {
+                    Ok(p) => Message::Play(Cmd::Step(p)),
+                    Err(err) => {
+                        println!("invalid input: {}", err);
+                        continue;
+                    }
+                },
+            },
+            Input::Net(None) => {
+                println!("connection closed");
+                return;
+            }
+            Input::Net(Some(Err(err))) => {
+                println!("invalid message: {}", err);
+                continue;
+            }
+            Input::Net(Some(Ok(message))) => {
+                if let Err(err) = client.apply(&message) {
+                    println!("can not follow the server: {}", err);
+                    return;
+                }
+                match message {
+                    Message::Move(..) | Message::Undone(_) | Message::Sync(_) => {
+                        print!("{}", client.game())
+                    }
+                    Message::Opponent(name) => println!("playing with {}", name),
+                    Message::Left => println!("the opponent left, waiting to come back"),
+                    Message::UndoRequest => {
+                        println!("the opponent asks to undo, accept or reject?")
+                    }
+                    Message::Rejected => println!("undo is rejected"),
+                    Message::Estimate(score) => println!(
+                        "both passed, the estimate is {}, send result <result> to end",
+                        score
+                    ),
+                    Message::Proposal(result) => {
+                        println!("the opponent sends result {}", result)
+                    }
+                    Message::Error(err) => println!("{}", err),
+                    Message::Result(result) => {
+                        println!("game over: {}", result);
+                        return;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+        };
+        if let Err(err) = client.send(&message) {
+            println!("{}", err);
+            return;
+        }
+    }
+}
+
+// values of a file which are kept but not used by the game
+fn show_warnings(filename: &str, g: &Game) {
+    for warning in g.info().warnings() {
+        println!("{}: {}", filename, warning);
+    }
+}
+
+fn convert(from: &str, to: &str) {
+    let g = Game::load(from.to_string()).unwrap_or_else(|err| {
+        println!("can not load: {}", err);
+        exit(1)
+    });
+    show_warnings(from, &g);
+    g.dump_to(to).unwrap_or_else(|err| {
+        println!("{}", err);
+        exit(1)
+    });
+    println!("convert {} to {}", from, to);
+}
+
+// options are [<from>-<to>] [nocoords], return (numbers, coordinates)
+fn diagram_options(options: &[&str]) -> Result<(Option<(usize, usize)>, bool), String> {
+    let mut numbers = None;
+    let mut coordinates = true;
+    for option in options {
+        match *option {
+            "nocoords" => coordinates = false,
+            other => {
+                let invalid = || format!("invalid step range: {}", other);
+                let (from, to) = other.split_once('-').ok_or_else(invalid)?;
+                let from = from.parse().map_err(|_| invalid())?;
+                let to = to.parse().map_err(|_| invalid())?;
+                numbers = Some((from, to));
+            }
+        }
+    }
+    Ok((numbers, coordinates))
+}
+
+fn export_svg(g: &Game, filename: &str, options: &[&str]) -> Result<(), String> {
+    let (numbers, coordinates) = diagram_options(options)?;
+    fs::write(filename, g.to_svg(numbers, coordinates)).map_err(|err| err.to_string())
+}
+
+// text diagram of current position, kind is sensei or igo
+fn diagram(g: &Game, kind: &str, options: &[&str]) -> Result<String, String> {
+    let (numbers, coordinates) = diagram_options(options)?;
+    match kind {
+        "sensei" => g.to_sensei(numbers, coordinates),
+        "igo" => Ok(g.to_igo(numbers)),
+        other => Err(format!("invalid diagram: {}", other)),
+    }
+}
+
+fn search(dir: &str, conditions: &[String]) -> Result<(), String> {
+    let mut query = Query::default();
+    let mut pattern: Option<Pattern> = None;
+    let mut swap = false;
+    for pair in conditions.chunks(2) {
+        let value = pair
+            .get(1)
+            .ok_or_else(|| format!("missing value of {}", pair[0]))?
+            .clone();
+        match pair[0].as_str() {
+            "player" => query.player = Some(value),
+            "black" => query.black = Some(value),
+            "white" => query.white = Some(value),
+            "date" => query.date = Some(value),
+            "result" => query.result = Some(value),
+            "position" => query.position = Some(load_position(&value)?),
+            "pattern" => {
+                let text = fs::read_to_string(&value).map_err(|err| err.to_string())?;
+                pattern = Some(text.parse()?);
+            }
+            "swap" => swap = value == "yes",
+            other => return Err(format!("invalid condition: {}", other)),
+        }
+    }
+    let mut db = Database::open(dir)?;
+    for err in db.update()? {
+        println!("skip {}", err);
+    }
+    let (found, next_moves) = match &pattern {
+        Some(p) => {
+            let result = db.search_pattern(&query, p, swap)?;
+            (result.matches, result.next_moves)
+        }
+        None => (db.search(&query), vec![]),
+    };
+    for m in found.iter() {
+        let info = &m.game.info;
+        print!(
+            "{}\t{} vs {}\t{}\t{}",
+            m.game.path, info.black, info.white, info.date, info.result
+        );
+        if !m.steps.is_empty() {
+            let steps: Vec<String> = m.steps.iter().map(|s| s.to_string()).collect();
+            print!("\tat step {}", steps.join(", "));
+        }
+        println!();
+    }
+    println!("{} of {} games found", found.len(), db.games().len());
+    if let Some(p) = pattern {
+        show_next_moves(&p, &next_moves);
+    }
+    Ok(())
+}
+
+// the pattern with the next moves labelled by letters from a, and the counts
+fn show_next_moves(pattern: &Pattern, next_moves: &[NextMove]) {
+    if next_moves.is_empty() {
+        return;
+    }
+    let total: usize = next_moves.iter().map(|m| m.count).sum();
+    let mut text = pattern.to_string();
+    // places of cells in the text, row by row
+    let cells: Vec<usize> = text
+        .char_indices()
+        .filter(|(_, c)| ".XO?".contains(*c))
+        .map(|(pos, _)| pos)
+        .collect();
+    let mut labels: Vec<((usize, usize), char)> = vec![];
+    println!("next moves:");
+    let mut lines = vec![];
+    for m in next_moves {
+        let stone = if m.stone == Stone::Black { "B" } else { "W" };
+        let at = match m.point {
+            Some(point) => match labels.iter().find(|(p, _)| *p == point) {
+                Some((_, label)) => label.to_string(),
+                None if labels.len() < 26 => {
+                    let label = (b'a' + labels.len() as u8) as char;
+                    let pos = cells[point.0 + point.1 * pattern.width()];
+                    text.replace_range(pos..pos + 1, &label.to_string());
+                    labels.push((point, label));
+                    label.to_string()
+                }
+                None => format!("({}, {})", point.0 + 1, point.1 + 1),
+            },
+            None => String::from("elsewhere"),
+        };
+        lines.push(format!(
+            "{} {}\t{}\t{}%",
+            stone,
+            at,
+            m.count,
+            m.count * 100 / total
+        ));
+    }
+    print!("{}", text);
+    for line in lines {
+        println!("{}", line);
+    }
+}
+
+// options are [<depth>] [nosymmetry], games of the most common size are taken
+fn explore(dir: &str, options: &[String]) -> Result<(), String> {
+    let mut depth = 30;
+    let mut symmetric = true;
+    for option in options {
+        match option.as_str() {
+            "nosymmetry" => symmetric = false,
+            other => {
+                depth = other
+                    .parse()
+                    .map_err(|_| format!("invalid option: {}", other))?
+            }
+        }
+    }
+    let mut db = Database::open(dir)?;
+    for err in db.update()? {
+        println!("skip {}", err);
+    }
+    let size = [BoardSize::Normal, BoardSize::Medium, BoardSize::Small]
+        .into_iter()
+        .max_by_key(|size| db.games().iter().filter(|g| g.size == *size).count())
+        .unwrap();
+    let mut book = Book::from_database(&db, size, depth, symmetric)?;
+    loop {
+        let next = book.continuations();
+        let size = book.game().board().lines();
+        let mut markup = vec![];
+        for (i, (step, _)) in next.iter().enumerate().take(26) {
+            if let Cmd::Step(p) = step {
+                let (x, y) = Notation::Sgf.parse(p, size)?;
+                let label = ((b'a' + i as u8) as char).to_string();
+                markup.push(Markup {
+                    mark: Mark::Label(label),
+                    x,
+                    y,
+                });
+            }
+        }
+        print!("{}", book.game().board().with_markup(&markup));
+        println!("{}", book.stats());
+        for (i, (step, stats)) in next.iter().enumerate() {
+            let label = if i < 26 {
+                (b'a' + i as u8) as char
+            } else {
+                ' '
+            };
+            println!("{} {}\t{}", label, step, stats);
+        }
+        println!();
+        println!("label or step to play, back, sgf <path> to save the book, exit:");
+        let mut buffer = String::new();
+        if io::stdin().read_line(&mut buffer).unwrap() == 0 {
+            return Ok(());
+        }
+        let result = match buffer.trim() {
+            "exit" => return Ok(()),
+            "back" => book.back(),
+            other if other.starts_with("sgf ") => book
+                .to_game()
+                .and_then(|g| g.dump_to(other[4..].trim()))
+                .map(|_| println!("save to file: {}", other[4..].trim())),
+            "pass" => book.play(&Cmd::Pass),
+            other if other.len() == 1 => {
+                let i = (other.as_bytes()[0] as usize).wrapping_sub(b'a' as usize);
+                match next.get(i) {
+                    Some((step, _)) => book.play(step),
+                    None => Err(format!("invalid label: {}", other)),
+                }
+            }
+            other => Notation::Sgf
+                .parse(other, size)
+                .and_then(|(x, y)| book.play(&Cmd::Step(Cmd::point_to_cmd(x, y)))),
+        };
+        if let Err(err) = result {
+            println!("{}", err);
+        }
+    }
+}
+
+// options are [montecarlo [<playouts>]], bouzy's estimation by default
+fn estimate(g: &Game, options: &[&str]) -> Result<(), String> {
+    let ownership = match options {
+        [] => bouzy(g.board()),
+        ["montecarlo", rest @ ..] => {
+            let playouts = match rest.first() {
+                Some(n) => n.parse().map_err(|_| format!("invalid playouts: {}", n))?,
+                None => 100,
+            };
+            monte_carlo(
+                g.board(),
+                g.next_player().stone(),
+                playouts,
+                &mut Rng::from_time(),
+            )
+        }
+        other => return Err(format!("invalid options: {}", other.join(" "))),
+    };
+    let markup = ownership.markup(g.board());
+    print!(
+        "{}",
+        g.board()
+            .with_markup(&markup)
+            .with_notation(g.notation())
+            .with_theme(g.style().theme)
+    );
+    let komi = g.info().komi.unwrap_or_default();
+    println!(
+        "estimated score: {} (komi {})",
+        format_score(ownership.score(komi)),
+        komi
+    );
+    Ok(())
+}
+
+// position of a sensei's library diagram, or the current one of a game file
+fn load_position(filename: &str) -> Result<Board, String> {
+    let text = fs::read_to_string(filename).map_err(|err| err.to_string())?;
+    if text.trim_start().starts_with("$$") {
+        return parse_sensei(&text).map(|(board, _)| board);
+    }
+    Game::load_from(&mut text.as_bytes()).map(|g| g.board().clone())
+}
+
+fn show_sensei_diagram(filename: &str) {
+    let text = fs::read_to_string(filename).unwrap_or_else(|err| {
+        println!("can not read: {}", err);
+        exit(1)
+    });
+    let (board, markup) = parse_sensei(&text).unwrap_or_else(|err| {
+        println!("invalid diagram: {}", err);
+        exit(1)
+    });
+    print!("{}", board.with_markup(&markup));
+}
+
+// options are [<delay-ms>] [<pixels>]
+fn export_replay(g: &Game, filename: &str, options: &[&str]) -> Result<(), String> {
+    let defaults = ReplayOptions::default();
+    let delay = match options.first() {
+        Some(delay) => delay
+            .parse()
+            .map_err(|_| format!("invalid delay: {}", delay))?,
+        None => defaults.delay,
+    };
+    let pixels = match options.get(1) {
+        Some(pixels) => pixels
+            .parse()
+            .map_err(|_| format!("invalid pixels: {}", pixels))?,
+        None => defaults.pixels,
+    };
+    g.export_replay(filename, delay, pixels)
+}
+
+fn confirm(question: &str) -> bool {
+    println!("{} (y/n)", question);
+    let mut buffer = String::new();
+    io::stdin().read_line(&mut buffer).unwrap();
+    buffer.trim() == "y"
+}
+
+fn dump(g: &Game, filename: &str) {
+    if Path::new(filename).exists() && !confirm(&format!("{} exists, overwrite?", filename)) {
+        return;
+    }
+    match g.dump_to(filename) {
+        Ok(()) => println!("dump to file: {}", filename),
+        Err(err) => println!("can not dump: {}", err),
+    }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    // tui takes the same options
+    let tui = args.len() > 1 && args[1] == "tui";
+    if tui {
+        args.remove(1);
+    }
 
     let mut g = if args.len() < 2 {
         show_usage();
@@ -59,7 +1044,187 @@ fn main() {
                     panic!("invalid args");
                 }
                 let filename = &args[2];
-                Game::load(filename.to_string()).unwrap()
+                let g = Game::load(filename.to_string()).unwrap();
+                show_warnings(filename, &g);
+                g
+            }
+            "convert" => {
+                if args.len() < 4 {
+                    show_usage();
+                    exit(1)
+                }
+                convert(&args[2], &args[3]);
+                exit(0)
+            }
+            "export-svg" => {
+                if args.len() < 4 {
+                    show_usage();
+                    exit(1)
+                }
+                let g = Game::load(args[2].to_string()).unwrap_or_else(|err| {
+                    println!("can not load: {}", err);
+                    exit(1)
+                });
+                let options: Vec<&str> = args[4..].iter().map(|a| a.as_str()).collect();
+                if let Err(err) = export_svg(&g, &args[3], &options) {
+                    println!("can not export: {}", err);
+                    exit(1)
+                }
+                println!("export {} to {}", args[2], args[3]);
+                exit(0)
+            }
+            "export-replay" => {
+                if args.len() < 4 {
+                    show_usage();
+                    exit(1)
+                }
+                let g = Game::load(args[2].to_string()).unwrap_or_else(|err| {
+                    println!("can not load: {}", err);
+                    exit(1)
+                });
+                let options: Vec<&str> = args[4..].iter().map(|a| a.as_str()).collect();
+                if let Err(err) = export_replay(&g, &args[3], &options) {
+                    println!("can not export: {}", err);
+                    exit(1)
+                }
+                println!("export {} to {}", args[2], args[3]);
+                exit(0)
+            }
+            "search" => {
+                if args.len() < 3 {
+                    show_usage();
+                    exit(1)
+                }
+                if let Err(err) = search(&args[2], &args[3..]) {
+                    println!("can not search: {}", err);
+                    exit(1)
+                }
+                exit(0)
+            }
+            "explore" => {
+                if args.len() < 3 {
+                    show_usage();
+                    exit(1)
+                }
+                if let Err(err) = explore(&args[2], &args[3..]) {
+                    println!("can not explore: {}", err);
+                    exit(1)
+                }
+                exit(0)
+            }
+            "serve" => {
+                if let Err(err) = serve(&args[2..]) {
+                    println!("can not serve: {}", err);
+                    exit(1)
+                }
+                exit(0)
+            }
+            "serve-http" => {
+                let port = args.get(2).map_or("8080", |p| p.as_str());
+                let served = ApiServer::bind(
+                    &format!("0.0.0.0:{}", port),
+                    args.get(3).map(|d| d.as_str()),
+                )
+                .and_then(|server| {
+                    println!("serving games on http://localhost:{}/games", port);
+                    server.run()
+                });
+                if let Err(err) = served {
+                    println!("can not serve: {}", err);
+                    exit(1)
+                }
+                exit(0)
+            }
+            "watch" => {
+                if args.len() < 3 {
+                    show_usage();
+                    exit(1)
+                }
+                if let Err(err) = watch(&args[2]) {
+                    println!("can not watch: {}", err);
+                    exit(1)
+                }
+                exit(0)
+            }
+            "match" => {
+                if args.len() < 4 {
+                    show_usage();
+                    exit(1)
+                }
+                if let Err(err) = play_match(&args[2], &args[3], &args[4..]) {
+                    println!("can not play the match: {}", err);
+                    exit(1)
+                }
+                exit(0)
+            }
+            "review" => {
+                if args.len() < 3 {
+                    show_usage();
+                    exit(1)
+                }
+                let spec = args.get(3).map_or("mcts", |s| s.as_str());
+                if let Err(err) = review_file(&args[2], spec, args.get(4)) {
+                    println!("can not review: {}", err);
+                    exit(1)
+                }
+                exit(0)
+            }
+            "selfplay" => {
+                if args.len() < 3 {
+                    show_usage();
+                    exit(1)
+                }
+                if let Err(err) = self_play(&args[2], &args[3..]) {
+                    println!("self-play failed: {}", err);
+                    exit(1)
+                }
+                exit(0)
+            }
+            "tournament" => {
+                if args.len() < 4 {
+                    show_usage();
+                    exit(1)
+                }
+                if let Err(err) = tournament(&args[2], &args[3], &args[4..]) {
+                    println!("tournament failed: {}", err);
+                    exit(1)
+                }
+                exit(0)
+            }
+            "connect" | "reconnect" => {
+                if args.len() < 3 || (arg == "reconnect" && args.len() < 4) {
+                    show_usage();
+                    exit(1)
+                }
+                let joined = if arg == "reconnect" {
+                    Client::resume(&args[2], &args[3])
+                } else {
+                    let color = args.get(3).and_then(|c| c.parse().ok());
+                    let name = args[3..]
+                        .iter()
+                        .filter(|a| a.parse::<Player>().is_err())
+                        .cloned()
+                        .collect::<Vec<String>>()
+                        .join(" ");
+                    let name = if name.is_empty() { "guest" } else { &name };
+                    Client::join(&args[2], color, name)
+                };
+                match joined {
+                    Ok((client, messages)) => play_online(client, messages),
+                    Err(err) => {
+                        println!("can not connect: {}", err);
+                        exit(1)
+                    }
+                }
+                exit(0)
+            }
+            "diagram" => {
+                if args.len() < 3 {
+                    show_usage();
+                    exit(1)
+                }
+                show_sensei_diagram(&args[2]);
+                exit(0)
             }
             "new" => Game::new(BoardSize::Normal),
             "medium" => Game::new(BoardSize::Medium),
@@ -72,38 +1237,193 @@ fn main() {
         }
     };
 
+    if Path::new(RECOVERY_FILE).exists()
+        && confirm(&format!(
+            "found unfinished game in {}, resume?",
+            RECOVERY_FILE
+        ))
+    {
+        g = Game::load(RECOVERY_FILE.to_string()).unwrap_or_else(|err| {
+            println!("can not resume: {}", err);
+            exit(1)
+        });
+    }
+    g.set_autosave(Some(RECOVERY_FILE.to_string()));
+
+    if tui {
+        rustgo::tui::run(&mut g).unwrap_or_else(|err| {
+            println!("tui failed: {}", err);
+            exit(1)
+        });
+        g.set_autosave(None);
+        let _ = fs::remove_file(RECOVERY_FILE);
+        exit(0)
+    }
+
     print!("{}", g);
 
+    // told once until it is written again
+    let mut autosave_error: Option<String> = None;
     loop {
+        let error = g.last_autosave_error().map(String::from);
+        if let Some(err) = error.as_ref().filter(|_| error != autosave_error) {
+            println!("autosave failed: {}", err);
+        }
+        autosave_error = error;
         println!();
+        let range = g.notation().range(g.board_size().lines());
         match g.next_player() {
-            Player::Black => println!("Black (aa-ss or pass):"),
-            Player::White => println!("White (aa-ss or pass):"),
+            Player::Black => println!("Black ({} or pass):", range),
+            Player::White => println!("White ({} or pass):", range),
         };
         let mut buffer = String::new();
-        io::stdin().read_line(&mut buffer).unwrap();
+        // input is closed, keep the recovery file to resume later
+        if io::stdin().read_line(&mut buffer).unwrap() == 0 {
+            exit(0)
+        }
         match buffer.trim() {
             "help" => {
                 show_operator_usage();
                 continue;
             }
-            "exit" => exit(0),
-            "dump" => {
-                g.dump();
+            "exit" => {
+                g.set_autosave(None);
+                let _ = fs::remove_file(RECOVERY_FILE);
+                exit(0)
+            }
+            "dump" | "sgf" => {
+                let ext = if buffer.trim() == "sgf" { "sgf" } else { "txt" };
+                match g.dump(ext) {
+                    Ok(filename) => println!("dump to file: {}", filename),
+                    Err(err) => println!("can not dump: {}", err),
+                }
+                continue;
+            }
+            other if other.starts_with("dump ") => {
+                dump(&g, other[5..].trim());
+                continue;
+            }
+            other if other.starts_with("export-svg ") => {
+                let args: Vec<&str> = other[11..].split_whitespace().collect();
+                match export_svg(&g, args[0], &args[1..]) {
+                    Ok(()) => println!("export to file: {}", args[0]),
+                    Err(err) => println!("can not export: {}", err),
+                }
+                continue;
+            }
+            other if other.starts_with("export-replay ") => {
+                let args: Vec<&str> = other[14..].split_whitespace().collect();
+                match export_replay(&g, args[0], &args[1..]) {
+                    Ok(()) => println!("export to file: {}", args[0]),
+                    Err(err) => println!("can not export: {}", err),
+                }
+                continue;
+            }
+            other if other.starts_with("diagram ") => {
+                let args: Vec<&str> = other[8..].split_whitespace().collect();
+                match diagram(&g, args[0], &args[1..]) {
+                    Ok(text) => print!("{}", text),
+                    Err(err) => println!("can not make diagram: {}", err),
+                }
+                continue;
+            }
+            other if other.starts_with("transform ") => {
+                match other[10..]
+                    .trim()
+                    .parse()
+                    .and_then(|symmetry| g.transform(symmetry))
+                {
+                    Ok(mut t) => {
+                        t.set_autosave(g.autosave_file().map(String::from));
+                        g = t;
+                    }
+                    Err(err) => println!("can not transform: {}", err),
+                }
+            }
+            "estimate" => {
+                if let Err(err) = estimate(&g, &[]) {
+                    println!("can not estimate: {}", err);
+                }
+                continue;
+            }
+            other if other.starts_with("estimate ") => {
+                let options: Vec<&str> = other[9..].split_whitespace().collect();
+                if let Err(err) = estimate(&g, &options) {
+                    println!("can not estimate: {}", err);
+                }
+                continue;
+            }
+            other if other.starts_with("notation ") => match other[9..].trim().parse() {
+                Ok(notation) => g.set_notation(notation),
+                Err(err) => println!("{}", err),
+            },
+            other if other.starts_with("theme ") => match other[6..].trim().parse() {
+                Ok(theme) => g.set_style(Style {
+                    theme,
+                    ..g.style().clone()
+                }),
+                Err(err) => println!("{}", err),
+            },
+            "lastmove on" | "lastmove off" => g.set_style(Style {
+                last_step: buffer.trim() == "lastmove on",
+                ..g.style().clone()
+            }),
+            other if other.starts_with("numbers ") => match other[8..].trim() {
+                "off" => g.set_style(Style {
+                    numbers_from: None,
+                    ..g.style().clone()
+                }),
+                from => match from.parse() {
+                    Ok(from) => g.set_style(Style {
+                        numbers_from: Some(from),
+                        ..g.style().clone()
+                    }),
+                    Err(_) => println!("invalid step number: {}", from),
+                },
+            },
+            "autosave on" => {
+                g.set_autosave(Some(RECOVERY_FILE.to_string()));
+                continue;
+            }
+            "autosave off" => {
+                g.set_autosave(None);
+                let _ = fs::remove_file(RECOVERY_FILE);
                 continue;
             }
+            "info" => {
+                for (key, value) in g.info().fields() {
+                    println!("{}: {}", key, value);
+                }
+                continue;
+            }
+            other if other.starts_with("info ") => {
+                let (key, value) = other[5..].split_once(' ').unwrap_or((&other[5..], ""));
+                g.set_info(key, value).unwrap_or_else(|err| {
+                    println!("can not set info: {}", err);
+                });
+                continue;
+            }
+            "undo" if g.undo_rule() != UndoRule::Free => {
+                if let Err(err) = ask_undo(&mut g) {
+                    println!("can not undo: {}", err);
+                }
+            }
             "undo" => g.undo().unwrap_or_else(|err| {
                 println!("can not undo: {}", err);
             }),
+            other if other.starts_with("undo-rule ") => match other[10..].trim().parse() {
+                Ok(rule) => g.set_undo_rule(rule),
+                Err(err) => println!("{}", err),
+            },
             "pass" => g.next(Cmd::Pass).unwrap(),
             "redo" => {
                 let redo_list = g.redo_list();
-                if redo_list.len() == 0 {
-                    println!("can not redo {}", "no redo steps");
+                if redo_list.is_empty() {
+                    println!("can not redo no redo steps");
                 } else {
                     let mut steps = String::from("");
-                    for i in 0..redo_list.len() {
-                        let cmd = match &redo_list[i] {
+                    for (i, cmd) in redo_list.iter().enumerate() {
+                        let cmd = match cmd {
                             Cmd::Pass => String::from("pass"),
                             Cmd::Step(s) => s.clone(),
                             Cmd::Start => String::from("start"),
@@ -123,13 +1443,13 @@ fn main() {
                     }
                 }
             }
+            other if review(&mut g, other) => {}
             other => {
-                if other.len() != 2 {
-                    println!("invalid input: {:?}", other.as_bytes());
-                }
-                g.next(Cmd::Step(other.to_string())).unwrap_or_else(|err| {
-                    println!("invalid input with err: {:?}", err);
-                });
+                let step = g.sgf_point(other);
+                step.and_then(|p| g.next(Cmd::Step(p)))
+                    .unwrap_or_else(|err| {
+                        println!("invalid input with err: {:?}", err);
+                    });
             }
         };
         print!("{}", g);
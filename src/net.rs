@@ -0,0 +1,15 @@
+mod api;
+mod client;
+mod http;
+mod protocol;
+mod server;
+mod spectate;
+mod websocket;
+
+pub use api::*;
+pub use client::*;
+pub use http::*;
+pub use protocol::*;
+pub use server::*;
+pub use spectate::*;
+pub use websocket::*;
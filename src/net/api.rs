@@ -0,0 +1,472 @@
+use crate::basic::*;
+use crate::net::{GameEvent, Request, Response, Spectators, WebSocket};
+use crate::util::Json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+
+// http api of games, all bodies are json and points are in sgf notation:
+//
+//     GET    /games                 list games
+//     POST   /games                 create, {"size", "rules", "komi", "handicap",
+//                                   "black", "white"} are all optional
+//     GET    /games/<id>            the game with board
+//     DELETE /games/<id>
+//     GET    /games/<id>/board      board only
+//     POST   /games/<id>/moves      play {"move": "dd"} or {"move": "pass"}
+//     POST   /games/<id>/undo
+//     GET    /games/<id>/legal      points the next player can play
+//     GET    /games/<id>/sgf        download as sgf
+//     GET    /games/<id>/events     websocket of the game events, see GameEvent
+//
+// games are kept in memory, and also in <dir>/<id>.sgf if dir is given.
+pub struct ApiServer {
+    listener: TcpListener,
+    dir: Option<String>,
+}
+
+impl ApiServer {
+    pub fn bind(addr: &str, dir: Option<&str>) -> Result<ApiServer, String> {
+        let listener =
+            TcpListener::bind(addr).map_err(|err| format!("can not listen {}: {}", addr, err))?;
+        if let Some(dir) = dir {
+            fs::create_dir_all(dir).map_err(|err| format!("can not create {}: {}", dir, err))?;
+        }
+        Ok(ApiServer {
+            listener,
+            dir: dir.map(String::from),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|err| err.to_string())
+    }
+
+    // serve forever, requests are read by a thread for each connection and
+    // handled one by one here, where the games are
+    pub fn run(self) -> Result<(), String> {
+        let mut games = Games::load(self.dir)?;
+        let (tx, rx) = channel();
+        let listener = self.listener;
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                thread::spawn(move || serve_connection(stream, tx));
+            }
+        });
+        for call in rx {
+            match call {
+                Call::Http(request, reply) => {
+                    let _ = reply.send(games.handle(&request));
+                }
+                Call::Watch(request, stream) => games.watch(&request, stream),
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Call {
+    Http(Request, Sender<Response>),
+    // websocket upgrade, the stream is kept by the spectators
+    Watch(Request, TcpStream),
+}
+
+fn serve_connection(mut stream: TcpStream, tx: Sender<Call>) {
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let response = match Request::read(&mut BufReader::new(reader)) {
+        Ok(request) if request.header("upgrade").is_some() => {
+            let _ = tx.send(Call::Watch(request, stream));
+            return;
+        }
+        Ok(request) => {
+            let (reply, response) = channel();
+            if tx.send(Call::Http(request, reply)).is_err() {
+                return;
+            }
+            match response.recv() {
+                Ok(response) => response,
+                Err(_) => return,
+            }
+        }
+        Err(err) => Response::unreadable(&err),
+    };
+    let _ = response.write_to(&mut stream);
+}
+
+struct Games {
+    games: BTreeMap<usize, Game>,
+    next_id: usize,
+    dir: Option<String>,
+    spectators: HashMap<usize, Spectators>,
+}
+
+type Handled = Result<Response, (u16, String)>;
+
+impl Games {
+    // games saved in dir before, named by their ids
+    fn load(dir: Option<String>) -> Result<Games, String> {
+        let mut games = Games {
+            games: BTreeMap::new(),
+            next_id: 1,
+            dir,
+            spectators: HashMap::new(),
+        };
+        let Some(dir) = &games.dir else {
+            return Ok(games);
+        };
+        let entries = fs::read_dir(dir).map_err(|err| format!("can not read {}: {}", dir, err))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let id = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".sgf"))
+                .and_then(|id| id.parse::<usize>().ok());
+            if let Some(id) = id {
+                let g = Game::load(path.to_string_lossy().to_string())?;
+                games.games.insert(id, g);
+                games.next_id = games.next_id.max(id + 1);
+            }
+        }
+        Ok(games)
+    }
+
+    fn handle(&mut self, request: &Request) -> Response {
+        if request.method == "OPTIONS" {
+            return Response::new(204, "text/plain", vec![])
+                .with_header("Access-Control-Allow-Origin", "*")
+                .with_header("Access-Control-Allow-Methods", "GET, POST, DELETE, OPTIONS")
+                .with_header("Access-Control-Allow-Headers", "Content-Type");
+        }
+        let parts: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        let handled = match (request.method.as_str(), &parts[..]) {
+            ("GET", ["games"]) => Ok(self.list()),
+            ("POST", ["games"]) => self.create(request),
+            ("GET", ["games", id]) => self
+                .game(id)
+                .map(|(id, g)| Response::json(200, &game_json(id, g))),
+            ("DELETE", ["games", id]) => self.delete(id),
+            ("GET", ["games", id, "board"]) => self
+                .game(id)
+                .map(|(_, g)| Response::json(200, &board_json(g))),
+            ("POST", ["games", id, "moves"]) => self.play(id, request),
+            ("POST", ["games", id, "undo"]) => self.undo(id),
+            ("GET", ["games", id, "legal"]) => self.game(id).map(|(_, g)| {
+                let moves: Vec<Json> = g
+                    .legal_moves()
+                    .iter()
+                    .map(|(x, y)| Cmd::point_to_cmd(*x, *y).into())
+                    .collect();
+                Response::json(200, &Json::object([("moves", moves.into())]))
+            }),
+            ("GET", ["games", id, "sgf"]) => self.game(id).map(|(id, g)| {
+                Response::new(200, "application/x-go-sgf", g.to_sgf().into_bytes()).with_header(
+                    "Content-Disposition",
+                    &format!("attachment; filename=\"{}.sgf\"", id),
+                )
+            }),
+            (_, ["games", ..]) => Err((405, format!("{} is not allowed", request.method))),
+            _ => Err((404, format!("not found: {}", request.path))),
+        };
+        handled
+            .unwrap_or_else(|(status, err)| Response::error(status, &err))
+            .with_header("Access-Control-Allow-Origin", "*")
+    }
+
+    fn watch(&mut self, request: &Request, mut stream: TcpStream) {
+        let parts: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        let found = match (request.method.as_str(), &parts[..]) {
+            ("GET", ["games", id, "events"]) => self.game(id).map(|(id, _)| id),
+            _ => Err((404, format!("not found: {}", request.path))),
+        };
+        match found {
+            Ok(id) => {
+                if let Ok(socket) = WebSocket::accept(stream, request) {
+                    let spectators = self.spectators.entry(id).or_default();
+                    spectators.join(socket, &self.games[&id]);
+                }
+            }
+            Err((status, err)) => {
+                let _ = Response::error(status, &err).write_to(&mut stream);
+            }
+        }
+    }
+
+    fn broadcast(&mut self, id: usize, event: &GameEvent) {
+        if let Some(spectators) = self.spectators.get_mut(&id) {
+            spectators.broadcast(event);
+        }
+    }
+
+    fn game(&self, id: &str) -> Result<(usize, &Game), (u16, String)> {
+        id.parse::<usize>()
+            .ok()
+            .and_then(|id| self.games.get(&id).map(|g| (id, g)))
+            .ok_or_else(|| (404, format!("no game {}", id)))
+    }
+
+    fn game_mut(&mut self, id: &str) -> Result<(usize, &mut Game), (u16, String)> {
+        id.parse::<usize>()
+            .ok()
+            .and_then(|id| self.games.get_mut(&id).map(|g| (id, g)))
+            .ok_or_else(|| (404, format!("no game {}", id)))
+    }
+
+    fn list(&self) -> Response {
+        let games: Vec<Json> = self
+            .games
+            .iter()
+            .map(|(id, g)| {
+                Json::object([
+                    ("id", (*id).into()),
+                    ("size", g.board().lines().into()),
+                    ("black", g.info().black.clone().into()),
+                    ("white", g.info().white.clone().into()),
+                    ("steps", g.step_count().into()),
+                    ("result", g.info().result.clone().into()),
+                ])
+            })
+            .collect();
+        Response::json(200, &Json::object([("games", games.into())]))
+    }
+
+    fn create(&mut self, request: &Request) -> Handled {
+        let bad = |err: String| (400, err);
+        let body = request.json().map_err(bad)?;
+        let size = match body.get("size").map(|s| s.as_usize()) {
+            None | Some(Some(19)) => BoardSize::Normal,
+            Some(Some(13)) => BoardSize::Medium,
+            Some(Some(9)) => BoardSize::Small,
+            _ => return Err(bad(String::from("size should be 9, 13 or 19"))),
+        };
+        let mut g = Game::new(size);
+        let handicap = match body.get("handicap") {
+            Some(h) => h
+                .as_usize()
+                .ok_or_else(|| bad(String::from("invalid handicap")))?,
+            None => 0,
+        };
+        if handicap > 0 {
+            g.set_handicap(handicap).map_err(bad)?;
+        }
+        let komi = match body.get("komi") {
+            Some(k) => k
+                .as_f64()
+                .ok_or_else(|| bad(String::from("invalid komi")))?,
+            None if handicap > 0 => 0.5,
+            None => 6.5,
+        };
+        g.set_info("komi", &komi.to_string()).map_err(bad)?;
+        for key in ["rules", "black", "white"] {
+            if let Some(value) = body.get(key) {
+                let value = value
+                    .as_str()
+                    .ok_or_else(|| bad(format!("{} should be a string", key)))?;
+                g.set_info(key, value).map_err(bad)?;
+            }
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.games.insert(id, g);
+        self.save(id)?;
+        Ok(Response::json(201, &game_json(id, &self.games[&id])))
+    }
+
+    fn delete(&mut self, id: &str) -> Handled {
+        let (id, _) = self.game(id)?;
+        self.games.remove(&id);
+        if let Some(mut spectators) = self.spectators.remove(&id) {
+            spectators.close();
+        }
+        if let Some(dir) = &self.dir {
+            let _ = fs::remove_file(Path::new(dir).join(format!("{}.sgf", id)));
+        }
+        Ok(Response::new(204, "text/plain", vec![]))
+    }
+
+    fn play(&mut self, id: &str, request: &Request) -> Handled {
+        let body = request.json().map_err(|err| (400, err))?;
+        let step: Cmd = body
+            .get("move")
+            .and_then(|m| m.as_str())
+            .ok_or_else(|| (400, String::from("missing move")))?
+            .parse()
+            .map_err(|err| (400, err))?;
+        let (id, g) = self.game_mut(id)?;
+        if !g.info().result.is_empty() {
+            return Err((409, String::from("the game is over")));
+        }
+        g.next(step).map_err(|err| (400, err))?;
+        let event = GameEvent::last(g).unwrap();
+        self.broadcast(id, &event);
+        self.save(id)?;
+        Ok(Response::json(200, &game_json(id, &self.games[&id])))
+    }
+
+    fn undo(&mut self, id: &str) -> Handled {
+        let (id, g) = self.game_mut(id)?;
+        g.undo().map_err(|err| (409, err))?;
+        self.broadcast(id, &GameEvent::Undo(1));
+        self.save(id)?;
+        Ok(Response::json(200, &game_json(id, &self.games[&id])))
+    }
+
+    fn save(&self, id: usize) -> Result<(), (u16, String)> {
+        if let Some(dir) = &self.dir {
+            let filename = Path::new(dir).join(format!("{}.sgf", id));
+            self.games[&id]
+                .dump_to(&filename.to_string_lossy())
+                .map_err(|err| (500, err))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn game_json(id: usize, g: &Game) -> Json {
+    let info = g.info();
+    let steps: Vec<Json> = g.steps().iter().map(|s| s.to_string().into()).collect();
+    let (black, white) = g.captures();
+    Json::object([
+        ("id", id.into()),
+        ("size", g.board().lines().into()),
+        ("rules", info.rules.clone().into()),
+        ("komi", info.komi.into()),
+        ("handicap", info.handicap.into()),
+        ("black", info.black.clone().into()),
+        ("white", info.white.clone().into()),
+        ("next", g.next_player().to_string().into()),
+        ("steps", steps.into()),
+        (
+            "captures",
+            Json::object([("black", black.into()), ("white", white.into())]),
+        ),
+        ("result", info.result.clone().into()),
+        ("board", board_json(g)),
+    ])
+}
+
+// rows from the top, X for black, O for white and . for empty
+pub fn board_json(g: &Game) -> Json {
+    let board = g.board();
+    let size = board.lines();
+    let rows: Vec<Json> = (1..=size)
+        .map(|y| {
+            (1..=size)
+                .map(|x| match board.at(x, y).unwrap() {
+                    Stone::Black => 'X',
+                    Stone::White => 'O',
+                    Stone::Empty => '.',
+                })
+                .collect::<String>()
+                .into()
+        })
+        .collect();
+    let last = g.last_step().map(|(x, y)| Cmd::point_to_cmd(x, y));
+    Json::object([
+        ("size", size.into()),
+        ("rows", rows.into()),
+        ("last", last.into()),
+        ("next", g.next_player().to_string().into()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::Watcher;
+    use crate::util::TempDir;
+    use std::io::{Read, Write};
+
+    // status and body of the response
+    fn call(addr: &str, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, body.to_string())
+    }
+
+    fn start(dir: &str) -> String {
+        let server = ApiServer::bind("127.0.0.1:0", Some(dir)).unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        thread::spawn(move || server.run());
+        addr
+    }
+
+    #[test]
+    fn games_over_http() {
+        let dir = TempDir::new("api");
+        let addr = start(dir.path());
+
+        let (status, body) = call(&addr, "POST", "/games", r#"{"size": 9, "handicap": 2}"#);
+        assert!(status == 201);
+        let game: Json = body.parse().unwrap();
+        assert!(
+            game.get("id") == Some(&Json::from(1)) && game.get("next") == Some(&"white".into())
+        );
+        assert!(game.get("komi") == Some(&Json::from(0.5)));
+
+        let mut watcher = Watcher::connect(&addr, "/games/1/events").unwrap();
+        assert!(matches!(
+            watcher.next_event(),
+            Ok(Some(GameEvent::Start { .. }))
+        ));
+        assert!(Watcher::connect(&addr, "/games/9/events").is_err());
+
+        let (status, body) = call(&addr, "POST", "/games/1/moves", r#"{"move": "cc"}"#);
+        assert!(status == 200 && body.contains(r#""steps":["cc"]"#));
+        let (status, _) = call(&addr, "POST", "/games/1/moves", r#"{"move": "cc"}"#);
+        assert!(status == 400);
+        let (status, body) = call(&addr, "GET", "/games/1/board", "");
+        let board: Json = body.parse().unwrap();
+        assert!(
+            status == 200
+                && board.get("rows").unwrap().as_array().unwrap()[2] == "..O...X..".into()
+        );
+        let (_, body) = call(&addr, "GET", "/games/1/legal", "");
+        let legal: Json = body.parse().unwrap();
+        assert!(legal.get("moves").unwrap().as_array().unwrap().len() == 81 - 3);
+        let (status, body) = call(&addr, "GET", "/games/1/sgf", "");
+        assert!(status == 200 && body.contains("AB[gc][cg]") && body.contains("W[cc]"));
+        let (status, _) = call(&addr, "POST", "/games/1/undo", "");
+        assert!(status == 200);
+        let next = watcher.next_event().unwrap();
+        assert!(
+            next == Some(GameEvent::Next(
+                Player::White,
+                Cmd::Step(String::from("cc"))
+            ))
+        );
+        assert!(watcher.next_event().unwrap() == Some(GameEvent::Undo(1)));
+        assert!(watcher.game().step_count() == 0 && watcher.game().handicap_stones().len() == 2);
+        assert!(call(&addr, "GET", "/games/2", "").0 == 404);
+        assert!(call(&addr, "PUT", "/games/1", "").0 == 405);
+
+        // games are loaded from the directory by another server
+        call(&addr, "POST", "/games", r#"{"black": "Shusaku"}"#);
+        let addr = start(dir.path());
+        let (_, body) = call(&addr, "GET", "/games", "");
+        let list: Json = body.parse().unwrap();
+        let games = list.get("games").unwrap().as_array().unwrap();
+        assert!(games.len() == 2 && games[1].get("black") == Some(&"Shusaku".into()));
+        assert!(call(&addr, "DELETE", "/games/2", "").0 == 204);
+        assert!(!Path::new(&dir.file("2.sgf")).exists());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::basic::*;
+use crate::net::{Message, Messages};
+use std::io::Write;
+use std::net::TcpStream;
+
+// a player connected to a server, with the game kept the same as the
+// server's one by messages applied
+pub struct Client {
+    stream: TcpStream,
+    player: Player,
+    token: String,
+    game: Game,
+}
+
+impl Client {
+    // join as a new player
+    pub fn join(
+        addr: &str,
+        color: Option<Player>,
+        name: &str,
+    ) -> Result<(Client, Messages), String> {
+        Client::connect(addr, Message::Hello(color, name.to_string()))
+    }
+
+    // take the seat back by the token got in welcome
+    pub fn resume(addr: &str, token: &str) -> Result<(Client, Messages), String> {
+        Client::connect(addr, Message::Resume(token.to_string()))
+    }
+
+    // send the first message, and wait for welcome and the steps so far.
+    // later messages are read from the returned messages.
+    fn connect(addr: &str, first: Message) -> Result<(Client, Messages), String> {
+        let mut stream =
+            TcpStream::connect(addr).map_err(|err| format!("can not connect {}: {}", addr, err))?;
+        writeln!(stream, "{}", first).map_err(|err| err.to_string())?;
+        let reader = stream.try_clone().map_err(|err| err.to_string())?;
+        let mut messages = Messages::new(reader);
+        let mut next = || {
+            messages
+                .next()
+                .unwrap_or_else(|| Err(String::from("connection closed")))
+        };
+        let (player, token, size) = match next()? {
+            Message::Welcome(player, token, size) => (player, token, size),
+            Message::Error(err) => return Err(err),
+            other => return Err(format!("unexpected message: {}", other)),
+        };
+        let size = match size {
+            9 => BoardSize::Small,
+            13 => BoardSize::Medium,
+            19 => BoardSize::Normal,
+            other => return Err(format!("invalid board size: {}", other)),
+        };
+        let mut client = Client {
+            stream,
+            player,
+            token,
+            game: Game::new(size),
+        };
+        let sync = next()?;
+        client.apply(&sync)?;
+        Ok((client, messages))
+    }
+
+    pub fn player(&self) -> Player {
+        self.player.clone()
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), String> {
+        writeln!(self.stream, "{}", message).map_err(|err| format!("can not send: {}", err))
+    }
+
+    // keep the game the same as the server's one
+    pub fn apply(&mut self, message: &Message) -> Result<(), String> {
+        match message {
+            Message::Sync(steps) => {
+                self.game = Game::new(self.game.board_size());
+                for step in steps {
+                    self.game.next(step.clone())?;
+                }
+            }
+            Message::Move(_, step) => self.game.next(step.clone())?,
+            Message::Undone(count) => {
+                for _ in 0..*count {
+                    self.game.undo()?;
+                }
+            }
+            Message::Result(result) => self.game.set_info("result", result)?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use crate::util::Json;
+use std::io::{self, BufRead, Read, Write};
+
+// request body larger than this is refused
+const MAX_BODY: usize = 1 << 20;
+// request line and header lines longer than this are refused, and so are
+// requests with more headers
+const MAX_HEAD_LINE: usize = 8 << 10;
+const MAX_HEADERS: usize = 100;
+
+// errors of a request head over the limits
+pub const LINE_TOO_LONG: &str = "line is too long";
+pub const TOO_MANY_HEADERS: &str = "too many headers";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub method: String,
+    // without query
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    // read one http/1.1 request, the body is read by content-length
+    pub fn read<R: BufRead>(reader: &mut R) -> Result<Request, String> {
+        let line = read_limited_line(reader, MAX_HEAD_LINE)?.unwrap_or_default();
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (method, target) = match fields[..] {
+            [method, target, version] if version.starts_with("HTTP/") => (method, target),
+            _ => return Err(format!("invalid request line: {}", line.trim())),
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let mut request = Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            headers: vec![],
+            body: vec![],
+        };
+        loop {
+            let line = read_limited_line(reader, MAX_HEAD_LINE)?.unwrap_or_default();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if request.headers.len() == MAX_HEADERS {
+                return Err(String::from(TOO_MANY_HEADERS));
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| format!("invalid header: {}", line))?;
+            request
+                .headers
+                .push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+        let length: usize = match request.header("content-length") {
+            Some(length) => length
+                .parse()
+                .map_err(|_| format!("invalid content-length: {}", length))?,
+            None => 0,
+        };
+        if length > MAX_BODY {
+            return Err(format!("body is too large: {}", length));
+        }
+        request.body = vec![0; length];
+        reader
+            .read_exact(&mut request.body)
+            .map_err(|err| format!("can not read body: {}", err))?;
+        Ok(request)
+    }
+
+    // header value by name in any case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.headers
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    // json body, empty body is an empty object
+    pub fn json(&self) -> Result<Json, String> {
+        let text = String::from_utf8_lossy(&self.body);
+        if text.trim().is_empty() {
+            return Ok(Json::Object(vec![]));
+        }
+        text.parse()
+    }
+}
+
+// one line with the newline, read no more than limit bytes, so a peer can
+// not make it grow without end. none at the end of input.
+pub(crate) fn read_limited_line<R: BufRead>(
+    reader: &mut R,
+    limit: usize,
+) -> Result<Option<String>, String> {
+    let mut line = String::new();
+    let n = Read::take(reader, limit as u64)
+        .read_line(&mut line)
+        .map_err(|err| format!("can not read: {}", err))?;
+    if n == limit && !line.ends_with('\n') {
+        return Err(String::from(LINE_TOO_LONG));
+    }
+    Ok(if n == 0 { None } else { Some(line) })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &str, body: Vec<u8>) -> Response {
+        Response {
+            status,
+            headers: vec![(String::from("Content-Type"), content_type.to_string())],
+            body,
+        }
+    }
+
+    pub fn json(status: u16, json: &Json) -> Response {
+        Response::new(status, "application/json", json.to_string().into_bytes())
+    }
+
+    // json like {"error": "..."}
+    pub fn error(status: u16, message: &str) -> Response {
+        Response::json(status, &Json::object([("error", message.into())]))
+    }
+
+    // answer to a request which can not be read, 431 if the head is over the
+    // limits
+    pub fn unreadable(err: &str) -> Response {
+        if err == LINE_TOO_LONG || err == TOO_MANY_HEADERS {
+            Response::error(431, err)
+        } else {
+            Response::error(400, err)
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Response {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // the connection is closed after each response
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason(self.status));
+        for (name, value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str(&format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            self.body.len()
+        ));
+        writer.write_all(head.as_bytes())?;
+        writer.write_all(&self.body)?;
+        writer.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_request_and_write_response() {
+        let data = "POST /games/1/moves?x=1 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 15\r\n\r\n{\"move\": \"dd\"}\n";
+        let request = Request::read(&mut data.as_bytes()).unwrap();
+        assert!(request.method == "POST" && request.path == "/games/1/moves");
+        assert!(request.query == "x=1" && request.header("HOST") == Some("localhost"));
+        assert!(request.json().unwrap().get("move") == Some(&Json::from("dd")));
+        assert!(Request::read(&mut "hello\r\n\r\n".as_bytes()).is_err());
+        let long = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEAD_LINE));
+        assert!(Request::read(&mut long.as_bytes()).err().unwrap() == LINE_TOO_LONG);
+        let many = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "A: b\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert!(Request::read(&mut many.as_bytes()).err().unwrap() == TOO_MANY_HEADERS);
+        assert!(Response::unreadable(LINE_TOO_LONG).status == 431);
+        assert!(Response::unreadable("invalid header: a").status == 400);
+
+        let mut out = vec![];
+        Response::error(404, "no game").write_to(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\n"));
+        assert!(text.ends_with("\r\n\r\n{\"error\":\"no game\"}"));
+    }
+}
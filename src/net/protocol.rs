@@ -0,0 +1,216 @@
+use crate::basic::*;
+use crate::net::read_limited_line;
+use std::fmt;
+use std::io::BufReader;
+use std::net::TcpStream;
+use std::str::FromStr;
+
+// one line of the text protocol, steps are in sgf notation or pass
+//
+// client to server:
+//     hello <black|white|any> <name>    join the game
+//     resume <token>                    take the seat back after disconnected
+//     play <step>
+//     resign
+//     undo                              ask to take back the last step
+//     accept / reject                   answer an undo request
+//     result <result>                   after 2 passes, the game is over when
+//                                       both players send the same result
+//
+// server to client:
+//     welcome <black|white> <token> <size>
+//     sync <step>...                    steps played so far
+//     opponent <name>                   the opponent is connected
+//     left                              the opponent is disconnected
+//     move <black|white> <step>
+//     undo-request
+//     undone <count>                    count steps are taken back
+//     rejected
+//     estimate <score>                  after 2 passes, the score guessed by
+//                                       the territory estimate, not a result
+//     proposal <result>                 the result sent by the opponent
+//     result <result>                   like B+R or W+6.5, the game is over
+//     error <message>
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Hello(Option<Player>, String),
+    Resume(String),
+    Play(Cmd),
+    Resign,
+    Undo,
+    Accept,
+    Reject,
+    Welcome(Player, String, usize),
+    Sync(Vec<Cmd>),
+    Opponent(String),
+    Left,
+    Move(Player, Cmd),
+    UndoRequest,
+    Undone(usize),
+    Rejected,
+    Estimate(String),
+    Proposal(String),
+    Result(String),
+    Error(String),
+}
+
+impl FromStr for Message {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (key, value) = s.split_once(' ').unwrap_or((s, ""));
+        let missing = || format!("invalid message: {}", s);
+        match key {
+            "hello" => {
+                let (color, name) = value.split_once(' ').ok_or_else(missing)?;
+                let color = match color {
+                    "any" => None,
+                    color => Some(color.parse()?),
+                };
+                Ok(Message::Hello(color, name.to_string()))
+            }
+            "resume" if !value.is_empty() => Ok(Message::Resume(value.to_string())),
+            "play" => Ok(Message::Play(value.parse()?)),
+            "resign" => Ok(Message::Resign),
+            "undo" => Ok(Message::Undo),
+            "accept" => Ok(Message::Accept),
+            "reject" => Ok(Message::Reject),
+            "welcome" => {
+                let fields: Vec<&str> = value.split(' ').collect();
+                match fields[..] {
+                    [color, token, size] => Ok(Message::Welcome(
+                        color.parse()?,
+                        token.to_string(),
+                        size.parse().map_err(|_| missing())?,
+                    )),
+                    _ => Err(missing()),
+                }
+            }
+            "sync" => value
+                .split_whitespace()
+                .map(|step| step.parse())
+                .collect::<Result<Vec<Cmd>, String>>()
+                .map(Message::Sync),
+            "opponent" => Ok(Message::Opponent(value.to_string())),
+            "left" => Ok(Message::Left),
+            "move" => {
+                let (color, step) = value.split_once(' ').ok_or_else(missing)?;
+                Ok(Message::Move(color.parse()?, step.parse()?))
+            }
+            "undo-request" => Ok(Message::UndoRequest),
+            "undone" => Ok(Message::Undone(value.parse().map_err(|_| missing())?)),
+            "rejected" => Ok(Message::Rejected),
+            "estimate" => Ok(Message::Estimate(value.to_string())),
+            "proposal" => Ok(Message::Proposal(value.to_string())),
+            "result" => Ok(Message::Result(value.to_string())),
+            "error" => Ok(Message::Error(value.to_string())),
+            _ => Err(missing()),
+        }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Hello(Some(color), name) => write!(f, "hello {} {}", color, name),
+            Message::Hello(None, name) => write!(f, "hello any {}", name),
+            Message::Resume(token) => write!(f, "resume {}", token),
+            Message::Play(step) => write!(f, "play {}", step),
+            Message::Resign => write!(f, "resign"),
+            Message::Undo => write!(f, "undo"),
+            Message::Accept => write!(f, "accept"),
+            Message::Reject => write!(f, "reject"),
+            Message::Welcome(color, token, size) => {
+                write!(f, "welcome {} {} {}", color, token, size)
+            }
+            Message::Sync(steps) => {
+                let steps: Vec<String> = steps.iter().map(|s| s.to_string()).collect();
+                write!(f, "sync {}", steps.join(" "))
+            }
+            Message::Opponent(name) => write!(f, "opponent {}", name),
+            Message::Left => write!(f, "left"),
+            Message::Move(color, step) => write!(f, "move {} {}", color, step),
+            Message::UndoRequest => write!(f, "undo-request"),
+            Message::Undone(count) => write!(f, "undone {}", count),
+            Message::Rejected => write!(f, "rejected"),
+            Message::Estimate(score) => write!(f, "estimate {}", score),
+            Message::Proposal(result) => write!(f, "proposal {}", result),
+            Message::Result(result) => write!(f, "result {}", result),
+            Message::Error(message) => write!(f, "error {}", message),
+        }
+    }
+}
+
+// a message line longer than this is refused and ends the messages
+pub(crate) const MAX_LINE: usize = 64 << 10;
+
+// messages read from a connection until it is closed
+pub struct Messages {
+    reader: BufReader<TcpStream>,
+    done: bool,
+}
+
+impl Messages {
+    pub fn new(stream: TcpStream) -> Messages {
+        Messages {
+            reader: BufReader::new(stream),
+            done: false,
+        }
+    }
+
+    // go on with a reader read already
+    pub fn with_reader(reader: BufReader<TcpStream>) -> Messages {
+        Messages {
+            reader,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Messages {
+    type Item = Result<Message, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match read_limited_line(&mut self.reader, MAX_LINE) {
+            Ok(Some(line)) => Some(line.parse()),
+            Ok(None) => None,
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_in_text() {
+        let messages = [
+            Message::Hello(None, String::from("Go Seigen")),
+            Message::Hello(Some(Player::White), String::from("Kitani")),
+            Message::Play(Cmd::Step(String::from("dd"))),
+            Message::Play(Cmd::Pass),
+            Message::Welcome(Player::Black, String::from("a1b2"), 19),
+            Message::Sync(vec![]),
+            Message::Sync(vec![Cmd::Step(String::from("dd")), Cmd::Pass]),
+            Message::Move(Player::White, Cmd::Step(String::from("pp"))),
+            Message::Undone(2),
+            Message::Estimate(String::from("W+0.5")),
+            Message::Proposal(String::from("B+2.5")),
+            Message::Result(String::from("B+R")),
+        ];
+        for m in messages {
+            assert!(m.to_string().parse::<Message>().unwrap() == m);
+        }
+        assert!("play d4".parse::<Message>().is_err());
+        assert!("hello red me".parse::<Message>().is_err());
+        assert!("welcome black".parse::<Message>().is_err());
+    }
+}
@@ -0,0 +1,520 @@
+use crate::basic::*;
+use crate::net::{
+    read_limited_line, GameEvent, Message, Messages, Request, Response, Spectators, WebSocket,
+    MAX_LINE, WRITE_TIMEOUT,
+};
+use crate::util::secret_token;
+use std::collections::HashMap;
+use std::io::{BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+enum Event {
+    Connected(usize, TcpStream),
+    Received(usize, Result<Message, String>),
+    Watch(usize, Result<Request, String>),
+    Closed(usize),
+    Stop,
+}
+
+// a player of the game, conn is none while disconnected
+struct Seat {
+    name: String,
+    token: String,
+    conn: Option<usize>,
+}
+
+// hosts one game for 2 players, black is the first one joined unless
+// colours are asked in hello. steps are checked by the rules of game, and a
+// player disconnected can take the seat back by the token in welcome.
+// spectators connect to the same port by websocket and get game events.
+pub struct Server {
+    listener: TcpListener,
+    size: BoardSize,
+    komi: f64,
+    undo_rule: UndoRule,
+    tx: Sender<Event>,
+    rx: Receiver<Event>,
+}
+
+// ends the game of a running server, which is left without result
+pub struct ServerStopper {
+    tx: Sender<Event>,
+}
+
+impl ServerStopper {
+    pub fn stop(&self) {
+        let _ = self.tx.send(Event::Stop);
+    }
+}
+
+impl Server {
+    pub fn bind(addr: &str, size: BoardSize, komi: f64) -> Result<Server, String> {
+        let listener =
+            TcpListener::bind(addr).map_err(|err| format!("can not listen {}: {}", addr, err))?;
+        let (tx, rx) = channel();
+        Ok(Server {
+            listener,
+            size,
+            komi,
+            undo_rule: UndoRule::Unlimited,
+            tx,
+            rx,
+        })
+    }
+
+    // undo is asked and accepted in any rule but free, unlimited by default
+    pub fn set_undo_rule(&mut self, rule: UndoRule) {
+        self.undo_rule = match rule {
+            UndoRule::Free => UndoRule::Unlimited,
+            rule => rule,
+        };
+    }
+
+    pub fn stopper(&self) -> ServerStopper {
+        ServerStopper {
+            tx: self.tx.clone(),
+        }
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.listener.local_addr().map_err(|err| err.to_string())
+    }
+
+    // serve until the game is over by resign, or by 2 passes and both
+    // players sending the same result, the game with result is returned. the game is returned as it is if stopped.
+    pub fn run(self) -> Result<Game, String> {
+        let (tx, rx) = (self.tx, self.rx);
+        let listener = self.listener;
+        thread::spawn(move || accept(listener, tx));
+
+        let mut room = Room {
+            game: Game::new(self.size),
+            conns: HashMap::new(),
+            seats: [None, None],
+            passes: 0,
+            proposals: [None, None],
+            spectators: Spectators::new(),
+        };
+        room.game.set_info("komi", &self.komi.to_string())?;
+        room.game.set_undo_rule(self.undo_rule);
+        for event in rx {
+            match event {
+                Event::Connected(id, stream) => {
+                    room.conns.insert(id, stream);
+                }
+                Event::Received(id, Ok(message)) => room.receive(id, message)?,
+                Event::Received(id, Err(err)) => room.send(id, &Message::Error(err)),
+                Event::Watch(id, request) => room.watch(id, request),
+                Event::Closed(id) => room.close(id),
+                Event::Stop => {
+                    room.broadcast(&Message::Error(String::from("the game is closed")));
+                    for stream in room.conns.values() {
+                        let _ = stream.shutdown(Shutdown::Both);
+                    }
+                    break;
+                }
+            }
+            if !room.game.info().result.is_empty() {
+                break;
+            }
+        }
+        room.spectators.close();
+        Ok(room.game)
+    }
+}
+
+fn accept(listener: TcpListener, tx: Sender<Event>) {
+    for (id, stream) in listener.incoming().enumerate() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let Ok(reader) = stream.try_clone() else {
+            continue;
+        };
+        // a player who does not read must not block the room
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+        if tx.send(Event::Connected(id, stream)).is_err() {
+            return;
+        }
+        let tx = tx.clone();
+        thread::spawn(move || {
+            read_connection(id, reader, &tx);
+            let _ = tx.send(Event::Closed(id));
+        });
+    }
+}
+
+// messages of a player, or a websocket upgrade of a spectator which is
+// told by the first line
+fn read_connection(id: usize, stream: TcpStream, tx: &Sender<Event>) {
+    let mut reader = BufReader::new(stream);
+    let line = match read_limited_line(&mut reader, MAX_LINE) {
+        Ok(Some(line)) => line,
+        Ok(None) => return,
+        Err(err) => {
+            let _ = tx.send(Event::Received(id, Err(err)));
+            return;
+        }
+    };
+    if line.starts_with("GET ") {
+        let request = Request::read(&mut line.as_bytes().chain(&mut reader));
+        // the spectators read the rest of the websocket
+        let _ = tx.send(Event::Watch(id, request));
+        return;
+    }
+    if tx.send(Event::Received(id, line.parse())).is_err() {
+        return;
+    }
+    for message in Messages::with_reader(reader) {
+        if tx.send(Event::Received(id, message)).is_err() {
+            return;
+        }
+    }
+}
+
+struct Room {
+    game: Game,
+    conns: HashMap<usize, TcpStream>,
+    // black and white
+    seats: [Option<Seat>; 2],
+    passes: usize,
+    // results sent by black and white after 2 passes
+    proposals: [Option<String>; 2],
+    spectators: Spectators,
+}
+
+impl Room {
+    fn send(&mut self, id: usize, message: &Message) {
+        if let Some(stream) = self.conns.get_mut(&id) {
+            let _ = writeln!(stream, "{}", message);
+        }
+    }
+
+    fn send_seat(&mut self, seat: usize, message: &Message) {
+        if let Some(id) = self.seats[seat].as_ref().and_then(|s| s.conn) {
+            self.send(id, message);
+        }
+    }
+
+    fn broadcast(&mut self, message: &Message) {
+        for seat in 0..2 {
+            self.send_seat(seat, message);
+        }
+    }
+
+    fn watch(&mut self, id: usize, request: Result<Request, String>) {
+        let Some(mut stream) = self.conns.remove(&id) else {
+            return;
+        };
+        let request = match request {
+            Ok(request) => request,
+            Err(err) => {
+                let _ = Response::unreadable(&err).write_to(&mut stream);
+                return;
+            }
+        };
+        if let Ok(socket) = WebSocket::accept(stream, &request) {
+            self.spectators.join(socket, &self.game);
+        }
+    }
+
+    fn seat_of(&self, id: usize) -> Option<usize> {
+        (0..2).find(|i| self.seats[*i].as_ref().is_some_and(|s| s.conn == Some(id)))
+    }
+
+    fn close(&mut self, id: usize) {
+        self.conns.remove(&id);
+        if let Some(seat) = self.seat_of(id) {
+            self.seats[seat].as_mut().unwrap().conn = None;
+            self.send_seat(1 - seat, &Message::Left);
+        }
+    }
+
+    fn receive(&mut self, id: usize, message: Message) -> Result<(), String> {
+        let result = match (self.seat_of(id), message) {
+            (None, Message::Hello(color, name)) => self.join(id, color, name),
+            (None, Message::Resume(token)) => self.resume(id, &token),
+            (None, _) => Err(String::from("say hello first")),
+            (Some(_), Message::Hello(..)) | (Some(_), Message::Resume(_)) => {
+                Err(String::from("already in the game"))
+            }
+            (Some(seat), message) => self.play(seat, message),
+        };
+        if let Err(err) = result {
+            self.send(id, &Message::Error(err));
+        }
+        Ok(())
+    }
+
+    fn join(&mut self, id: usize, color: Option<Player>, name: String) -> Result<(), String> {
+        let seat = match color {
+            Some(Player::Black) => 0,
+            Some(Player::White) => 1,
+            None => (0..2)
+                .find(|i| self.seats[*i].is_none())
+                .ok_or_else(|| String::from("the game is full"))?,
+        };
+        if self.seats[seat].is_some() {
+            return Err(format!("{} is taken", seat_player(seat)));
+        }
+        let token = secret_token();
+        self.game.set_info(&seat_player(seat).to_string(), &name)?;
+        self.seats[seat] = Some(Seat {
+            name,
+            token,
+            conn: Some(id),
+        });
+        self.welcome(seat);
+        Ok(())
+    }
+
+    fn resume(&mut self, id: usize, token: &str) -> Result<(), String> {
+        let seat = (0..2)
+            .find(|i| self.seats[*i].as_ref().is_some_and(|s| s.token == token))
+            .ok_or_else(|| String::from("invalid token"))?;
+        let s = self.seats[seat].as_mut().unwrap();
+        if s.conn.is_some() {
+            return Err(String::from("the player is connected"));
+        }
+        s.conn = Some(id);
+        self.welcome(seat);
+        Ok(())
+    }
+
+    // tell the seat the game so far, and both players are there if so
+    fn welcome(&mut self, seat: usize) {
+        let token = self.seats[seat].as_ref().unwrap().token.clone();
+        let size = self.game.board().lines();
+        self.send_seat(seat, &Message::Welcome(seat_player(seat), token, size));
+        self.send_seat(seat, &Message::Sync(self.game.steps()));
+        if let (Some(a), Some(b)) = (&self.seats[seat], &self.seats[1 - seat]) {
+            let (name, other) = (a.name.clone(), b.name.clone());
+            if b.conn.is_some() {
+                self.send_seat(seat, &Message::Opponent(other));
+                self.send_seat(1 - seat, &Message::Opponent(name));
+            }
+            if self.game.undo_request() == Some(seat_player(1 - seat)) {
+                self.send_seat(seat, &Message::UndoRequest);
+            }
+            if let Some(result) = self.proposals[1 - seat].clone() {
+                self.send_seat(seat, &Message::Proposal(result));
+            }
+        }
+    }
+
+    fn play(&mut self, seat: usize, message: Message) -> Result<(), String> {
+        let player = seat_player(seat);
+        let opponent = self.seats[1 - seat]
+            .as_ref()
+            .is_some_and(|s| s.conn.is_some());
+        match message {
+            Message::Play(step) => {
+                if !opponent {
+                    return Err(String::from("the opponent is not connected"));
+                }
+                if self.game.next_player() != player {
+                    return Err(String::from("not your turn"));
+                }
+                self.game.next(step.clone())?;
+                self.broadcast(&Message::Move(player.clone(), step.clone()));
+                self.spectators
+                    .broadcast(&GameEvent::Next(player, step.clone()));
+                self.passes = if step == Cmd::Pass {
+                    self.passes + 1
+                } else {
+                    0
+                };
+                // playing on drops the results sent, the estimate is only a
+                // hint for the players to agree on the result
+                self.proposals = [None, None];
+                if self.passes == 2 {
+                    let score =
+                        bouzy(self.game.board()).score(self.game.info().komi.unwrap_or_default());
+                    self.broadcast(&Message::Estimate(format_score(score)));
+                }
+            }
+            Message::Result(result) => {
+                if self.passes < 2 {
+                    return Err(String::from("the result is sent after 2 passes"));
+                }
+                if !is_valid_result(&result) {
+                    return Err(format!("invalid result: {}", result));
+                }
+                self.proposals[seat] = Some(result.clone());
+                if self.proposals[1 - seat].as_ref() == Some(&result) {
+                    self.finish(result)?;
+                } else {
+                    self.send_seat(1 - seat, &Message::Proposal(result));
+                }
+            }
+            Message::Resign => {
+                let winner = if player == Player::Black { "W" } else { "B" };
+                self.finish(format!("{}+R", winner))?;
+            }
+            Message::Undo => {
+                if !opponent {
+                    return Err(String::from("the opponent is not connected"));
+                }
+                self.game.request_undo(player)?;
+                self.send_seat(1 - seat, &Message::UndoRequest);
+            }
+            Message::Accept => {
+                self.game.accept_undo(player)?;
+                self.passes = 0;
+                self.proposals = [None, None];
+                self.broadcast(&Message::Undone(1));
+                self.spectators.broadcast(&GameEvent::Undo(1));
+            }
+            Message::Reject => {
+                self.game.reject_undo(player)?;
+                self.send_seat(1 - seat, &Message::Rejected);
+            }
+            other => return Err(format!("unexpected message: {}", other)),
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, result: String) -> Result<(), String> {
+        self.game.set_info("result", &result)?;
+        self.spectators
+            .broadcast(&GameEvent::Result(result.clone()));
+        self.broadcast(&Message::Result(result));
+        Ok(())
+    }
+}
+
+fn seat_player(seat: usize) -> Player {
+    if seat == 0 {
+        Player::Black
+    } else {
+        Player::White
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{Client, Watcher};
+
+    fn step(p: &str) -> Cmd {
+        Cmd::Step(p.to_string())
+    }
+
+    #[test]
+    fn play_on_localhost() {
+        let server = Server::bind("127.0.0.1:0", BoardSize::Small, 6.5).unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || server.run().map(|g| g.to_sgf()));
+
+        let (mut black, mut bm) = Client::join(&addr, None, "Shusaku").unwrap();
+        let (mut white, mut wm) = Client::join(&addr, None, "Gennan").unwrap();
+        assert!(black.player() == Player::Black && white.player() == Player::White);
+        assert!(bm.next().unwrap() == Ok(Message::Opponent(String::from("Gennan"))));
+        assert!(wm.next().unwrap() == Ok(Message::Opponent(String::from("Shusaku"))));
+        assert!(
+            matches!(Client::join(&addr, None, "Inseki"), Err(err) if err == "the game is full")
+        );
+
+        black.send(&Message::Play(step("cc"))).unwrap();
+        for (client, messages) in [(&mut black, &mut bm), (&mut white, &mut wm)] {
+            let m = messages.next().unwrap().unwrap();
+            assert!(m == Message::Move(Player::Black, step("cc")));
+            client.apply(&m).unwrap();
+        }
+        // a spectator joined late gets the game so far
+        let mut watcher = Watcher::connect(&addr, "/").unwrap();
+        assert!(matches!(
+            watcher.next_event(),
+            Ok(Some(GameEvent::Start { black, .. })) if black == "Shusaku"
+        ));
+        let next = watcher.next_event().unwrap();
+        assert!(next == Some(GameEvent::Next(Player::Black, step("cc"))));
+
+        black.send(&Message::Play(step("gg"))).unwrap();
+        assert!(bm.next().unwrap() == Ok(Message::Error(String::from("not your turn"))));
+        white.send(&Message::Play(step("cc"))).unwrap();
+        assert!(matches!(wm.next().unwrap(), Ok(Message::Error(_))));
+
+        // black takes back the step after asked
+        black.send(&Message::Undo).unwrap();
+        assert!(wm.next().unwrap() == Ok(Message::UndoRequest));
+        white.send(&Message::Accept).unwrap();
+        for (client, messages) in [(&mut black, &mut bm), (&mut white, &mut wm)] {
+            let m = messages.next().unwrap().unwrap();
+            assert!(m == Message::Undone(1));
+            client.apply(&m).unwrap();
+            assert!(client.game().step_count() == 0);
+        }
+        assert!(watcher.next_event().unwrap() == Some(GameEvent::Undo(1)));
+
+        // white comes back after disconnected
+        let token = white.token().to_string();
+        drop((white, wm));
+        assert!(bm.next().unwrap() == Ok(Message::Left));
+        black.send(&Message::Play(step("ee"))).unwrap();
+        assert!(matches!(bm.next().unwrap(), Ok(Message::Error(_))));
+        let (_white, mut wm) = Client::resume(&addr, &token).unwrap();
+        assert!(wm.next().unwrap() == Ok(Message::Opponent(String::from("Shusaku"))));
+        assert!(bm.next().unwrap() == Ok(Message::Opponent(String::from("Gennan"))));
+
+        black.send(&Message::Resign).unwrap();
+        assert!(bm.next().unwrap() == Ok(Message::Result(String::from("W+R"))));
+        assert!(wm.next().unwrap() == Ok(Message::Result(String::from("W+R"))));
+        let result = watcher.next_event().unwrap();
+        assert!(result == Some(GameEvent::Result(String::from("W+R"))));
+        assert!(watcher.next_event().unwrap().is_none());
+        assert!(watcher.game().info().white == "Gennan");
+        let sgf = handle.join().unwrap().unwrap();
+        assert!(sgf.contains("RE[W+R]") && sgf.contains("PB[Shusaku]"));
+    }
+
+    #[test]
+    fn agree_on_result() {
+        let server = Server::bind("127.0.0.1:0", BoardSize::Small, 6.5).unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || server.run().map(|g| g.to_sgf()));
+        let (mut black, mut bm) = Client::join(&addr, None, "Shusaku").unwrap();
+        let (mut white, mut wm) = Client::join(&addr, None, "Gennan").unwrap();
+        bm.next().unwrap().unwrap();
+        wm.next().unwrap().unwrap();
+        let result = |r: &str| Message::Result(r.to_string());
+
+        black.send(&result("B+R")).unwrap();
+        assert!(matches!(bm.next().unwrap(), Ok(Message::Error(_))));
+        for (i, step) in [step("cc"), Cmd::Pass, Cmd::Pass].into_iter().enumerate() {
+            let client = if i == 1 { &mut white } else { &mut black };
+            client.send(&Message::Play(step)).unwrap();
+            assert!(matches!(bm.next().unwrap(), Ok(Message::Move(..))));
+            assert!(matches!(wm.next().unwrap(), Ok(Message::Move(..))));
+        }
+        // the estimate is no result, the game goes on until both agree
+        assert!(matches!(bm.next().unwrap(), Ok(Message::Estimate(_))));
+        assert!(matches!(wm.next().unwrap(), Ok(Message::Estimate(_))));
+        black.send(&result("black")).unwrap();
+        assert!(matches!(bm.next().unwrap(), Ok(Message::Error(_))));
+        white.send(&result("W+0.5")).unwrap();
+        assert!(bm.next().unwrap() == Ok(Message::Proposal(String::from("W+0.5"))));
+        black.send(&result("B+3.5")).unwrap();
+        assert!(wm.next().unwrap() == Ok(Message::Proposal(String::from("B+3.5"))));
+        white.send(&result("B+3.5")).unwrap();
+        assert!(bm.next().unwrap() == Ok(result("B+3.5")));
+        assert!(wm.next().unwrap() == Ok(result("B+3.5")));
+        assert!(handle.join().unwrap().unwrap().contains("RE[B+3.5]"));
+    }
+
+    #[test]
+    fn stop_when_host_leaves() {
+        let server = Server::bind("127.0.0.1:0", BoardSize::Small, 6.5).unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        let stopper = server.stopper();
+        let handle = thread::spawn(move || server.run().map(|g| g.info().result.clone()));
+        let (black, mut bm) = Client::join(&addr, None, "Shusaku").unwrap();
+        assert!(black.token().len() == 32);
+        stopper.stop();
+        assert!(handle.join().unwrap().unwrap().is_empty());
+        let closed = Message::Error(String::from("the game is closed"));
+        assert!(bm.next().unwrap() == Ok(closed) && bm.next().is_none());
+    }
+}
@@ -0,0 +1,355 @@
+use crate::basic::*;
+use crate::net::{Frame, WebSocket, TOO_LARGE};
+use crate::util::Json;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+
+// what spectators are told about a game, one json object per message:
+//
+//     {"event": "start", "size": 19, "komi": 6.5, "black": "", "white": "",
+//      "setup": ["dp", "pd"]}
+//     {"event": "next", "player": "black", "move": "dd"}
+//     {"event": "undo", "count": 1}
+//     {"event": "result", "result": "B+R"}
+//
+// moves are in sgf notation or pass, setup are the handicap stones, komi
+// is null when it is unknown.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    Start {
+        size: usize,
+        komi: Option<f64>,
+        black: String,
+        white: String,
+        setup: Vec<Cmd>,
+    },
+    Next(Player, Cmd),
+    Undo(usize),
+    Result(String),
+}
+
+impl GameEvent {
+    // events to make the game from an empty board
+    pub fn replay(g: &Game) -> Vec<GameEvent> {
+        let info = g.info();
+        let setup = g.handicap_stones();
+        let mut events = vec![GameEvent::Start {
+            size: g.board().lines(),
+            komi: info.komi,
+            black: info.black.clone(),
+            white: info.white.clone(),
+            setup: setup
+                .iter()
+                .map(|(x, y)| Cmd::Step(Cmd::point_to_cmd(*x, *y)))
+                .collect(),
+        }];
+        for (i, step) in g.steps().into_iter().enumerate() {
+            events.push(GameEvent::Next(g.player_of_step(i + 1), step));
+        }
+        if !info.result.is_empty() {
+            events.push(GameEvent::Result(info.result.clone()));
+        }
+        events
+    }
+
+    // the last step of the game
+    pub fn last(g: &Game) -> Option<GameEvent> {
+        let step = g.steps().pop()?;
+        Some(GameEvent::Next(g.player_of_step(g.step_count()), step))
+    }
+
+    pub fn apply(&self, g: &mut Game) -> Result<(), String> {
+        match self {
+            GameEvent::Start {
+                size,
+                komi,
+                black,
+                white,
+                setup,
+            } => {
+                let board_size = match size {
+                    9 => BoardSize::Small,
+                    13 => BoardSize::Medium,
+                    19 => BoardSize::Normal,
+                    other => return Err(format!("invalid board size: {}", other)),
+                };
+                let mut game = Game::new(board_size);
+                let points = setup
+                    .iter()
+                    .map(|p| Notation::Sgf.parse(&p.to_string(), *size))
+                    .collect::<Result<Vec<(usize, usize)>, String>>()?;
+                if !points.is_empty() {
+                    game.set_handicap_stones(&points)?;
+                }
+                if let Some(komi) = komi {
+                    game.set_info("komi", &komi.to_string())?;
+                }
+                game.set_info("black", black)?;
+                game.set_info("white", white)?;
+                *g = game;
+            }
+            GameEvent::Next(player, step) => {
+                if g.next_player() != *player {
+                    return Err(format!("not the turn of {}", player));
+                }
+                g.next(step.clone())?;
+            }
+            GameEvent::Undo(count) => {
+                for _ in 0..*count {
+                    g.undo()?;
+                }
+            }
+            GameEvent::Result(result) => g.set_info("result", result)?,
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Json {
+        match self {
+            GameEvent::Start {
+                size,
+                komi,
+                black,
+                white,
+                setup,
+            } => {
+                let setup: Vec<Json> = setup.iter().map(|p| p.to_string().into()).collect();
+                Json::object([
+                    ("event", "start".into()),
+                    ("size", (*size).into()),
+                    ("komi", (*komi).into()),
+                    ("black", black.clone().into()),
+                    ("white", white.clone().into()),
+                    ("setup", setup.into()),
+                ])
+            }
+            GameEvent::Next(player, step) => Json::object([
+                ("event", "next".into()),
+                ("player", player.to_string().into()),
+                ("move", step.to_string().into()),
+            ]),
+            GameEvent::Undo(count) => {
+                Json::object([("event", "undo".into()), ("count", (*count).into())])
+            }
+            GameEvent::Result(result) => Json::object([
+                ("event", "result".into()),
+                ("result", result.clone().into()),
+            ]),
+        }
+    }
+
+    pub fn from_json(json: &Json) -> Result<GameEvent, String> {
+        let invalid = || format!("invalid event: {}", json);
+        let text = |key: &str| {
+            json.get(key)
+                .and_then(|v| v.as_str())
+                .map(String::from)
+                .ok_or_else(invalid)
+        };
+        match text("event")?.as_str() {
+            "start" => Ok(GameEvent::Start {
+                size: json
+                    .get("size")
+                    .and_then(|s| s.as_usize())
+                    .ok_or_else(invalid)?,
+                komi: match json.get("komi") {
+                    None | Some(Json::Null) => None,
+                    Some(komi) => Some(komi.as_f64().ok_or_else(invalid)?),
+                },
+                black: text("black")?,
+                white: text("white")?,
+                setup: json
+                    .get("setup")
+                    .and_then(|s| s.as_array())
+                    .ok_or_else(invalid)?
+                    .iter()
+                    .map(|p| p.as_str().ok_or_else(invalid)?.parse())
+                    .collect::<Result<Vec<Cmd>, String>>()?,
+            }),
+            "next" => Ok(GameEvent::Next(
+                text("player")?.parse()?,
+                text("move")?.parse()?,
+            )),
+            "undo" => Ok(GameEvent::Undo(
+                json.get("count")
+                    .and_then(|c| c.as_usize())
+                    .ok_or_else(invalid)?,
+            )),
+            "result" => Ok(GameEvent::Result(text("result")?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// what the writer of a spectator sends
+enum Outgoing {
+    Text(String),
+    Pong(Vec<u8>),
+    // with the status code if any
+    Close(Option<u16>),
+}
+
+// websockets watching a game. each one is written by its own thread, so a
+// slow spectator does not hold up the game, and read by another which
+// answers pings and close. the ones whose threads have ended are dropped.
+#[derive(Default)]
+pub struct Spectators {
+    writers: Vec<Sender<Outgoing>>,
+}
+
+impl Spectators {
+    pub fn new() -> Spectators {
+        Spectators::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.writers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writers.is_empty()
+    }
+
+    // the game so far is replayed to the one joined late
+    pub fn join(&mut self, mut socket: WebSocket, g: &Game) {
+        let Ok(mut reader) = socket.try_clone() else {
+            return;
+        };
+        let (tx, rx) = channel();
+        for event in GameEvent::replay(g) {
+            let _ = tx.send(Outgoing::Text(event.to_json().to_string()));
+        }
+        thread::spawn(move || {
+            for out in rx {
+                let sent = match out {
+                    Outgoing::Text(text) => socket.send(&text),
+                    Outgoing::Pong(payload) => socket.pong(&payload),
+                    Outgoing::Close(status) => {
+                        match status {
+                            Some(status) => socket.close_with(status),
+                            None => socket.close(),
+                        }
+                        return;
+                    }
+                };
+                if sent.is_err() {
+                    return;
+                }
+            }
+        });
+        let pongs = tx.clone();
+        thread::spawn(move || loop {
+            let out = match reader.read_frame() {
+                Ok(Frame::Ping(payload)) => Outgoing::Pong(payload),
+                Err(err) if err == "message is too large" => Outgoing::Close(Some(TOO_LARGE)),
+                Ok(Frame::Close) | Err(_) => Outgoing::Close(None),
+                Ok(_) => continue,
+            };
+            let close = matches!(out, Outgoing::Close(_));
+            if pongs.send(out).is_err() || close {
+                return;
+            }
+        });
+        self.writers.push(tx);
+    }
+
+    pub fn broadcast(&mut self, event: &GameEvent) {
+        let text = event.to_json().to_string();
+        self.writers
+            .retain(|tx| tx.send(Outgoing::Text(text.clone())).is_ok());
+    }
+
+    pub fn close(&mut self) {
+        for tx in self.writers.drain(..) {
+            let _ = tx.send(Outgoing::Close(None));
+        }
+    }
+}
+
+// follows the game of a websocket
+pub struct Watcher {
+    socket: WebSocket,
+    game: Game,
+}
+
+impl Watcher {
+    pub fn connect(addr: &str, path: &str) -> Result<Watcher, String> {
+        Ok(Watcher {
+            socket: WebSocket::connect(addr, path)?,
+            game: Game::new(BoardSize::Normal),
+        })
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    // wait for the next event and apply it, none when the stream is over
+    pub fn next_event(&mut self) -> Result<Option<GameEvent>, String> {
+        let Some(text) = self.socket.receive()? else {
+            return Ok(None);
+        };
+        let event = GameEvent::from_json(&text.parse()?)?;
+        event.apply(&mut self.game)?;
+        Ok(Some(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_events() {
+        let mut g = Game::new(BoardSize::Small);
+        g.set_handicap(2).unwrap();
+        g.set_info("black", "Honinbo Shusaku").unwrap();
+        for step in ["cc", "ee", "pass"] {
+            g.next(step.parse().unwrap()).unwrap();
+        }
+        g.set_info("result", "B+R").unwrap();
+
+        let events = GameEvent::replay(&g);
+        assert!(events.len() == 5);
+        assert!(events[1] == GameEvent::Next(Player::White, Cmd::Step(String::from("cc"))));
+        let mut copy = Game::new(BoardSize::Normal);
+        for event in events {
+            let text = event.to_json().to_string();
+            let event = GameEvent::from_json(&text.parse().unwrap()).unwrap();
+            event.apply(&mut copy).unwrap();
+        }
+        assert!(copy.to_sgf() == g.to_sgf());
+        let wrong = GameEvent::Next(Player::White, Cmd::Pass);
+        assert!(wrong.apply(&mut copy).is_err());
+    }
+
+    #[test]
+    fn answer_spectators() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let client = thread::spawn(move || WebSocket::connect(&addr, "/").unwrap());
+        let (stream, _) = listener.accept().unwrap();
+        let request = crate::net::Request::read(&mut std::io::BufReader::new(&stream)).unwrap();
+        let mut spectators = Spectators::new();
+        spectators.join(
+            WebSocket::accept(stream, &request).unwrap(),
+            &Game::new(BoardSize::Small),
+        );
+        let mut client = client.join().unwrap();
+
+        assert!(matches!(client.read_frame(), Ok(Frame::Text(_))));
+        client.ping(b"hi").unwrap();
+        assert!(client.read_frame() == Ok(Frame::Pong));
+        // the spectator leaves, which is answered and ends its threads
+        client.close();
+        assert!(client.read_frame() == Ok(Frame::Close));
+        for _ in 0..100 {
+            spectators.broadcast(&GameEvent::Undo(1));
+            if spectators.is_empty() {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(spectators.is_empty());
+    }
+}
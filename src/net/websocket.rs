@@ -0,0 +1,360 @@
+use crate::net::{Request, Response};
+use crate::util::Rng;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+// rfc 6455, appended to the key of the client to make the accept key
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// writes to a peer which does not read fail after this, so a stalled one
+// can not block the server for long
+pub const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// a message larger than this, with all its fragments, is refused before it
+// is read, and the socket is closed with status 1009
+const MAX_MESSAGE: usize = 1 << 20;
+pub const TOO_LARGE: u16 = 1009;
+
+// a message of the other side, text messages are put together from their
+// fragments
+#[derive(Clone, Debug, PartialEq)]
+pub enum Frame {
+    Text(String),
+    Ping(Vec<u8>),
+    Pong,
+    Close,
+}
+
+// text messages over a websocket, frames sent by the client are masked
+pub struct WebSocket {
+    reader: BufReader<TcpStream>,
+    client: bool,
+    rng: Rng,
+}
+
+impl WebSocket {
+    // answer the upgrade request read from stream
+    pub fn accept(mut stream: TcpStream, request: &Request) -> Result<WebSocket, String> {
+        let upgrade = request.header("upgrade").unwrap_or("");
+        let key = request.header("sec-websocket-key");
+        let Some(key) = key.filter(|_| upgrade.eq_ignore_ascii_case("websocket")) else {
+            let response = Response::error(400, "websocket upgrade is expected");
+            let _ = response.write_to(&mut stream);
+            return Err(String::from("not a websocket request"));
+        };
+        let head = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(key)
+        );
+        stream
+            .set_write_timeout(Some(WRITE_TIMEOUT))
+            .and_then(|_| stream.write_all(head.as_bytes()))
+            .map_err(|err| format!("can not upgrade: {}", err))?;
+        Ok(WebSocket {
+            reader: BufReader::new(stream),
+            client: false,
+            rng: Rng::from_time(),
+        })
+    }
+
+    // the same websocket, to read in one thread and write in another
+    pub fn try_clone(&self) -> Result<WebSocket, String> {
+        let stream = self
+            .reader
+            .get_ref()
+            .try_clone()
+            .map_err(|err| err.to_string())?;
+        Ok(WebSocket {
+            reader: BufReader::new(stream),
+            client: self.client,
+            rng: self.rng.clone(),
+        })
+    }
+
+    // open a websocket to ws://addr/path
+    pub fn connect(addr: &str, path: &str) -> Result<WebSocket, String> {
+        let mut stream =
+            TcpStream::connect(addr).map_err(|err| format!("can not connect {}: {}", addr, err))?;
+        let mut rng = Rng::from_time();
+        let nonce: Vec<u8> = (0..16).map(|_| rng.below(256) as u8).collect();
+        let key = base64(&nonce);
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            path, addr, key
+        )
+        .map_err(|err| format!("can not send: {}", err))?;
+
+        let mut reader = BufReader::new(stream);
+        let mut status = String::new();
+        reader
+            .read_line(&mut status)
+            .map_err(|err| format!("can not read: {}", err))?;
+        let mut accept = None;
+        loop {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) if line.trim().is_empty() => break,
+                Ok(_) => {
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.trim().eq_ignore_ascii_case("sec-websocket-accept") {
+                            accept = Some(value.trim().to_string());
+                        }
+                    }
+                }
+                Err(err) => return Err(format!("can not read: {}", err)),
+            }
+        }
+        if !status.starts_with("HTTP/1.1 101") {
+            return Err(format!("upgrade refused: {}", status.trim()));
+        }
+        if accept != Some(accept_key(&key)) {
+            return Err(String::from("invalid accept key"));
+        }
+        Ok(WebSocket {
+            reader,
+            client: true,
+            rng,
+        })
+    }
+
+    pub fn send(&mut self, text: &str) -> Result<(), String> {
+        self.send_frame(0x1, text.as_bytes())
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), String> {
+        let mask_bit = if self.client { 0x80 } else { 0 };
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            n if n < 126 => frame.push(mask_bit | n as u8),
+            n if n < 1 << 16 => {
+                frame.push(mask_bit | 126);
+                frame.extend_from_slice(&(n as u16).to_be_bytes());
+            }
+            n => {
+                frame.push(mask_bit | 127);
+                frame.extend_from_slice(&(n as u64).to_be_bytes());
+            }
+        }
+        if self.client {
+            let mask = (self.rng.next_u64() as u32).to_be_bytes();
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        } else {
+            frame.extend_from_slice(payload);
+        }
+        let stream = self.reader.get_mut();
+        stream
+            .write_all(&frame)
+            .and_then(|_| stream.flush())
+            .map_err(|err| format!("can not send: {}", err))
+    }
+
+    // the next text message, none when the socket is closed. pings are
+    // answered while waiting.
+    pub fn receive(&mut self) -> Result<Option<String>, String> {
+        loop {
+            match self.read_frame() {
+                Ok(Frame::Text(text)) => return Ok(Some(text)),
+                Ok(Frame::Ping(payload)) => self.pong(&payload)?,
+                Ok(Frame::Pong) => {}
+                Ok(Frame::Close) => {
+                    self.close();
+                    return Ok(None);
+                }
+                Err(err) if err == "closed" => return Ok(None),
+                Err(err) if err == "message is too large" => {
+                    self.close_with(TOO_LARGE);
+                    return Err(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    // the next frame, which is not answered. the error is "closed" when
+    // the stream is over, and "message is too large" over MAX_MESSAGE, which
+    // should be answered by close_with(TOO_LARGE).
+    pub fn read_frame(&mut self) -> Result<Frame, String> {
+        let mut message = vec![];
+        loop {
+            let mut head = [0u8; 2];
+            if self.read(&mut head).is_err() {
+                return Err(String::from("closed"));
+            }
+            let (fin, opcode) = (head[0] & 0x80 != 0, head[0] & 0x0f);
+            let length = match head[1] & 0x7f {
+                126 => {
+                    let mut n = [0u8; 2];
+                    self.read(&mut n)?;
+                    u16::from_be_bytes(n) as usize
+                }
+                127 => {
+                    let mut n = [0u8; 8];
+                    self.read(&mut n)?;
+                    u64::from_be_bytes(n) as usize
+                }
+                n => n as usize,
+            };
+            // control frames are not fragmented and at most 125 bytes
+            let limit = if opcode & 0x8 != 0 {
+                125
+            } else {
+                MAX_MESSAGE - message.len()
+            };
+            if length > limit {
+                return Err(String::from("message is too large"));
+            }
+            let mut mask = [0u8; 4];
+            if head[1] & 0x80 != 0 {
+                self.read(&mut mask)?;
+            }
+            let mut payload = vec![0u8; length];
+            self.read(&mut payload)?;
+            for (i, b) in payload.iter_mut().enumerate() {
+                *b ^= mask[i % 4];
+            }
+            match opcode {
+                0x8 => return Ok(Frame::Close),
+                0x9 => return Ok(Frame::Ping(payload)),
+                0xa => return Ok(Frame::Pong),
+                _ => {
+                    message.extend_from_slice(&payload);
+                    if fin {
+                        return String::from_utf8(message)
+                            .map(Frame::Text)
+                            .map_err(|_| String::from("invalid text message"));
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn ping(&mut self, payload: &[u8]) -> Result<(), String> {
+        self.send_frame(0x9, payload)
+    }
+
+    pub fn pong(&mut self, payload: &[u8]) -> Result<(), String> {
+        self.send_frame(0xa, payload)
+    }
+
+    pub fn close(&mut self) {
+        let _ = self.send_frame(0x8, &[]);
+    }
+
+    // close with the status code, like TOO_LARGE
+    pub fn close_with(&mut self, status: u16) {
+        let _ = self.send_frame(0x8, &status.to_be_bytes());
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), String> {
+        self.reader
+            .read_exact(buf)
+            .map_err(|err| format!("can not read: {}", err))
+    }
+}
+
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, GUID).as_bytes()))
+}
+
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([
+                chunk[i * 4],
+                chunk[i * 4 + 1],
+                chunk[i * 4 + 2],
+                chunk[i * 4 + 3],
+            ]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (x, y) in h.iter_mut().zip([a, b, c, d, e]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+    let mut digest = [0u8; 20];
+    for (i, x) in h.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&x.to_be_bytes());
+    }
+    digest
+}
+
+pub fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().fold(0u32, |n, b| n << 8 | *b as u32) << (8 * (3 - chunk.len()));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(TABLE[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handshake_digest() {
+        let hex: String = sha1(b"abc").iter().map(|b| format!("{:02x}", b)).collect();
+        assert!(hex == "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert!(base64(b"").is_empty() && base64(b"f") == "Zg==" && base64(b"foob") == "Zm9vYg==");
+        assert!(base64(b"foobar") == "Zm9vYmFy");
+        assert!(accept_key("dGhlIHNhbXBsZSBub25jZQ==") == "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn refuse_large_messages() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let client = std::thread::spawn(move || WebSocket::connect(&addr, "/").unwrap());
+        let (stream, _) = listener.accept().unwrap();
+        let request = crate::net::Request::read(&mut BufReader::new(&stream)).unwrap();
+        let mut server = WebSocket::accept(stream, &request).unwrap();
+        let mut client = client.join().unwrap();
+
+        // a masked text frame of 2^62 bytes, which is not read
+        let head = [0x81, 0xff, 0x40, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4];
+        client.reader.get_mut().write_all(&head).unwrap();
+        assert!(server.receive().unwrap_err() == "message is too large");
+        assert!(client.read_frame() == Ok(Frame::Close));
+    }
+}
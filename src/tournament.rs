@@ -0,0 +1,9 @@
+mod event;
+mod pairing;
+mod rank;
+mod rating;
+
+pub use event::*;
+pub use pairing::*;
+pub use rank::*;
+pub use rating::*;
@@ -0,0 +1,603 @@
+use crate::basic::*;
+use crate::tournament::*;
+use std::fs;
+
+// tournament file:
+//
+//     rustgo/tournament
+//     version 1
+//     name <name>
+//     system round-robin|swiss|mcmahon
+//     rounds <count>
+//     size <lines>
+//     komi <komi>
+//     handicap yes|no
+//     bar <rank>
+//     rated yes|no
+//     player <rank> <name>
+//     round
+//     game <black> <white> <handicap> <komi> <result>
+//     bye <player>
+//
+// players are numbers from 0 in the order of player lines, the games and
+// the bye after a round line belong to it. result is - until known.
+const TOURNAMENT_MAGIC: &str = "rustgo/tournament";
+const TOURNAMENT_VERSION: usize = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entrant {
+    pub name: String,
+    pub rank: Rank,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pairing {
+    pub black: usize,
+    pub white: usize,
+    pub handicap: usize,
+    pub komi: f64,
+    // like B+R, W+3.5, 0 for draw
+    pub result: Option<String>,
+}
+
+impl Pairing {
+    // points of black and white, none until the result is known
+    pub fn points(&self) -> Option<(f64, f64)> {
+        match self.result.as_deref()?.chars().next() {
+            Some('B') => Some((1.0, 0.0)),
+            Some('W') => Some((0.0, 1.0)),
+            _ => Some((0.5, 0.5)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Round {
+    pub games: Vec<Pairing>,
+    // the player left without opponent, who gets a point
+    pub bye: Option<usize>,
+}
+
+// a row of the standings, sos is the sum of the opponents' scores and
+// sodos the sum of the scores of the opponents beaten
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standing {
+    pub player: usize,
+    pub score: f64,
+    pub sos: f64,
+    pub sodos: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tournament {
+    pub name: String,
+    pub system: System,
+    pub rounds: usize,
+    pub size: BoardSize,
+    pub komi: f64,
+    // stones are given by the rank difference if set
+    pub handicap: bool,
+    // mcmahon scores of players at or above the bar start from 0, the top
+    // rank if none
+    pub bar: Option<Rank>,
+    // the games are in the ratings already
+    rated: bool,
+    players: Vec<Entrant>,
+    played: Vec<Round>,
+}
+
+impl Tournament {
+    pub fn new(name: &str, system: System, rounds: usize) -> Tournament {
+        Tournament {
+            name: name.to_string(),
+            system,
+            rounds,
+            size: BoardSize::Normal,
+            komi: 6.5,
+            handicap: false,
+            bar: None,
+            rated: false,
+            players: vec![],
+            played: vec![],
+        }
+    }
+
+    pub fn players(&self) -> &[Entrant] {
+        &self.players
+    }
+
+    // players can not join after the first round is paired
+    pub fn add_player(&mut self, name: &str, rank: Rank) -> Result<(), String> {
+        if !self.played.is_empty() {
+            return Err(String::from("the tournament has started"));
+        }
+        if name.is_empty() || self.find(name).is_some() {
+            return Err(format!("invalid player name: {}", name));
+        }
+        self.players.push(Entrant {
+            name: name.to_string(),
+            rank,
+        });
+        Ok(())
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.players.iter().position(|p| p.name == name)
+    }
+
+    // rounds paired so far
+    pub fn played(&self) -> &[Round] {
+        &self.played
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.played.len() >= self.total_rounds() && self.is_round_done()
+    }
+
+    // round-robin rounds follow from the count of players
+    pub fn total_rounds(&self) -> usize {
+        match self.system {
+            System::RoundRobin => round_robin_rounds(self.players.len()),
+            _ => self.rounds,
+        }
+    }
+
+    fn is_round_done(&self) -> bool {
+        self.played
+            .last()
+            .is_none_or(|r| r.games.iter().all(|g| g.result.is_some()))
+    }
+
+    // pair the next round once every game of the last one has a result
+    pub fn pair_round(&mut self) -> Result<&Round, String> {
+        if self.players.len() < 2 {
+            return Err(String::from("at least 2 players are needed"));
+        }
+        if !self.is_round_done() {
+            return Err(String::from("results of the last round are missing"));
+        }
+        if self.played.len() >= self.total_rounds() {
+            return Err(String::from("all rounds are played"));
+        }
+        let (pairs, bye) = match self.system {
+            System::RoundRobin => {
+                let mut pairs = vec![];
+                let mut bye = None;
+                for (a, b) in round_robin(self.players.len(), self.played.len()) {
+                    match b {
+                        Some(b) => pairs.push((a, b)),
+                        None => bye = Some(a),
+                    }
+                }
+                (pairs, bye)
+            }
+            System::Swiss | System::McMahon => self.pair_by_score(),
+        };
+        let games = pairs.iter().map(|(a, b)| self.colours(*a, *b)).collect();
+        self.played.push(Round { games, bye });
+        Ok(self.played.last().unwrap())
+    }
+
+    // the lowest one in the order who has had the fewest byes sits out if
+    // the count is odd
+    fn pair_by_score(&self) -> (Vec<(usize, usize)>, Option<usize>) {
+        let scores = self.scores();
+        let mut order: Vec<usize> = (0..self.players.len()).collect();
+        order.sort_by(|a, b| {
+            scores[*b]
+                .total_cmp(&scores[*a])
+                .then(self.players[*b].rank.cmp(&self.players[*a].rank))
+        });
+        let mut bye = None;
+        if order.len() % 2 == 1 {
+            let byes = |p: usize| self.played.iter().filter(|r| r.bye == Some(p)).count();
+            let fewest = order.iter().map(|p| byes(*p)).min().unwrap_or(0);
+            let i = order.iter().rposition(|p| byes(*p) == fewest).unwrap();
+            bye = Some(order.remove(i));
+        }
+        let played = |a: usize, b: usize| self.opponents(a).contains(&b);
+        (swiss(&order, &played), bye)
+    }
+
+    // with handicap the weaker one takes black. otherwise the one who had
+    // black less often does, then the one who had white last, then the
+    // weaker one.
+    fn colours(&self, a: usize, b: usize) -> Pairing {
+        let (rank_a, rank_b) = (self.players[a].rank, self.players[b].rank);
+        let diff = rank_a.value().abs_diff(rank_b.value()) as usize;
+        let (handicap, komi) = if self.handicap {
+            handicap(diff, self.komi)
+        } else {
+            (0, self.komi)
+        };
+        let a_black = if handicap > 0 || komi != self.komi {
+            rank_a < rank_b
+        } else {
+            let prefer = [
+                self.colour_balance(b) - self.colour_balance(a),
+                self.last_white(a) as i32 - self.last_white(b) as i32,
+                rank_b.value() - rank_a.value(),
+            ];
+            prefer.iter().find(|v| **v != 0).map_or(a < b, |v| *v > 0)
+        };
+        let (black, white) = if a_black { (a, b) } else { (b, a) };
+        Pairing {
+            black,
+            white,
+            handicap,
+            komi,
+            result: None,
+        }
+    }
+
+    // games as black minus games as white
+    fn colour_balance(&self, player: usize) -> i32 {
+        self.played
+            .iter()
+            .flat_map(|r| r.games.iter())
+            .map(|g| (g.black == player) as i32 - (g.white == player) as i32)
+            .sum()
+    }
+
+    fn last_white(&self, player: usize) -> bool {
+        self.played
+            .iter()
+            .rev()
+            .flat_map(|r| r.games.iter())
+            .find(|g| g.black == player || g.white == player)
+            .is_some_and(|g| g.white == player)
+    }
+
+    fn opponents(&self, player: usize) -> Vec<usize> {
+        self.played
+            .iter()
+            .flat_map(|r| r.games.iter())
+            .filter_map(|g| {
+                if g.black == player {
+                    Some(g.white)
+                } else if g.white == player {
+                    Some(g.black)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // mcmahon score the player starts with
+    fn start_score(&self, player: usize) -> f64 {
+        if self.system != System::McMahon {
+            return 0.0;
+        }
+        let top = self.players.iter().map(|p| p.rank).max().unwrap();
+        let bar = self.bar.unwrap_or(top);
+        (self.players[player].rank.min(bar).value() - bar.value()) as f64
+    }
+
+    // points of wins, draws and byes, from the start score for mcmahon
+    pub fn scores(&self) -> Vec<f64> {
+        let mut scores: Vec<f64> = (0..self.players.len())
+            .map(|p| self.start_score(p))
+            .collect();
+        for round in self.played.iter() {
+            if let Some(p) = round.bye {
+                scores[p] += 1.0;
+            }
+            for g in round.games.iter() {
+                if let Some((black, white)) = g.points() {
+                    scores[g.black] += black;
+                    scores[g.white] += white;
+                }
+            }
+        }
+        scores
+    }
+
+    // players from the best by score, sos and sodos
+    pub fn standings(&self) -> Vec<Standing> {
+        let scores = self.scores();
+        let mut standings: Vec<Standing> = (0..self.players.len())
+            .map(|p| Standing {
+                player: p,
+                score: scores[p],
+                sos: 0.0,
+                sodos: 0.0,
+            })
+            .collect();
+        for g in self.played.iter().flat_map(|r| r.games.iter()) {
+            if let Some((black, white)) = g.points() {
+                standings[g.black].sos += scores[g.white];
+                standings[g.white].sos += scores[g.black];
+                standings[g.black].sodos += black * scores[g.white];
+                standings[g.white].sodos += white * scores[g.black];
+            }
+        }
+        standings.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(b.sos.total_cmp(&a.sos))
+                .then(b.sodos.total_cmp(&a.sodos))
+        });
+        standings
+    }
+
+    // record the result of the game between the players in the round from 1
+    pub fn record(
+        &mut self,
+        round: usize,
+        black: &str,
+        white: &str,
+        result: &str,
+    ) -> Result<(), String> {
+        let (black, white) = match (self.find(black), self.find(white)) {
+            (Some(black), Some(white)) => (black, white),
+            _ => return Err(format!("unknown players: {} and {}", black, white)),
+        };
+        if !result.starts_with(['B', 'W', '0']) && result != "Draw" {
+            return Err(format!("invalid result: {}", result));
+        }
+        let game = self
+            .played
+            .get_mut(round.wrapping_sub(1))
+            .and_then(|r| {
+                r.games
+                    .iter_mut()
+                    .find(|g| g.black == black && g.white == white)
+            })
+            .ok_or_else(|| format!("no such game in round {}", round))?;
+        game.result = Some(result.to_string());
+        Ok(())
+    }
+
+    // record the result of a game by its info, the round is the last one
+    // unless the game tells it
+    pub fn record_game(&mut self, g: &Game) -> Result<(), String> {
+        let info = g.info();
+        let round = info.round.parse().unwrap_or(self.played.len());
+        if info.result.is_empty() {
+            return Err(format!("the game of round {} has no result", round));
+        }
+        self.record(round, &info.black, &info.white, &info.result)
+    }
+
+    // a new game of the pairing with the players, ranks and handicap set
+    pub fn new_game(&self, round: usize, board: usize) -> Result<Game, String> {
+        let pairing = self
+            .played
+            .get(round.wrapping_sub(1))
+            .and_then(|r| r.games.get(board.wrapping_sub(1)))
+            .ok_or_else(|| format!("no board {} in round {}", board, round))?;
+        let (black, white) = (&self.players[pairing.black], &self.players[pairing.white]);
+        let mut g = Game::new(self.size.clone());
+        if pairing.handicap > 1 {
+            g.set_handicap(pairing.handicap)?;
+        }
+        g.set_info("black", &black.name)?;
+        g.set_info("black-rank", &black.rank.to_string())?;
+        g.set_info("white", &white.name)?;
+        g.set_info("white-rank", &white.rank.to_string())?;
+        g.set_info("komi", &pairing.komi.to_string())?;
+        g.set_info("event", &self.name)?;
+        g.set_info("round", &round.to_string())?;
+        Ok(g)
+    }
+
+    // add the games to the ratings once the tournament is over, which can
+    // only be done once
+    pub fn rate(&mut self, ratings: &mut Ratings) -> Result<(), String> {
+        if !self.is_over() {
+            return Err(String::from("the tournament is not over"));
+        }
+        if self.rated {
+            return Err(String::from("the tournament is rated already"));
+        }
+        ratings.add_event(&self.game_scores());
+        self.rated = true;
+        Ok(())
+    }
+
+    // every game with a result, for ratings
+    pub fn game_scores(&self) -> Vec<GameScore> {
+        self.played
+            .iter()
+            .flat_map(|r| r.games.iter())
+            .filter_map(|g| {
+                g.points().map(|(black, _)| GameScore {
+                    first: self.players[g.black].name.clone(),
+                    second: self.players[g.white].name.clone(),
+                    score: black,
+                })
+            })
+            .collect()
+    }
+
+    pub fn load(filename: &str) -> Result<Tournament, String> {
+        let data = fs::read_to_string(filename)
+            .map_err(|err| format!("can not read {}: {}", filename, err))?;
+        parse_tournament(&data).map_err(|err| format!("invalid tournament {}, {}", filename, err))
+    }
+
+    pub fn save(&self, filename: &str) -> Result<(), String> {
+        let mut data = format!("{}\nversion {}\n", TOURNAMENT_MAGIC, TOURNAMENT_VERSION);
+        data.push_str(&format!("name {}\n", self.name));
+        data.push_str(&format!("system {}\n", self.system));
+        data.push_str(&format!("rounds {}\n", self.rounds));
+        data.push_str(&format!("size {}\n", self.size.lines()));
+        data.push_str(&format!("komi {}\n", self.komi));
+        let handicap = if self.handicap { "yes" } else { "no" };
+        data.push_str(&format!("handicap {}\n", handicap));
+        if let Some(bar) = self.bar {
+            data.push_str(&format!("bar {}\n", bar));
+        }
+        let rated = if self.rated { "yes" } else { "no" };
+        data.push_str(&format!("rated {}\n", rated));
+        for p in self.players.iter() {
+            data.push_str(&format!("player {} {}\n", p.rank, p.name));
+        }
+        for round in self.played.iter() {
+            data.push_str("round\n");
+            for g in round.games.iter() {
+                data.push_str(&format!(
+                    "game {} {} {} {} {}\n",
+                    g.black,
+                    g.white,
+                    g.handicap,
+                    g.komi,
+                    g.result.as_deref().unwrap_or("-")
+                ));
+            }
+            if let Some(p) = round.bye {
+                data.push_str(&format!("bye {}\n", p));
+            }
+        }
+        fs::write(filename, data).map_err(|err| format!("can not write {}: {}", filename, err))
+    }
+}
+
+fn parse_tournament(data: &str) -> Result<Tournament, String> {
+    let mut lines = data.lines().enumerate();
+    if lines.next().map(|(_, l)| l) != Some(TOURNAMENT_MAGIC) {
+        return Err(String::from("line 1: not a rustgo tournament"));
+    }
+    let mut t = Tournament::new("", System::Swiss, 0);
+    for (n, line) in lines {
+        let at = |err: String| format!("line {}: {}", n + 1, err);
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        let number = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| at(format!("invalid number {}", s)))
+        };
+        let player = |s: &str| {
+            number(s).and_then(|p| {
+                if p < t.players.len() {
+                    Ok(p)
+                } else {
+                    Err(at(format!("unknown player {}", p)))
+                }
+            })
+        };
+        match key {
+            "version" => {
+                if number(value)? > TOURNAMENT_VERSION {
+                    return Err(at(format!("unsupported version {}", value)));
+                }
+            }
+            "name" => t.name = value.to_string(),
+            "system" => t.system = value.parse().map_err(at)?,
+            "rounds" => t.rounds = number(value)?,
+            "size" => {
+                t.size = match value {
+                    "19" => BoardSize::Normal,
+                    "13" => BoardSize::Medium,
+                    "9" => BoardSize::Small,
+                    other => return Err(at(format!("invalid board size {}", other))),
+                }
+            }
+            "komi" => {
+                t.komi = value
+                    .parse()
+                    .map_err(|_| at(format!("invalid komi {}", value)))?
+            }
+            "handicap" => t.handicap = value == "yes",
+            "bar" => t.bar = Some(value.parse().map_err(at)?),
+            "rated" => t.rated = value == "yes",
+            "player" => {
+                let (rank, name) = value.split_once(' ').unwrap_or((value, ""));
+                let rank = rank.parse().map_err(at)?;
+                t.players.push(Entrant {
+                    name: name.to_string(),
+                    rank,
+                });
+            }
+            "round" => t.played.push(Round::default()),
+            "game" | "bye" if t.played.is_empty() => {
+                return Err(at(format!("{} before round", key)))
+            }
+            "game" => {
+                let fields: Vec<&str> = value.split(' ').collect();
+                if fields.len() != 5 {
+                    return Err(at(format!("invalid game {}", value)));
+                }
+                let game = Pairing {
+                    black: player(fields[0])?,
+                    white: player(fields[1])?,
+                    handicap: number(fields[2])?,
+                    komi: fields[3]
+                        .parse()
+                        .map_err(|_| at(format!("invalid komi {}", fields[3])))?,
+                    result: Some(fields[4].to_string()).filter(|r| r != "-"),
+                };
+                t.played.last_mut().unwrap().games.push(game);
+            }
+            "bye" => t.played.last_mut().unwrap().bye = Some(player(value)?),
+            "" => {}
+            other => return Err(at(format!("unknown line {}", other))),
+        }
+    }
+    Ok(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn mcmahon_with_handicap() {
+        let mut t = Tournament::new("club cup", System::McMahon, 3);
+        t.handicap = true;
+        t.bar = Some("1d".parse().unwrap());
+        for (name, rank) in [
+            ("Ann", "2d"),
+            ("Bob", "1d"),
+            ("Cy", "1k"),
+            ("Di", "3k"),
+            ("Ed", "5k"),
+        ] {
+            t.add_player(name, rank.parse().unwrap()).unwrap();
+        }
+        // ann and bob start at 0 above the bar, ed at -5
+        assert!(t.scores() == vec![0.0, 0.0, -1.0, -3.0, -5.0]);
+
+        let round = t.pair_round().unwrap().clone();
+        assert!(round.bye == Some(4) && round.games.len() == 2);
+        // 2d against 1d is played with komi 0.5, 1d at black
+        let ann = &round.games[0];
+        assert!((ann.black, ann.white, ann.handicap, ann.komi) == (1, 0, 0, 0.5));
+        let cy = &round.games[1];
+        assert!((cy.black, cy.white, cy.handicap, cy.komi) == (3, 2, 2, 0.5));
+        assert!(t.pair_round().is_err());
+        let mut g = t.new_game(1, 2).unwrap();
+        assert!(g.info().handicap == 2 && g.info().event == "club cup");
+        g.set_info("result", "W+R").unwrap();
+        t.record_game(&g).unwrap();
+        t.record(1, "Bob", "Ann", "B+3.5").unwrap();
+
+        // ed had the bye, di lost and cy won
+        let round = t.pair_round().unwrap().clone();
+        assert!(round.bye == Some(3));
+        let standings = t.standings();
+        assert!(standings[0].player == 1 && standings[0].sodos == 0.0);
+        assert!(standings[1].player == 0 && standings[1].sos == 1.0);
+
+        let dir = TempDir::new("tournament");
+        let filename = dir.file("tournament");
+        t.save(&filename).unwrap();
+        let loaded = Tournament::load(&filename).unwrap();
+        assert!(loaded == t && loaded.game_scores().len() == 2);
+        assert!(t.rate(&mut Ratings::new()).is_err());
+
+        // an event is rated once, which is kept in the file
+        let mut duel = Tournament::new("duel", System::RoundRobin, 0);
+        duel.add_player("Ann", "2d".parse().unwrap()).unwrap();
+        duel.add_player("Bob", "1d".parse().unwrap()).unwrap();
+        duel.pair_round().unwrap();
+        duel.record(1, "Bob", "Ann", "B+R").unwrap();
+        let mut ratings = Ratings::new();
+        duel.rate(&mut ratings).unwrap();
+        assert!(ratings.get("Ann").games == 1);
+        duel.save(&filename).unwrap();
+        let mut loaded = Tournament::load(&filename).unwrap();
+        assert!(loaded.rate(&mut ratings).is_err() && ratings.get("Ann").games == 1);
+    }
+}
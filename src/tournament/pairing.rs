@@ -0,0 +1,137 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum System {
+    // everyone plays everyone once
+    RoundRobin,
+    // players of the same score meet, never twice if it can be helped
+    Swiss,
+    // swiss with scores starting from the rank, players at or above the
+    // bar start the same
+    McMahon,
+}
+
+impl FromStr for System {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(System::RoundRobin),
+            "swiss" => Ok(System::Swiss),
+            "mcmahon" => Ok(System::McMahon),
+            other => Err(format!("invalid system: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for System {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            System::RoundRobin => write!(f, "round-robin"),
+            System::Swiss => write!(f, "swiss"),
+            System::McMahon => write!(f, "mcmahon"),
+        }
+    }
+}
+
+// count of rounds for everyone to meet everyone
+pub fn round_robin_rounds(players: usize) -> usize {
+    (players + players % 2).saturating_sub(1)
+}
+
+// pairs of the round from 0 by the circle method, the last player stays and
+// the others turn around. a player without opponent gets none, which is
+// the bye.
+pub fn round_robin(players: usize, round: usize) -> Vec<(usize, Option<usize>)> {
+    let count = players + players % 2;
+    if count < 2 {
+        return (0..players).map(|p| (p, None)).collect();
+    }
+    let turning = count - 1;
+    let at = |i: usize| (round + i) % turning;
+    let mut pairs = vec![(turning, at(0))];
+    for i in 1..count / 2 {
+        pairs.push((at(i), at(turning - i)));
+    }
+    pairs
+        .into_iter()
+        .map(|(a, b)| match (a < players, b < players) {
+            (true, true) => (a, Some(b)),
+            (true, false) => (a, None),
+            _ => (b, None),
+        })
+        .collect()
+}
+
+// pairs of players in order from the best, each one meets the best one
+// left who is not met before. rematches are made only if there is no way
+// around them. the search tries every order, which is fine for the size of
+// a club.
+pub fn swiss(order: &[usize], played: &dyn Fn(usize, usize) -> bool) -> Vec<(usize, usize)> {
+    let mut used = vec![false; order.len()];
+    let mut pairs = vec![];
+    if !pair_rest(order, &mut used, played, &mut pairs) {
+        pairs = order.chunks(2).map(|c| (c[0], c[c.len() - 1])).collect();
+    }
+    pairs
+}
+
+fn pair_rest(
+    order: &[usize],
+    used: &mut [bool],
+    played: &dyn Fn(usize, usize) -> bool,
+    pairs: &mut Vec<(usize, usize)>,
+) -> bool {
+    let first = match (0..order.len()).find(|i| !used[*i]) {
+        Some(first) => first,
+        None => return true,
+    };
+    used[first] = true;
+    for second in first + 1..order.len() {
+        if used[second] || played(order[first], order[second]) {
+            continue;
+        }
+        used[second] = true;
+        pairs.push((order[first], order[second]));
+        if pair_rest(order, used, played, pairs) {
+            return true;
+        }
+        pairs.pop();
+        used[second] = false;
+    }
+    used[first] = false;
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn everyone_meets_once() {
+        for players in [4, 5] {
+            let mut met = vec![];
+            let mut byes = vec![];
+            for round in 0..round_robin_rounds(players) {
+                for (a, b) in round_robin(players, round) {
+                    match b {
+                        Some(b) => met.push((a.min(b), a.max(b))),
+                        None => byes.push(a),
+                    }
+                }
+            }
+            met.sort();
+            met.dedup();
+            assert!(met.len() == players * (players - 1) / 2);
+            assert!(byes.len() == players % 2 * players);
+        }
+
+        // 0 and 1 met, so did 2 and 3
+        let played = |a: usize, b: usize| a / 2 == b / 2;
+        assert!(swiss(&[0, 1, 2, 3], &played) == vec![(0, 2), (1, 3)]);
+        // 0 met everyone but 3, which takes the others apart
+        let played = |a: usize, b: usize| a.min(b) == 0 && a.max(b) != 3;
+        assert!(swiss(&[0, 1, 2, 3], &played) == vec![(0, 3), (1, 2)]);
+    }
+}
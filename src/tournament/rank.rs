@@ -0,0 +1,82 @@
+use std::fmt;
+use std::str::FromStr;
+
+// kyu ranks 30k to 1k and dan ranks 1d to 9d, 1d is one rank above 1k
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Rank(i32);
+
+impl Rank {
+    // 1d is 1, 1k is 0, 30k is -29
+    pub fn value(&self) -> i32 {
+        self.0
+    }
+
+    pub fn from_value(value: i32) -> Rank {
+        Rank(value.clamp(-29, 9))
+    }
+}
+
+impl FromStr for Rank {
+    type Err = String;
+
+    // like 5k or 3d, in any case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_lowercase();
+        let invalid = || format!("invalid rank: {}", s);
+        let number = |n: &str| n.parse::<i32>().map_err(|_| invalid());
+        if let Some(kyu) = lower.strip_suffix('k') {
+            let kyu = number(kyu)?;
+            if (1..=30).contains(&kyu) {
+                return Ok(Rank(1 - kyu));
+            }
+        } else if let Some(dan) = lower.strip_suffix('d') {
+            let dan = number(dan)?;
+            if (1..=9).contains(&dan) {
+                return Ok(Rank(dan));
+            }
+        }
+        Err(invalid())
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 > 0 {
+            write!(f, "{}d", self.0)
+        } else {
+            write!(f, "{}k", 1 - self.0)
+        }
+    }
+}
+
+// stones and komi of a game between players of the rank difference, the
+// weaker one takes black. one rank is given by komi 0.5 without stones,
+// more by a stone for each rank up to 9
+pub fn handicap(diff: usize, komi: f64) -> (usize, f64) {
+    match diff {
+        0 => (0, komi),
+        1 => (0, 0.5),
+        diff => (diff.min(9), 0.5),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_and_handicap() {
+        let ranks: Vec<Rank> = ["30k", "1K", "1d", "9d"]
+            .iter()
+            .map(|r| r.parse().unwrap())
+            .collect();
+        assert!(ranks.windows(2).all(|w| w[0] < w[1]));
+        assert!(ranks[2].value() - ranks[1].value() == 1);
+        assert!(ranks[0].to_string() == "30k" && ranks[1].to_string() == "1k");
+        for bad in ["0k", "3x", "3段", "", "k"] {
+            assert!(bad.parse::<Rank>().is_err());
+        }
+        assert!(handicap(0, 6.5) == (0, 6.5) && handicap(1, 6.5) == (0, 0.5));
+        assert!(handicap(4, 6.5) == (4, 0.5) && handicap(12, 6.5) == (9, 0.5));
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::f64::consts::{LN_10, PI};
+use std::fs;
+use std::path::Path;
+
+// ratings file:
+//
+//     rustgo/ratings
+//     version 1
+//     player <elo> <glicko> <deviation> <games> <name>
+const RATINGS_MAGIC: &str = "rustgo/ratings";
+const RATINGS_VERSION: usize = 1;
+
+const START_RATING: f64 = 1500.0;
+// deviation of a new player, which is the most uncertain
+const START_DEVIATION: f64 = 350.0;
+// the deviation grows by this in each event as the rating gets old
+const DEVIATION_GROWTH: f64 = 35.0;
+const ELO_K: f64 = 32.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rating {
+    pub elo: f64,
+    pub glicko: f64,
+    pub deviation: f64,
+    pub games: usize,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating {
+            elo: START_RATING,
+            glicko: START_RATING,
+            deviation: START_DEVIATION,
+            games: 0,
+        }
+    }
+}
+
+// a game of an event, score is 1 if the first one won, 0.5 for a draw
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameScore {
+    pub first: String,
+    pub second: String,
+    pub score: f64,
+}
+
+// ratings of players kept across events
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ratings {
+    players: BTreeMap<String, Rating>,
+}
+
+impl Ratings {
+    pub fn new() -> Ratings {
+        Ratings::default()
+    }
+
+    // no one is rated if the file does not exist yet
+    pub fn load(filename: &str) -> Result<Ratings, String> {
+        if !Path::new(filename).exists() {
+            return Ok(Ratings::new());
+        }
+        let data = fs::read_to_string(filename)
+            .map_err(|err| format!("can not read {}: {}", filename, err))?;
+        parse_ratings(&data).map_err(|err| format!("invalid ratings {}, {}", filename, err))
+    }
+
+    pub fn save(&self, filename: &str) -> Result<(), String> {
+        let mut data = format!("{}\nversion {}\n", RATINGS_MAGIC, RATINGS_VERSION);
+        for (name, r) in self.players.iter() {
+            data.push_str(&format!(
+                "player {:.1} {:.1} {:.1} {} {}\n",
+                r.elo, r.glicko, r.deviation, r.games, name
+            ));
+        }
+        fs::write(filename, data).map_err(|err| format!("can not write {}: {}", filename, err))
+    }
+
+    pub fn get(&self, name: &str) -> Rating {
+        self.players.get(name).cloned().unwrap_or_default()
+    }
+
+    // players from the highest glicko rating
+    pub fn ranking(&self) -> Vec<(&str, &Rating)> {
+        let mut ranking: Vec<(&str, &Rating)> = self
+            .players
+            .iter()
+            .map(|(name, r)| (name.as_str(), r))
+            .collect();
+        ranking.sort_by(|a, b| b.1.glicko.total_cmp(&a.1.glicko));
+        ranking
+    }
+
+    // rate the games of an event together, every game is rated by the
+    // ratings before the event
+    pub fn add_event(&mut self, games: &[GameScore]) {
+        let mut names: Vec<&str> = games
+            .iter()
+            .flat_map(|g| [g.first.as_str(), g.second.as_str()])
+            .collect();
+        names.sort();
+        names.dedup();
+        let mut updated = vec![];
+        for name in names {
+            let before = self.get(name);
+            // every game seen from the player
+            let results: Vec<(Rating, f64)> = games
+                .iter()
+                .filter_map(|g| {
+                    if g.first == name {
+                        Some((self.get(&g.second), g.score))
+                    } else if g.second == name {
+                        Some((self.get(&g.first), 1.0 - g.score))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            let mut after = before.clone();
+            after.elo += results
+                .iter()
+                .map(|(o, score)| ELO_K * (score - elo_expected(before.elo, o.elo)))
+                .sum::<f64>();
+            let deviation = (before.deviation.powi(2) + DEVIATION_GROWTH.powi(2))
+                .sqrt()
+                .min(START_DEVIATION);
+            (after.glicko, after.deviation) = glicko(before.glicko, deviation, &results);
+            after.games += results.len();
+            updated.push((name.to_string(), after));
+        }
+        self.players.extend(updated);
+    }
+}
+
+// chance to win against the opponent
+pub fn elo_expected(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+// glicko rating and deviation after the games of a rating period
+fn glicko(rating: f64, deviation: f64, results: &[(Rating, f64)]) -> (f64, f64) {
+    let q = LN_10 / 400.0;
+    let g = |d: f64| 1.0 / (1.0 + 3.0 * q * q * d * d / (PI * PI)).sqrt();
+    let expected =
+        |o: &Rating| 1.0 / (1.0 + 10f64.powf(-g(o.deviation) * (rating - o.glicko) / 400.0));
+    let d2 = 1.0
+        / (q * q
+            * results
+                .iter()
+                .map(|(o, _)| g(o.deviation).powi(2) * expected(o) * (1.0 - expected(o)))
+                .sum::<f64>());
+    let precision = 1.0 / (deviation * deviation) + 1.0 / d2;
+    let change = q / precision
+        * results
+            .iter()
+            .map(|(o, score)| g(o.deviation) * (score - expected(o)))
+            .sum::<f64>();
+    (rating + change, (1.0 / precision).sqrt())
+}
+
+fn parse_ratings(data: &str) -> Result<Ratings, String> {
+    let mut lines = data.lines().enumerate();
+    if lines.next().map(|(_, l)| l) != Some(RATINGS_MAGIC) {
+        return Err(String::from("line 1: not rustgo ratings"));
+    }
+    let mut ratings = Ratings::new();
+    for (n, line) in lines {
+        let at = |err: String| format!("line {}: {}", n + 1, err);
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "version" => {
+                let version: usize = value
+                    .parse()
+                    .map_err(|_| at(format!("invalid version {}", value)))?;
+                if version > RATINGS_VERSION {
+                    return Err(at(format!("unsupported version {}", version)));
+                }
+            }
+            "player" => {
+                let fields: Vec<&str> = value.splitn(5, ' ').collect();
+                if fields.len() < 5 {
+                    return Err(at(format!("invalid player {}", value)));
+                }
+                let number = |s: &str| {
+                    s.parse::<f64>()
+                        .map_err(|_| at(format!("invalid number {}", s)))
+                };
+                let rating = Rating {
+                    elo: number(fields[0])?,
+                    glicko: number(fields[1])?,
+                    deviation: number(fields[2])?,
+                    games: number(fields[3])? as usize,
+                };
+                ratings.players.insert(fields[4].to_string(), rating);
+            }
+            "" => {}
+            other => return Err(at(format!("unknown line {}", other))),
+        }
+    }
+    Ok(ratings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TempDir;
+
+    #[test]
+    fn glickman_example() {
+        // the example of glickman's paper
+        let opponent = |glicko, deviation| Rating {
+            glicko,
+            deviation,
+            ..Rating::default()
+        };
+        let results = [
+            (opponent(1400.0, 30.0), 1.0),
+            (opponent(1550.0, 100.0), 0.0),
+            (opponent(1700.0, 300.0), 0.0),
+        ];
+        let (rating, deviation) = glicko(1500.0, 200.0, &results);
+        assert!((rating - 1464.1).abs() < 0.5 && (deviation - 151.4).abs() < 0.5);
+
+        let mut ratings = Ratings::new();
+        let game = |first: &str, second: &str, score| GameScore {
+            first: first.to_string(),
+            second: second.to_string(),
+            score,
+        };
+        ratings.add_event(&[game("Ann", "Bob", 1.0), game("Bob", "Cy Young", 0.5)]);
+        let (ann, bob) = (ratings.get("Ann"), ratings.get("Bob"));
+        assert!(ann.elo == 1516.0 && bob.elo == 1484.0 && bob.games == 2);
+        assert!(ann.glicko > 1500.0 && ann.deviation < START_DEVIATION);
+        let dir = TempDir::new("ratings");
+        let filename = dir.file("ratings");
+        ratings.save(&filename).unwrap();
+        let loaded = Ratings::load(&filename).unwrap();
+        assert!(loaded.ranking()[0].0 == "Ann" && loaded.get("Cy Young").games == 1);
+    }
+}
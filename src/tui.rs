@@ -0,0 +1,144 @@
+mod clock;
+mod key;
+mod term;
+mod view;
+
+pub use clock::*;
+pub use key::*;
+pub use term::*;
+pub use view::*;
+
+use crate::basic::{Cmd, Game, Notation, UndoRule};
+use std::io::{self, Read};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+// full screen game in terminal, return when user quits
+pub fn run(g: &mut Game) -> Result<(), String> {
+    let term = RawTerminal::enter()?;
+
+    // read input in another thread, so the clock keeps going
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = [0u8; 64];
+        loop {
+            match io::stdin().read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if tx.send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let size = g.board().lines();
+    let mut view = View::new(g);
+    let mut clock = Clock::new(g.next_player());
+    loop {
+        term.draw(&view.render(g, &clock))?;
+        let bytes = match rx.recv_timeout(Duration::from_secs(1)) {
+            Ok(bytes) => bytes,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        for key in parse_keys(&bytes) {
+            let player = g.next_player();
+            let ret = match key {
+                Key::Char('q') => return Ok(()),
+                Key::Up | Key::Down | Key::Left | Key::Right => {
+                    let (dx, dy) = match key {
+                        Key::Up => (0, -1),
+                        Key::Down => (0, 1),
+                        Key::Left => (-1, 0),
+                        _ => (1, 0),
+                    };
+                    view.move_cursor(dx, dy, size);
+                    Ok(())
+                }
+                Key::Enter | Key::Char(' ') => play(g, view.cursor),
+                Key::Click(column, row) => match view.point_at(g, column, row) {
+                    Some(point) => {
+                        view.cursor = point;
+                        play(g, point)
+                    }
+                    None => Ok(()),
+                },
+                Key::Char('p') => g.next(Cmd::Pass),
+                Key::Char('u') => undo(g),
+                Key::Char('y') => answer_undo(g, true),
+                Key::Char('n') => answer_undo(g, false),
+                Key::Char('r') => g.redo(0),
+                Key::Char(_) => Ok(()),
+            };
+            view.status = match (ret, g.last_autosave_error()) {
+                (Err(err), _) => err,
+                (Ok(()), Some(err)) => format!("autosave failed: {}", err),
+                (Ok(()), None) => match g.undo_request() {
+                    Some(asking) => format!(
+                        "{} asks to take back the last step, {} accept? (y/n)",
+                        asking,
+                        asking.another()
+                    ),
+                    None => String::new(),
+                },
+            };
+            if g.next_player() != player {
+                clock.switch(g.next_player());
+            }
+        }
+    }
+}
+
+fn play(g: &mut Game, (x, y): (usize, usize)) -> Result<(), String> {
+    let point = Notation::Sgf.format(x, y, g.board().lines())?;
+    g.next(Cmd::Step(point))
+}
+
+// take back at once with free undo, or else the player of the last step
+// asks and the opponent answers with y or n
+fn undo(g: &mut Game) -> Result<(), String> {
+    if g.undo_rule() == UndoRule::Free {
+        return g.undo();
+    }
+    g.request_undo(g.player_of_step(g.step_count()))
+}
+
+fn answer_undo(g: &mut Game, accept: bool) -> Result<(), String> {
+    let opponent = match g.undo_request() {
+        Some(asking) => asking.another(),
+        None => return Ok(()),
+    };
+    if accept {
+        g.accept_undo(opponent)
+    } else {
+        g.reject_undo(opponent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::BoardSize;
+
+    #[test]
+    fn ask_undo() {
+        let mut g = Game::new(BoardSize::Small);
+        g.next(Cmd::Step(String::from("ee"))).unwrap();
+        undo(&mut g).unwrap();
+        assert!(g.step_count() == 0);
+
+        g.set_undo_rule(UndoRule::Once);
+        g.next(Cmd::Step(String::from("ee"))).unwrap();
+        undo(&mut g).unwrap();
+        answer_undo(&mut g, false).unwrap();
+        assert!(g.step_count() == 1 && g.undo_request().is_none());
+        undo(&mut g).unwrap();
+        answer_undo(&mut g, true).unwrap();
+        assert!(g.step_count() == 0);
+        g.next(Cmd::Step(String::from("ee"))).unwrap();
+        assert!(undo(&mut g).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use crate::basic::Player;
+use std::time::{Duration, Instant};
+
+// time used by each player, counting for the player to move
+pub struct Clock {
+    black: Duration,
+    white: Duration,
+    running: Player,
+    since: Instant,
+}
+
+impl Clock {
+    pub fn new(running: Player) -> Clock {
+        Clock {
+            black: Duration::ZERO,
+            white: Duration::ZERO,
+            running,
+            since: Instant::now(),
+        }
+    }
+
+    // stop the running player and start the next one
+    pub fn switch(&mut self, next: Player) {
+        let elapsed = self.since.elapsed();
+        match self.running {
+            Player::Black => self.black += elapsed,
+            Player::White => self.white += elapsed,
+        }
+        self.running = next;
+        self.since = Instant::now();
+    }
+
+    pub fn used(&self, player: Player) -> Duration {
+        let used = match player {
+            Player::Black => self.black,
+            Player::White => self.white,
+        };
+        if player == self.running {
+            used + self.since.elapsed()
+        } else {
+            used
+        }
+    }
+}
+
+// like 01:05, or 1:01:05 if more than an hour
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format() {
+        assert!(format_duration(Duration::from_secs(65)) == "01:05");
+        assert!(format_duration(Duration::from_secs(3665)) == "1:01:05");
+    }
+}
@@ -0,0 +1,92 @@
+// input from terminal in raw mode
+#[derive(Clone, Debug, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Char(char),
+    // left button pressed at (column, row), 1 based
+    Click(usize, usize),
+}
+
+// parse bytes read from terminal, unknown sequences are dropped
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            0x1b if bytes.get(i + 1) == Some(&b'[') => {
+                let (key, len) = parse_csi(&bytes[i + 2..]);
+                if let Some(key) = key {
+                    keys.push(key);
+                }
+                i += len + 2;
+            }
+            b'\r' | b'\n' => {
+                keys.push(Key::Enter);
+                i += 1;
+            }
+            c if c.is_ascii() && !c.is_ascii_control() => {
+                keys.push(Key::Char(c as char));
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    keys
+}
+
+// parse control sequence after "ESC [", return the key and its length
+fn parse_csi(bytes: &[u8]) -> (Option<Key>, usize) {
+    match bytes.first() {
+        Some(b'A') => (Some(Key::Up), 1),
+        Some(b'B') => (Some(Key::Down), 1),
+        Some(b'C') => (Some(Key::Right), 1),
+        Some(b'D') => (Some(Key::Left), 1),
+        // sgr mouse: "<button;column;row" then M for press, m for release
+        Some(b'<') => {
+            let end = match bytes.iter().position(|b| *b == b'M' || *b == b'm') {
+                Some(end) => end,
+                None => return (None, bytes.len()),
+            };
+            let body = String::from_utf8_lossy(&bytes[1..end]).to_string();
+            let fields: Vec<usize> = body.split(';').filter_map(|f| f.parse().ok()).collect();
+            let key = match (fields.as_slice(), bytes[end]) {
+                ([0, column, row], b'M') => Some(Key::Click(*column, *row)),
+                _ => None,
+            };
+            (key, end + 1)
+        }
+        _ => {
+            // skip to the final byte of unknown sequence
+            let len = bytes
+                .iter()
+                .position(|b| (0x40..=0x7e).contains(b))
+                .map(|p| p + 1)
+                .unwrap_or(bytes.len());
+            (None, len)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_arrows_chars_and_mouse() {
+        let keys = parse_keys(b"\x1b[Aq\x1b[D\r\x1b[<0;12;5M\x1b[<0;12;5m\x1b[2~p");
+        assert!(
+            keys == vec![
+                Key::Up,
+                Key::Char('q'),
+                Key::Left,
+                Key::Enter,
+                Key::Click(12, 5),
+                Key::Char('p'),
+            ]
+        );
+    }
+}
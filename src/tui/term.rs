@@ -0,0 +1,44 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+// terminal in raw mode with alternate screen and mouse reporting,
+// everything is restored when dropped
+pub struct RawTerminal;
+
+impl RawTerminal {
+    pub fn enter() -> Result<RawTerminal, String> {
+        stty(&["raw", "-echo"])?;
+        // alternate screen, hide cursor, mouse press reporting in sgr mode
+        print!("\x1b[?1049h\x1b[?25l\x1b[?1000h\x1b[?1006h");
+        io::stdout().flush().map_err(|err| err.to_string())?;
+        Ok(RawTerminal)
+    }
+
+    // redraw the whole screen, lines need \r in raw mode
+    pub fn draw(&self, frame: &str) -> Result<(), String> {
+        let mut out = io::stdout();
+        write!(out, "\x1b[H\x1b[2J{}", frame.replace('\n', "\r\n"))
+            .and_then(|_| out.flush())
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        print!("\x1b[?1006l\x1b[?1000l\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&["sane"]);
+    }
+}
+
+fn stty(args: &[&str]) -> Result<(), String> {
+    let status = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .status()
+        .map_err(|err| format!("can not run stty: {}", err))?;
+    if !status.success() {
+        return Err(String::from("stty failed, is stdin a terminal?"));
+    }
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use crate::basic::*;
+use crate::tui::{format_duration, Clock};
+
+// lines above the column labels: title and a blank line
+const BOARD_TOP: usize = 2;
+const HELP: &str =
+    "arrows/click: move  enter/space: play  p: pass  u: undo  y/n: answer undo  r: redo  q: quit";
+
+// what the screen shows besides the game
+pub struct View {
+    pub cursor: (usize, usize),
+    pub status: String,
+}
+
+impl View {
+    // cursor starts at center of board
+    pub fn new(g: &Game) -> View {
+        let center = g.board().lines().div_ceil(2);
+        View {
+            cursor: (center, center),
+            status: String::new(),
+        }
+    }
+
+    pub fn move_cursor(&mut self, dx: isize, dy: isize, size: usize) {
+        let (x, y) = self.cursor;
+        let x = (x as isize + dx).clamp(1, size as isize) as usize;
+        let y = (y as isize + dy).clamp(1, size as isize) as usize;
+        self.cursor = (x, y);
+    }
+
+    // board point at terminal (column, row), 1 based
+    pub fn point_at(&self, g: &Game, column: usize, row: usize) -> Option<(usize, usize)> {
+        let size = g.board().lines();
+        let (indent, label_lines) = label_size(g);
+        let top = BOARD_TOP + label_lines + 1;
+        let left = indent + 2;
+        if row < top || column < left {
+            return None;
+        }
+        let (x, y) = ((column - left) / 2 + 1, row - top + 1);
+        if x > size || y > size {
+            return None;
+        }
+        Some((x, y))
+    }
+
+    pub fn render(&self, g: &Game, clock: &Clock) -> String {
+        let size = g.board().lines();
+        let notation = g.notation();
+        let (indent, label_lines) = label_size(g);
+        let board_width = indent * 2 + 1 + size * 2;
+
+        let mut left: Vec<String> = vec![];
+        let columns: Vec<String> = (1..=size)
+            .map(|x| format!("{:>w$}", notation.column_label(x, size), w = label_lines))
+            .collect();
+        let mut column_lines = vec![];
+        for line in 0..label_lines {
+            let mut text = format!("{:w$}", "", w = indent + 1);
+            for label in columns.iter() {
+                text.push_str(&label[line..line + 1]);
+                text.push(' ');
+            }
+            column_lines.push(text);
+        }
+        left.extend(column_lines.iter().cloned());
+        let note = g.note();
+        let last = g.last_step();
+        for y in 1..=size {
+            let label = notation.row_label(y, size);
+            let mut text = format!("{:>w$} ", label, w = indent);
+            for x in 1..=size {
+                text.push_str(&self.cell(g, &note, last, x, y));
+            }
+            text.push_str(&label);
+            left.push(text);
+        }
+        left.extend(column_lines);
+
+        let right = side_panel(g, clock, size + label_lines * 2);
+        let mut frame = title(g);
+        frame.push_str("\n\n");
+        for (i, line) in left.iter().enumerate() {
+            frame.push_str(line);
+            if let Some(panel) = right.get(i) {
+                // styled cells are longer than they look, board lines are full width
+                let width = if i < label_lines || i >= label_lines + size {
+                    line.len()
+                } else {
+                    board_width
+                };
+                frame.push_str(&" ".repeat(board_width + 2 - width));
+                frame.push_str(panel);
+            }
+            frame.push('\n');
+        }
+        frame.push('\n');
+        if self.status.is_empty() {
+            frame.push_str(HELP);
+        } else {
+            frame.push_str(&self.status);
+        }
+        frame
+    }
+
+    // 2 chars for each point, cursor is reversed and last step is underlined
+    fn cell(
+        &self,
+        g: &Game,
+        note: &Note,
+        last: Option<(usize, usize)>,
+        x: usize,
+        y: usize,
+    ) -> String {
+        let stone = g.board().at(x, y).unwrap();
+        let text = match (stone, note.mark_at(x, y)) {
+            (Stone::Empty, Some(mark)) => mark.symbol().chars().next().unwrap_or('.').to_string(),
+            (Stone::Empty, None) if g.board().is_star_position(x, y).unwrap() => String::from("+"),
+            (other, _) => other.to_string(),
+        };
+        let style = match (self.cursor == (x, y), last == Some((x, y))) {
+            (true, true) => "\x1b[7;1;4m",
+            (true, false) => "\x1b[7m",
+            (false, true) => "\x1b[1;4m",
+            (false, false) => return format!("{} ", text),
+        };
+        format!("{}{}\x1b[0m ", style, text)
+    }
+}
+
+// width of row labels and lines of column labels
+fn label_size(g: &Game) -> (usize, usize) {
+    let size = g.board().lines();
+    let notation = g.notation();
+    let indent = (1..=size)
+        .map(|y| notation.row_label(y, size).len())
+        .max()
+        .unwrap_or(1);
+    let lines = (1..=size)
+        .map(|x| notation.column_label(x, size).len())
+        .max()
+        .unwrap_or(1);
+    (indent, lines)
+}
+
+fn title(g: &Game) -> String {
+    let player = match g.next_player() {
+        Player::Black => "Black(x)",
+        Player::White => "White(o)",
+    };
+    let mut text = format!("rustgo  {} to play  [{}]", player, g.step_count());
+    let info = g.info();
+    if !info.black.is_empty() || !info.white.is_empty() {
+        text.push_str(format!("  {} vs {}", info.black, info.white).as_str());
+    }
+    text
+}
+
+// captures, clock and the latest steps, at most height lines
+fn side_panel(g: &Game, clock: &Clock, height: usize) -> Vec<String> {
+    let (black, white) = g.captures();
+    let main_time = std::time::Duration::try_from_secs_f64(g.info().time.unwrap_or_default())
+        .unwrap_or_default();
+    let time = |player: Player| {
+        let used = clock.used(player);
+        if main_time.is_zero() {
+            format_duration(used)
+        } else {
+            let left = main_time.saturating_sub(used);
+            format!("{} left {}", format_duration(used), format_duration(left))
+        }
+    };
+    let mut lines = vec![
+        format!("Black(x) captures {}", black),
+        format!("White(o) captures {}", white),
+        format!("Black(x) time {}", time(Player::Black)),
+        format!("White(o) time {}", time(Player::White)),
+        String::new(),
+        String::from("Steps:"),
+    ];
+    let size = g.board().lines();
+    let steps = g.steps();
+    let shown = height.saturating_sub(lines.len());
+    for (i, cmd) in steps
+        .iter()
+        .enumerate()
+        .skip(steps.len().saturating_sub(shown))
+    {
+        let player = g.player_of_step(i + 1).stone();
+        let text = match cmd {
+            Cmd::Step(p) => {
+                convert_point(p, Notation::Sgf, g.notation(), size).unwrap_or_else(|_| p.clone())
+            }
+            other => other.to_string(),
+        };
+        lines.push(format!("{:>4} {} {}", i + 1, player, text));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_and_click() {
+        let mut g = Game::new(BoardSize::Small);
+        g.next(Cmd::Step(String::from("cc"))).unwrap();
+        let view = View::new(&g);
+        let frame = view.render(&g, &Clock::new(Player::White));
+        let lines: Vec<&str> = frame.split('\n').collect();
+        assert!(lines[0] == "rustgo  White(o) to play  [1]");
+        assert!(lines[2].starts_with("  a b c d e f g h i    Black(x) captures 0"));
+        assert!(lines[5].starts_with("c . . \x1b[1;4mx\x1b[0m . + . + . . c"));
+        assert!(lines[7].contains("\x1b[7m+\x1b[0m"));
+        assert!(lines[8].ends_with("   1 x cc"));
+        assert!(lines[14] == HELP);
+        // the first line of board is terminal row 4, first cell is column 3
+        assert!(view.point_at(&g, 3, 4) == Some((1, 1)));
+        assert!(view.point_at(&g, 8, 6) == Some((3, 3)));
+        assert!(view.point_at(&g, 2, 6).is_none());
+        assert!(view.point_at(&g, 3, 13).is_none());
+    }
+}
@@ -1,3 +1,13 @@
+pub mod image;
+pub mod json;
 pub mod linked_tree;
+pub mod random;
+#[cfg(test)]
+pub mod temp_dir;
 
+pub use image::*;
+pub use json::*;
 pub use linked_tree::*;
+pub use random::*;
+#[cfg(test)]
+pub use temp_dir::*;
@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+
+// encoders of animated images, every frame is width * height palette
+// indexes in rows from the top, the palette has at most 256 colors
+
+pub fn encode_gif(
+    width: u16,
+    height: u16,
+    palette: &[[u8; 3]],
+    frames: &[Vec<u8>],
+    delay_ms: u32,
+) -> Vec<u8> {
+    // color table size is 2^bits
+    let bits = (1..=8).find(|b| 1 << b >= palette.len()).unwrap_or(8);
+    let mut data = b"GIF89a".to_vec();
+    data.extend_from_slice(&width.to_le_bytes());
+    data.extend_from_slice(&height.to_le_bytes());
+    data.push(0x80 | ((bits as u8 - 1) << 4) | (bits as u8 - 1));
+    data.extend_from_slice(&[0, 0]);
+    for i in 0..1 << bits {
+        data.extend_from_slice(palette.get(i).unwrap_or(&[0, 0, 0]));
+    }
+    // loop forever
+    data.extend_from_slice(&[0x21, 0xff, 0x0b]);
+    data.extend_from_slice(b"NETSCAPE2.0");
+    data.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+
+    // delay is in 1/100 seconds
+    let delay = (delay_ms / 10).min(u16::MAX as u32) as u16;
+    let min_code_size = bits.max(2) as u8;
+    for frame in frames {
+        data.extend_from_slice(&[0x21, 0xf9, 0x04, 0x00]);
+        data.extend_from_slice(&delay.to_le_bytes());
+        data.extend_from_slice(&[0x00, 0x00]);
+        data.push(0x2c);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.push(0x00);
+        data.push(min_code_size);
+        for block in lzw(frame, min_code_size).chunks(255) {
+            data.push(block.len() as u8);
+            data.extend_from_slice(block);
+        }
+        data.push(0x00);
+    }
+    data.push(0x3b);
+    data
+}
+
+pub fn encode_apng(
+    width: u32,
+    height: u32,
+    palette: &[[u8; 3]],
+    frames: &[Vec<u8>],
+    delay_ms: u32,
+) -> Vec<u8> {
+    let mut data = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    let mut header = vec![];
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits indexed color
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+    png_chunk(&mut data, b"IHDR", &header);
+    png_chunk(&mut data, b"PLTE", &palette.concat());
+    let mut control = (frames.len() as u32).to_be_bytes().to_vec();
+    // play forever
+    control.extend_from_slice(&0u32.to_be_bytes());
+    png_chunk(&mut data, b"acTL", &control);
+
+    let delay = delay_ms.min(u16::MAX as u32) as u16;
+    let mut sequence: u32 = 0;
+    for (i, frame) in frames.iter().enumerate() {
+        let mut fctl = sequence.to_be_bytes().to_vec();
+        fctl.extend_from_slice(&width.to_be_bytes());
+        fctl.extend_from_slice(&height.to_be_bytes());
+        fctl.extend_from_slice(&[0; 8]);
+        fctl.extend_from_slice(&delay.to_be_bytes());
+        fctl.extend_from_slice(&1000u16.to_be_bytes());
+        fctl.extend_from_slice(&[0, 0]);
+        png_chunk(&mut data, b"fcTL", &fctl);
+        sequence += 1;
+
+        // every row starts with filter type 0
+        let mut raw = Vec::with_capacity(frame.len() + height as usize);
+        for row in frame.chunks(width as usize) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+        let compressed = zlib(&raw);
+        // the first frame is the default image
+        if i == 0 {
+            png_chunk(&mut data, b"IDAT", &compressed);
+        } else {
+            let mut fdat = sequence.to_be_bytes().to_vec();
+            fdat.extend_from_slice(&compressed);
+            png_chunk(&mut data, b"fdAT", &fdat);
+            sequence += 1;
+        }
+    }
+    png_chunk(&mut data, b"IEND", &[]);
+    data
+}
+
+// bits are packed from the least significant one, as gif and deflate do
+struct BitWriter {
+    data: Vec<u8>,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter {
+            data: vec![],
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn write(&mut self, value: u32, bits: u32) {
+        self.buffer |= value << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.data.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // huffman codes are packed from the most significant bit
+    fn write_reversed(&mut self, code: u32, bits: u32) {
+        let reversed = code.reverse_bits() >> (32 - bits);
+        self.write(reversed, bits);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.data.push(self.buffer as u8);
+        }
+        self.data
+    }
+}
+
+fn lzw(indexes: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u32 << min_code_size;
+    let end = clear + 1;
+    let mut writer = BitWriter::new();
+    let mut table: HashMap<(u32, u8), u32> = HashMap::new();
+    let mut next = end + 1;
+    let mut size = min_code_size as u32 + 1;
+    writer.write(clear, size);
+    let mut prefix: Option<u32> = None;
+    for &index in indexes {
+        let code = match prefix {
+            None => {
+                prefix = Some(index as u32);
+                continue;
+            }
+            Some(code) => code,
+        };
+        if let Some(&found) = table.get(&(code, index)) {
+            prefix = Some(found);
+            continue;
+        }
+        writer.write(code, size);
+        if next < 4096 {
+            table.insert((code, index), next);
+            // the decoder is one code behind, so the size grows after next
+            // reaches the limit
+            if next == 1 << size {
+                size += 1;
+            }
+            next += 1;
+        } else {
+            writer.write(clear, size);
+            table.clear();
+            next = end + 1;
+            size = min_code_size as u32 + 1;
+        }
+        prefix = Some(index as u32);
+    }
+    if let Some(code) = prefix {
+        writer.write(code, size);
+    }
+    writer.write(end, size);
+    writer.finish()
+}
+
+fn png_chunk(data: &mut Vec<u8>, kind: &[u8; 4], content: &[u8]) {
+    data.extend_from_slice(&(content.len() as u32).to_be_bytes());
+    let start = data.len();
+    data.extend_from_slice(kind);
+    data.extend_from_slice(content);
+    let crc = crc32(&data[start..]);
+    data.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+const LENGTH_BASE: [u32; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u32; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const WINDOW: usize = 32768;
+const MAX_MATCH: usize = 258;
+
+// zlib stream of one deflate block with fixed huffman codes, matches are
+// found by the latest position with the same hash of 3 bytes, which is
+// enough for board images made of repeated rows
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    // final block with fixed codes
+    writer.write(1, 1);
+    writer.write(1, 2);
+    let hash = |i: usize| {
+        ((data[i] as usize) << 10 ^ (data[i + 1] as usize) << 5 ^ data[i + 2] as usize) & 0x7fff
+    };
+    let mut latest = vec![usize::MAX; 0x8000];
+    let mut i = 0;
+    while i < data.len() {
+        let mut length = 0;
+        let mut distance = 0;
+        if i + 3 <= data.len() {
+            let from = latest[hash(i)];
+            if from != usize::MAX && i - from <= WINDOW {
+                let limit = MAX_MATCH.min(data.len() - i);
+                length = (0..limit)
+                    .take_while(|&k| data[from + k] == data[i + k])
+                    .count();
+                distance = i - from;
+            }
+        }
+        let step = if length >= 3 {
+            write_match(&mut writer, length as u32, distance as u32);
+            length
+        } else {
+            write_literal(&mut writer, data[i] as u32);
+            1
+        };
+        for k in i..(i + step).min(data.len().saturating_sub(2)) {
+            latest[hash(k)] = k;
+        }
+        i += step;
+    }
+    write_literal(&mut writer, 256);
+
+    // deflate with 32k window, no dictionary
+    let mut stream = vec![0x78, 0x01];
+    stream.extend(writer.finish());
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn write_literal(writer: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => writer.write_reversed(0x30 + symbol, 8),
+        144..=255 => writer.write_reversed(0x190 + symbol - 144, 9),
+        256..=279 => writer.write_reversed(symbol - 256, 7),
+        _ => writer.write_reversed(0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: u32, distance: u32) {
+    let code = LENGTH_BASE
+        .iter()
+        .rposition(|&base| base <= length)
+        .unwrap();
+    write_literal(writer, 257 + code as u32);
+    writer.write(length - LENGTH_BASE[code], LENGTH_EXTRA[code]);
+    let code = DISTANCE_BASE
+        .iter()
+        .rposition(|&base| base <= distance)
+        .unwrap();
+    writer.write_reversed(code as u32, 5);
+    writer.write(distance - DISTANCE_BASE[code], DISTANCE_EXTRA[code]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // decode lzw codes of gif image data
+    fn unlzw(data: &[u8], min_code_size: u8) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let mut table: Vec<Vec<u8>> = vec![];
+        let mut size = min_code_size as usize + 1;
+        let mut out = vec![];
+        let mut previous: Option<Vec<u8>> = None;
+        let (mut buffer, mut count, mut bytes) = (0usize, 0, data.iter());
+        loop {
+            while count < size {
+                buffer |= (*bytes.next().unwrap() as usize) << count;
+                count += 8;
+            }
+            let code = buffer & ((1 << size) - 1);
+            buffer >>= size;
+            count -= size;
+            if code == clear {
+                table = (0..clear).map(|i| vec![i as u8]).collect();
+                table.push(vec![]);
+                table.push(vec![]);
+                size = min_code_size as usize + 1;
+                previous = None;
+                continue;
+            }
+            if code == clear + 1 {
+                return out;
+            }
+            let entry = match (table.get(code), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(p)) => [p.clone(), vec![p[0]]].concat(),
+                (None, None) => panic!("invalid code {}", code),
+            };
+            if let Some(p) = previous {
+                table.push([p, vec![entry[0]]].concat());
+                if table.len() == 1 << size && size < 12 {
+                    size += 1;
+                }
+            }
+            out.extend_from_slice(&entry);
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trip() {
+        let mut indexes = vec![];
+        for i in 0..20000 {
+            indexes.push(((i * 7 / 13) % 4) as u8);
+        }
+        assert!(unlzw(&lzw(&indexes, 2), 2) == indexes);
+        // noise fills the table and clears it
+        let mut seed: u32 = 1;
+        for _ in 0..50000 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            indexes.push((seed >> 16) as u8 % 4);
+        }
+        assert!(unlzw(&lzw(&indexes, 2), 2) == indexes);
+        let gif = encode_gif(
+            4,
+            4,
+            &[[0, 0, 0], [255, 255, 255]],
+            &[vec![1; 16], vec![0; 16]],
+            500,
+        );
+        assert!(gif.starts_with(b"GIF89a\x04\x00\x04\x00"));
+        assert!(gif.ends_with(&[0x00, 0x3b]));
+        // delay of 50/100 seconds
+        assert!(
+            gif.windows(6)
+                .filter(|w| w == &[0x21, 0xf9, 0x04, 0x00, 50, 0])
+                .count()
+                == 2
+        );
+    }
+
+    #[test]
+    fn png_checksums() {
+        assert!(crc32(b"123456789") == 0xcbf4_3926);
+        assert!(adler32(b"Wikipedia") == 0x11e6_0398);
+        let apng = encode_apng(
+            4,
+            4,
+            &[[0, 0, 0], [255, 255, 255]],
+            &[vec![1; 16], vec![0; 16]],
+            500,
+        );
+        assert!(apng.starts_with(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR"));
+        assert!(apng.windows(4).filter(|w| w == b"fcTL").count() == 2);
+        assert!(apng.windows(4).filter(|w| w == b"fdAT").count() == 1);
+    }
+}
@@ -7,6 +7,7 @@ pub trait LinkedTreeOperation<T> {
     fn size(&self) -> usize;
     fn deepth(&self) -> usize;
     fn val(&self) -> T;
+    fn set_val(&self, val: T);
     fn parent(&self) -> Option<Self>
     where
         Self: Sized;
@@ -14,6 +15,7 @@ pub trait LinkedTreeOperation<T> {
     where
         Self: Sized;
     fn child_len(&self) -> usize;
+    fn index(&self) -> usize;
     fn ptr(&self) -> Self;
     fn list_parents(&self) -> Vec<T>;
 }
@@ -58,8 +60,8 @@ impl<T: Clone> LinkedTreeOperation<T> for LinkedTree<T> {
     }
     fn add_child(&self, val: T) -> Self {
         let mut node = TreeNode::new(val);
-        node.deepth = Rc::clone(&self).borrow_mut().deepth + 1;
-        node.parent = Some(Rc::clone(&self));
+        node.deepth = Rc::clone(self).borrow_mut().deepth + 1;
+        node.parent = Some(Rc::clone(self));
 
         let rc_node = Rc::new(RefCell::new(node));
         self.borrow_mut().inc_size();
@@ -67,7 +69,10 @@ impl<T: Clone> LinkedTreeOperation<T> for LinkedTree<T> {
         rc_node
     }
     fn val(&self) -> T {
-        Rc::clone(&self).borrow().val.clone()
+        Rc::clone(self).borrow().val.clone()
+    }
+    fn set_val(&self, val: T) {
+        self.borrow_mut().val = val;
     }
     fn size(&self) -> usize {
         self.borrow().size
@@ -76,10 +81,7 @@ impl<T: Clone> LinkedTreeOperation<T> for LinkedTree<T> {
         self.borrow().deepth
     }
     fn parent(&self) -> Option<Self> {
-        match &self.borrow().parent {
-            None => None,
-            Some(parent) => Some(parent.clone()),
-        }
+        self.borrow().parent.as_ref().map(|parent| parent.clone())
     }
     fn child_len(&self) -> usize {
         self.borrow().children.len()
@@ -90,8 +92,20 @@ impl<T: Clone> LinkedTreeOperation<T> for LinkedTree<T> {
         }
         Some(self.borrow().children[n].clone())
     }
+    // position in children of parent, root is 0
+    fn index(&self) -> usize {
+        match self.parent() {
+            None => 0,
+            Some(parent) => parent
+                .borrow()
+                .children
+                .iter()
+                .position(|c| Rc::ptr_eq(c, self))
+                .unwrap(),
+        }
+    }
     fn ptr(&self) -> Self {
-        Rc::clone(&self)
+        Rc::clone(self)
     }
     fn list_parents(&self) -> Vec<T> {
         match &self.borrow().parent {
@@ -99,7 +113,7 @@ impl<T: Clone> LinkedTreeOperation<T> for LinkedTree<T> {
             Some(parent) => {
                 let mut ret = parent.list_parents();
                 ret.push(self.val());
-                return ret;
+                ret
             }
         }
     }
@@ -127,6 +141,9 @@ mod tests {
         assert!(n2.val().eq("2"));
         assert!(n3.val().eq("3"));
         assert!(n4.val().eq("4"));
+        n4.set_val(String::from("5"));
+        assert!(n4.val().eq("5"));
+        n4.set_val(String::from("4"));
         assert!(n1.child_len() == 2);
         assert!(n2.child_len() == 1);
         assert!(n3.child_len() == 0);
@@ -135,6 +152,10 @@ mod tests {
         assert!(n1.child(1).unwrap().val().eq("3"));
         assert!(n2.child(0).unwrap().val().eq("4"));
         assert!(n1.child(2).is_none());
+        assert!(n1.index() == 0);
+        assert!(n2.index() == 0);
+        assert!(n3.index() == 1);
+        assert!(n4.index() == 0);
         assert!(n3.child(0).is_none());
         assert!(n1.parent().is_none());
         assert!(n2.parent().unwrap().val().eq("1"));
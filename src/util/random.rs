@@ -0,0 +1,81 @@
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+
+// xorshift64* generator, the same seed gives the same numbers on every
+// platform, so games and records made with it can be made again
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // the state must not be 0
+        let state = seed ^ 0x9e37_79b9_7f4a_7c15;
+        Rng {
+            state: if state == 0 { 1 } else { state },
+        }
+    }
+
+    // seeded by the clock, for games which need not be made again
+    pub fn from_time() -> Rng {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Rng::new(nanos as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // in 0..n, n must not be 0
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// 128 bits in hex for secrets like seat tokens, which must not be guessed.
+// they are read from /dev/urandom, or taken from the keys of RandomState
+// which std gets from the os where there is no such file
+pub fn secret_token() -> String {
+    let mut bytes = [0u8; 16];
+    let read = File::open("/dev/urandom").and_then(|mut f| f.read_exact(&mut bytes));
+    if read.is_err() {
+        for chunk in bytes.chunks_mut(8) {
+            let n = RandomState::new().build_hasher().finish();
+            chunk.copy_from_slice(&n.to_le_bytes());
+        }
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+        for _ in 0..100 {
+            assert!(a.next_u64() == b.next_u64());
+            let f = a.next_f64();
+            assert!((0.0..1.0).contains(&f) && f == b.next_f64());
+            assert!(a.below(3) < 3 && b.below(3) < 3);
+        }
+        assert!(Rng::new(7).next_u64() != Rng::new(8).next_u64());
+        let token = secret_token();
+        assert!(token.len() == 32 && token != secret_token());
+    }
+}
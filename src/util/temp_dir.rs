@@ -0,0 +1,39 @@
+use std::fs;
+use std::path::Path;
+
+// empty directory for a test, which is removed when it is dropped, also
+// when the test fails
+pub struct TempDir {
+    path: String,
+}
+
+impl TempDir {
+    // rustgo-<name>-<pid> in the temporary directory, removed first if it
+    // is left by a test killed before
+    pub fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("rustgo-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir {
+            path: path.to_string_lossy().to_string(),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // path of a file in the directory
+    pub fn file(&self, name: &str) -> String {
+        Path::new(&self.path)
+            .join(name)
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}